@@ -0,0 +1 @@
+drop table channel_settings;
\ No newline at end of file
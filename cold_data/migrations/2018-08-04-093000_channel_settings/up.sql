@@ -0,0 +1,6 @@
+create table channel_settings(
+    channel VARCHAR(60) NOT NULL,
+    command_prefix VARCHAR(10) NOT NULL DEFAULT '#',
+
+    primary key (channel)
+);
\ No newline at end of file
@@ -0,0 +1,2 @@
+alter table commands drop column permission;
+alter table channel_settings drop column deny_reply;
\ No newline at end of file
@@ -0,0 +1,2 @@
+alter table commands add column permission INT NOT NULL DEFAULT 0;
+alter table channel_settings add column deny_reply BOOLEAN NOT NULL DEFAULT TRUE;
\ No newline at end of file
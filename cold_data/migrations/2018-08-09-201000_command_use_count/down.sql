@@ -0,0 +1 @@
+alter table commands drop column use_count;
\ No newline at end of file
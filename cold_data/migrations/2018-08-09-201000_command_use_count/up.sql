@@ -0,0 +1 @@
+alter table commands add column use_count INT NOT NULL DEFAULT 0;
\ No newline at end of file
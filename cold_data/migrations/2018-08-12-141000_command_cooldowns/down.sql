@@ -0,0 +1,3 @@
+alter table commands drop column global_cooldown;
+alter table commands drop column user_cooldown;
+alter table commands drop column cooldown_mod_exempt;
\ No newline at end of file
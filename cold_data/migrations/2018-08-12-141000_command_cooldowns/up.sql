@@ -0,0 +1,3 @@
+alter table commands add column global_cooldown INT NOT NULL DEFAULT 0;
+alter table commands add column user_cooldown INT NOT NULL DEFAULT 0;
+alter table commands add column cooldown_mod_exempt BOOLEAN NOT NULL DEFAULT TRUE;
\ No newline at end of file
@@ -0,0 +1 @@
+alter table commands drop column match_mode;
\ No newline at end of file
@@ -0,0 +1 @@
+alter table commands add column match_mode VARCHAR(10) NOT NULL DEFAULT 'exact';
\ No newline at end of file
@@ -0,0 +1,2 @@
+alter table commands drop column script;
+drop table script_store;
\ No newline at end of file
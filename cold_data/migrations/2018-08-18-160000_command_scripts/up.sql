@@ -0,0 +1,9 @@
+alter table commands add column script TEXT NULL;
+
+create table script_store(
+    channel VARCHAR(60) NOT NULL,
+    store_key VARCHAR(100) NOT NULL,
+    store_value VARCHAR(500) NOT NULL,
+
+    primary key (channel, store_key)
+);
\ No newline at end of file
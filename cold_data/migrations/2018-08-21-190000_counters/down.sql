@@ -0,0 +1 @@
+drop table counters;
\ No newline at end of file
@@ -0,0 +1,7 @@
+create table counters(
+    channel VARCHAR(60) NOT NULL,
+    name VARCHAR(60) NOT NULL,
+    value INT NOT NULL DEFAULT 0,
+
+    primary key (channel, name)
+);
\ No newline at end of file
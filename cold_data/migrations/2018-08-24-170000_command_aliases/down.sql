@@ -0,0 +1 @@
+drop table command_aliases;
\ No newline at end of file
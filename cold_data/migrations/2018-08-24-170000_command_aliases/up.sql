@@ -0,0 +1,10 @@
+create table command_aliases(
+    channel VARCHAR(60) NOT NULL,
+    alias VARCHAR(200) NOT NULL,
+    match_expr VARCHAR(200) NOT NULL,
+
+    primary key (channel, alias),
+    foreign key (channel, match_expr) references commands(channel, match_expr)
+        on delete cascade
+        on update cascade
+);
\ No newline at end of file
@@ -0,0 +1 @@
+drop table timers;
\ No newline at end of file
@@ -0,0 +1,10 @@
+create table timers(
+    channel VARCHAR(60) NOT NULL,
+    name VARCHAR(60) NOT NULL,
+    message VARCHAR(500) NOT NULL,
+    interval_seconds INT NOT NULL,
+    min_messages INT NOT NULL DEFAULT 0,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+
+    primary key (channel, name)
+);
\ No newline at end of file
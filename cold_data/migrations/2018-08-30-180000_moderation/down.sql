@@ -0,0 +1,2 @@
+drop table moderation_terms;
+drop table moderation_rules;
\ No newline at end of file
@@ -0,0 +1,20 @@
+create table moderation_rules(
+    channel VARCHAR(60) NOT NULL,
+    rule VARCHAR(20) NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    threshold INT NOT NULL,
+    exempt_level INT NOT NULL DEFAULT 3,
+    action VARCHAR(20) NOT NULL DEFAULT 'warn',
+    max_action VARCHAR(20) NOT NULL DEFAULT 'timeout',
+    timeout_seconds INT NOT NULL DEFAULT 600,
+
+    primary key (channel, rule)
+);
+
+create table moderation_terms(
+    channel VARCHAR(60) NOT NULL,
+    list VARCHAR(20) NOT NULL,
+    term VARCHAR(200) NOT NULL,
+
+    primary key (channel, list, term)
+);
\ No newline at end of file
@@ -0,0 +1,2 @@
+alter table channel_settings drop column permit_seconds;
+alter table channel_settings drop column permit_links;
\ No newline at end of file
@@ -0,0 +1,2 @@
+alter table channel_settings add column permit_seconds INT NOT NULL DEFAULT 60;
+alter table channel_settings add column permit_links INT NOT NULL DEFAULT 1;
\ No newline at end of file
@@ -0,0 +1 @@
+drop table quotes;
\ No newline at end of file
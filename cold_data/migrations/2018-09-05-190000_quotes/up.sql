@@ -0,0 +1,10 @@
+create table quotes(
+    channel VARCHAR(60) NOT NULL,
+    number INT NOT NULL,
+    quote VARCHAR(400) NOT NULL,
+    added_by VARCHAR(60) NOT NULL,
+    added_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    game VARCHAR(100) NULL,
+
+    primary key (channel, number)
+);
\ No newline at end of file
@@ -0,0 +1,21 @@
+create table polls(
+    id INT NOT NULL AUTO_INCREMENT,
+    channel VARCHAR(60) NOT NULL,
+    question VARCHAR(200) NOT NULL,
+    started_by VARCHAR(60) NOT NULL,
+    duration_seconds INT NOT NULL,
+    ended_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    primary key (id)
+);
+
+create table poll_options(
+    poll_id INT NOT NULL,
+    position INT NOT NULL,
+    option_text VARCHAR(100) NOT NULL,
+    votes INT NOT NULL DEFAULT 0,
+
+    primary key (poll_id, position),
+    foreign key (poll_id) references polls(id)
+        on delete cascade
+);
\ No newline at end of file
@@ -0,0 +1,3 @@
+drop table raffle_winners;
+drop table raffle_entries;
+drop table raffles;
\ No newline at end of file
@@ -0,0 +1,35 @@
+create table raffles(
+    id INT NOT NULL AUTO_INCREMENT,
+    channel VARCHAR(60) NOT NULL,
+    keyword VARCHAR(50) NOT NULL,
+    sub_luck INT NOT NULL DEFAULT 1,
+    seed VARCHAR(64) NOT NULL,
+    opened_by VARCHAR(60) NOT NULL,
+    opened_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    closed_at TIMESTAMP NULL,
+
+    primary key (id)
+);
+
+create table raffle_entries(
+    raffle_id INT NOT NULL,
+    user VARCHAR(60) NOT NULL,
+    position INT NOT NULL,
+    tickets INT NOT NULL DEFAULT 1,
+
+    primary key (raffle_id, user),
+    foreign key (raffle_id) references raffles(id)
+        on delete cascade
+);
+
+create table raffle_winners(
+    raffle_id INT NOT NULL,
+    draw INT NOT NULL,
+    user VARCHAR(60) NOT NULL,
+    drawn_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    claimed BOOLEAN NOT NULL DEFAULT FALSE,
+
+    primary key (raffle_id, draw),
+    foreign key (raffle_id) references raffles(id)
+        on delete cascade
+);
\ No newline at end of file
@@ -0,0 +1,7 @@
+alter table raffles drop column cost;
+
+alter table channel_settings drop column points_sub_multiplier;
+alter table channel_settings drop column points_chat;
+alter table channel_settings drop column points_presence;
+
+drop table points;
\ No newline at end of file
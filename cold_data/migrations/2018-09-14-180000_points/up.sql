@@ -0,0 +1,13 @@
+create table points(
+    channel VARCHAR(60) NOT NULL,
+    user VARCHAR(60) NOT NULL,
+    balance INT NOT NULL DEFAULT 0,
+
+    primary key (channel, user)
+);
+
+alter table channel_settings add column points_presence INT NOT NULL DEFAULT 1;
+alter table channel_settings add column points_chat INT NOT NULL DEFAULT 1;
+alter table channel_settings add column points_sub_multiplier INT NOT NULL DEFAULT 2;
+
+alter table raffles add column cost INT NOT NULL DEFAULT 0;
\ No newline at end of file
@@ -0,0 +1,2 @@
+drop table queue_entries;
+drop table queues;
\ No newline at end of file
@@ -0,0 +1,18 @@
+create table queues(
+    channel VARCHAR(60) NOT NULL,
+    open BOOLEAN NOT NULL DEFAULT FALSE,
+    sub_priority BOOLEAN NOT NULL DEFAULT FALSE,
+
+    primary key (channel)
+);
+
+create table queue_entries(
+    id INT NOT NULL AUTO_INCREMENT,
+    channel VARCHAR(60) NOT NULL,
+    user VARCHAR(60) NOT NULL,
+    subscriber BOOLEAN NOT NULL DEFAULT FALSE,
+    joined_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    primary key (id),
+    unique (channel, user)
+);
\ No newline at end of file
@@ -0,0 +1,2 @@
+drop table command_responses;
+alter table commands drop column response_mode;
\ No newline at end of file
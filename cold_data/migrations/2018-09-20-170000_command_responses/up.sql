@@ -0,0 +1,14 @@
+alter table commands add column response_mode VARCHAR(10) NOT NULL DEFAULT 'random';
+
+create table command_responses(
+    id INT NOT NULL AUTO_INCREMENT,
+    channel VARCHAR(60) NOT NULL,
+    match_expr VARCHAR(200) NOT NULL,
+    response VARCHAR(200) NOT NULL,
+    weight INT NOT NULL DEFAULT 1,
+
+    primary key (id),
+    foreign key (channel, match_expr) references commands(channel, match_expr)
+        on delete cascade
+        on update cascade
+);
\ No newline at end of file
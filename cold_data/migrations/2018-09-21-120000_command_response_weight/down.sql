@@ -0,0 +1 @@
+alter table commands drop column response_weight;
\ No newline at end of file
@@ -0,0 +1 @@
+alter table commands add column response_weight INT NOT NULL DEFAULT 1;
\ No newline at end of file
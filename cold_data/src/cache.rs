@@ -6,9 +6,43 @@ use std::ops::Deref;
 use std::sync::Arc;
 use std::sync::RwLock;
 
-use super::{models::Command, schema::commands::dsl::*, DbConnectionPool, ListCommands};
+use super::{
+    matcher::{MatchMode, Matcher},
+    models::{
+        Alias, ChannelSettings, Command, CommandResponse, Counter, ModerationRule, ModerationTerm,
+    },
+    schema::channel_settings, schema::command_aliases, schema::command_responses,
+    schema::commands::dsl::*, schema::counters,
+    schema::moderation_rules, schema::moderation_terms, DbConnectionPool, ListCommands,
+};
+use diesel::mysql::MysqlConnection;
+use diesel::r2d2::{ConnectionManager, Pool};
 use std::collections::HashMap;
 
+/// Prefix used by channels that did not choose their own
+pub const DEFAULT_COMMAND_PREFIX: &str = "#";
+
+/// Seconds a permit lasts in channels that did not choose
+pub const DEFAULT_PERMIT_SECONDS: i32 = 60;
+
+/// Messages with links a permit allows in channels that did not choose
+pub const DEFAULT_PERMIT_LINKS: i32 = 1;
+
+/// Points earned at every payout while live in channels that did not choose
+pub const DEFAULT_POINTS_PRESENCE: i32 = 1;
+
+/// Points earned at every payout for chatting in channels that did not choose
+pub const DEFAULT_POINTS_CHAT: i32 = 1;
+
+/// Multiplier of the points earned by subscribers in channels that did not choose
+pub const DEFAULT_POINTS_SUB_MULTIPLIER: i32 = 2;
+
+/// Longest keyword of a command or alias in bytes, the database columns hold 200 characters
+const MAX_KEYWORD_BYTES: usize = 4 * 200;
+
+/// Channel of the commands available in every channel, it can't be joined since it is not a valid name
+pub const GLOBAL_CHANNEL: &str = "*";
+
 #[derive(Debug, Fail)]
 pub enum CacheError {
     #[fail(display = "Could not acquire writer {}", 0)]
@@ -25,6 +59,13 @@ impl CommandCache {
         CommandCache {
             inner: Arc::new(RwLock::new(CommandCacheInner {
                 commands: Vec::new(),
+                channel_settings: HashMap::new(),
+                matchers: Vec::new(),
+                counters: HashMap::new(),
+                aliases: HashMap::new(),
+                responses: HashMap::new(),
+                moderation_rules: HashMap::new(),
+                moderation_terms: HashMap::new(),
             })),
         }
     }
@@ -41,7 +82,7 @@ impl CommandCache {
                 .map_err(|err| CacheError::WriterError(format!("{:?}", err)))?;
             if let Ok(result) = result {
                 if let Ok(result) = result {
-                    writer.commands = result;
+                    writer.set_commands(result);
                 }
             }
 
@@ -50,15 +91,96 @@ impl CommandCache {
     }
 
     pub fn update(&self, db: &DbConnectionPool) -> Result<(), Error> {
-        let connection = db.connection.get()?;
+        self.load(&db.connection)
+    }
+
+    /// Load everything cached from the database
+    pub fn load(&self, pool: &Pool<ConnectionManager<MysqlConnection>>) -> Result<(), Error> {
+        let connection = pool.get()?;
 
         let result = commands.load::<Command>(&connection)?;
+        let settings = channel_settings::table.load::<ChannelSettings>(&connection)?;
+        let all_counters = counters::table.load::<Counter>(&connection)?;
+        let all_aliases = command_aliases::table.load::<Alias>(&connection)?;
+        let all_responses = command_responses::table
+            .order(command_responses::id)
+            .load::<CommandResponse>(&connection)?;
+        let rules = moderation_rules::table.load::<ModerationRule>(&connection)?;
+        let terms = moderation_terms::table.load::<ModerationTerm>(&connection)?;
 
         let mut writer = self
             .write()
             .map_err(|err| CacheError::WriterError(format!("{:?}", err)))?;
 
-        writer.commands = result;
+        writer.set_commands(result);
+        writer.channel_settings = settings
+            .into_iter()
+            .map(|settings| (settings.channel.clone(), settings))
+            .collect();
+
+        writer.counters.clear();
+        for counter in all_counters {
+            writer
+                .counters
+                .entry(counter.channel)
+                .or_insert_with(HashMap::new)
+                .insert(counter.name, counter.value);
+        }
+
+        writer.aliases = all_aliases
+            .into_iter()
+            .map(|alias| ((alias.channel, alias.alias), alias.match_expr))
+            .collect();
+
+        writer.responses.clear();
+        for variant in all_responses {
+            writer
+                .responses
+                .entry((variant.channel.clone(), variant.match_expr.clone()))
+                .or_insert_with(Vec::new)
+                .push(variant);
+        }
+
+        writer.moderation_rules.clear();
+        for rule in rules {
+            writer
+                .moderation_rules
+                .entry(rule.channel.clone())
+                .or_insert_with(Vec::new)
+                .push(rule);
+        }
+
+        writer.moderation_terms.clear();
+        for term in terms {
+            writer
+                .moderation_terms
+                .entry((term.channel, term.list))
+                .or_insert_with(Vec::new)
+                .push(term.term);
+        }
+
+        Ok(())
+    }
+
+    /// Update a single counter without reloading everything, `None` removes it
+    pub fn set_counter(
+        &self,
+        channel_name: &str,
+        counter_name: &str,
+        value: Option<i32>,
+    ) -> Result<(), Error> {
+        let mut writer = self
+            .write()
+            .map_err(|err| CacheError::WriterError(format!("{:?}", err)))?;
+
+        let channel_counters = writer
+            .counters
+            .entry(channel_name.to_owned())
+            .or_insert_with(HashMap::new);
+        match value {
+            Some(value) => channel_counters.insert(counter_name.to_owned(), value),
+            None => channel_counters.remove(counter_name),
+        };
 
         Ok(())
     }
@@ -74,4 +196,228 @@ impl Deref for CommandCache {
 
 pub struct CommandCacheInner {
     pub commands: Vec<Command>,
+    pub channel_settings: HashMap<String, ChannelSettings>,
+    /// Compiled expressions of the commands that are not exact, by index in `commands`
+    matchers: Vec<(usize, Matcher)>,
+    /// Value of every counter, by channel then name
+    counters: HashMap<String, HashMap<String, i32>>,
+    /// Match expression used by each alias, by channel and alias
+    aliases: HashMap<(String, String), String>,
+    /// Response variants of the commands that have some, by channel and match expression
+    responses: HashMap<(String, String), Vec<CommandResponse>>,
+    /// Spam filter rules configured in each channel
+    moderation_rules: HashMap<String, Vec<ModerationRule>>,
+    /// Terms of the filter lists, by channel and list
+    moderation_terms: HashMap<(String, String), Vec<String>>,
+}
+
+impl CommandCacheInner {
+    /// Replace the cached commands, compiling their match expressions once
+    fn set_commands(&mut self, new_commands: Vec<Command>) {
+        self.matchers = new_commands
+            .iter()
+            .enumerate()
+            .filter_map(|(index, cmd)| match Matcher::compile(cmd.match_mode(), &cmd.match_expr) {
+                Ok(matcher) => matcher.map(|matcher| (index, matcher)),
+                Err(err) => {
+                    println!("Skipping command {} in {}: {}", cmd.match_expr, cmd.channel, err);
+                    None
+                }
+            })
+            .collect();
+        self.commands = new_commands;
+    }
+
+    /// Find the command stored for `keyword` in a channel, whatever its match mode
+    pub fn find(&self, channel_name: &str, keyword: &str) -> Option<&Command> {
+        self.commands
+            .iter()
+            .find(|cmd| cmd.channel == channel_name && cmd.match_expr == keyword)
+    }
+
+    /// Channel and keyword of every exact command and alias, the ones used after the prefix
+    pub fn keywords(&self) -> Vec<(&str, &str)> {
+        self.commands
+            .iter()
+            .filter(|cmd| cmd.match_mode() == MatchMode::Exact)
+            .map(|cmd| (cmd.channel.as_str(), cmd.match_expr.as_str()))
+            .chain(
+                self.aliases
+                    .keys()
+                    .map(|(channel_name, alias)| (channel_name.as_str(), alias.as_str())),
+            )
+            .collect()
+    }
+
+    /// Response variants of a command in the order they were added, without its own response
+    pub fn responses(&self, cmd: &Command) -> &[CommandResponse] {
+        self.responses
+            .get(&(cmd.channel.clone(), cmd.match_expr.clone()))
+            .map_or(&[][..], |variants| variants.as_slice())
+    }
+
+    /// Find the command an alias of a channel uses
+    pub fn find_alias(&self, channel_name: &str, alias: &str) -> Option<&Command> {
+        self.aliases
+            .get(&(channel_name.to_owned(), alias.to_owned()))
+            .and_then(|target| self.find(channel_name, target))
+    }
+
+    /// Find the command used for `keyword` in a channel, directly or by alias, or the global one
+    pub fn resolve(&self, channel_name: &str, keyword: &str) -> Option<&Command> {
+        self.lookup(channel_name, keyword).map(|(cmd, _)| cmd)
+    }
+
+    /// Find the exact command used as `prefix` + `keyword` in a channel, or the global one
+    /// Aliases are always exact, whatever the match mode of their command.
+    pub fn find_exact(&self, channel_name: &str, keyword: &str) -> Option<&Command> {
+        self.lookup(channel_name, keyword)
+            .filter(|(cmd, aliased)| *aliased || cmd.match_mode() == MatchMode::Exact)
+            .map(|(cmd, _)| cmd)
+    }
+
+    /// Find the longest exact command starting a message, with the keyword it was found with
+    /// Keywords set with quotes can hold spaces, so every run of leading words is tried.
+    pub fn find_exact_prefix<'a>(
+        &self,
+        channel_name: &str,
+        message: &'a str,
+    ) -> Option<(&Command, &'a str)> {
+        let mut ends: Vec<usize> = message
+            .char_indices()
+            .filter(|(_, c)| c.is_whitespace())
+            .map(|(index, _)| index)
+            .take_while(|index| *index <= MAX_KEYWORD_BYTES)
+            .collect();
+        ends.push(message.len());
+
+        ends.into_iter()
+            .rev()
+            .filter_map(|end| {
+                let keyword = &message[..end];
+                self.find_exact(channel_name, keyword).map(|cmd| (cmd, keyword))
+            })
+            .next()
+    }
+
+    /// Command used for `keyword` and whether it was found by alias
+    /// The channel's commands and aliases come before global ones.
+    fn lookup(&self, channel_name: &str, keyword: &str) -> Option<(&Command, bool)> {
+        [channel_name, GLOBAL_CHANNEL]
+            .iter()
+            .filter_map(|scope| {
+                self.find(scope, keyword)
+                    .map(|cmd| (cmd, false))
+                    .or_else(|| self.find_alias(scope, keyword).map(|cmd| (cmd, true)))
+            })
+            .next()
+    }
+
+    /// Find the first pattern command matching a whole message, with the groups it captured
+    /// Commands of the channel are tried first, then global commands it did not override.
+    pub fn find_pattern(
+        &self,
+        channel_name: &str,
+        message: &str,
+    ) -> Option<(&Command, HashMap<String, String>)> {
+        let patterns = || {
+            self.matchers
+                .iter()
+                .map(move |(index, matcher)| (&self.commands[*index], matcher))
+        };
+
+        let own = patterns().filter(|(cmd, _)| cmd.channel == channel_name);
+        let global = patterns().filter(|(cmd, _)| {
+            cmd.channel == GLOBAL_CHANNEL && self.find(channel_name, &cmd.match_expr).is_none()
+        });
+
+        own.chain(global)
+            .filter_map(|(cmd, matcher)| matcher.captures(message).map(|groups| (cmd, groups)))
+            .next()
+    }
+
+    /// Triggers usable in a channel, its own commands then the global ones it did not override
+    pub fn available(&self, channel_name: &str) -> Vec<&Command> {
+        let own = self.commands.iter().filter(|cmd| cmd.channel == channel_name);
+        let global = self.commands.iter().filter(|cmd| {
+            cmd.channel == GLOBAL_CHANNEL && self.find(channel_name, &cmd.match_expr).is_none()
+        });
+
+        own.chain(global).collect()
+    }
+
+    /// Prefix that starts commands in a channel
+    pub fn command_prefix(&self, channel_name: &str) -> &str {
+        self.channel_settings
+            .get(channel_name)
+            .map_or(DEFAULT_COMMAND_PREFIX, |settings| settings.command_prefix.as_str())
+    }
+
+    /// Whether users are told they are not allowed to use a command in a channel
+    pub fn deny_reply(&self, channel_name: &str) -> bool {
+        self.channel_settings
+            .get(channel_name)
+            .map_or(true, |settings| settings.deny_reply)
+    }
+
+    /// Seconds a permit lasts in a channel and how many messages with links it allows
+    pub fn permit_settings(&self, channel_name: &str) -> (i32, i32) {
+        self.channel_settings
+            .get(channel_name)
+            .map_or((DEFAULT_PERMIT_SECONDS, DEFAULT_PERMIT_LINKS), |settings| {
+                (settings.permit_seconds, settings.permit_links)
+            })
+    }
+
+    /// Points earned in a channel for being present and for chatting, and the subscriber multiplier
+    pub fn point_rates(&self, channel_name: &str) -> (i32, i32, i32) {
+        self.channel_settings.get(channel_name).map_or(
+            (
+                DEFAULT_POINTS_PRESENCE,
+                DEFAULT_POINTS_CHAT,
+                DEFAULT_POINTS_SUB_MULTIPLIER,
+            ),
+            |settings| {
+                (
+                    settings.points_presence,
+                    settings.points_chat,
+                    settings.points_sub_multiplier,
+                )
+            },
+        )
+    }
+
+    /// Value of a counter in a channel, `None` if it does not exist
+    pub fn counter(&self, channel_name: &str, counter_name: &str) -> Option<i32> {
+        self.counters
+            .get(channel_name)
+            .and_then(|channel_counters| channel_counters.get(counter_name))
+            .cloned()
+    }
+
+    /// Every counter of a channel, by name
+    pub fn channel_counters(&self, channel_name: &str) -> HashMap<String, i32> {
+        self.counters.get(channel_name).cloned().unwrap_or_default()
+    }
+
+    /// Spam filter rules configured in a channel
+    pub fn moderation_rules(&self, channel_name: &str) -> &[ModerationRule] {
+        self.moderation_rules
+            .get(channel_name)
+            .map_or(&[][..], |rules| rules.as_slice())
+    }
+
+    /// Settings of one spam filter rule in a channel
+    pub fn moderation_rule(&self, channel_name: &str, rule_name: &str) -> Option<&ModerationRule> {
+        self.moderation_rules(channel_name)
+            .iter()
+            .find(|rule| rule.rule == rule_name)
+    }
+
+    /// Terms of a filter list of a channel
+    pub fn moderation_terms(&self, channel_name: &str, list_name: &str) -> &[String] {
+        self.moderation_terms
+            .get(&(channel_name.to_owned(), list_name.to_owned()))
+            .map_or(&[][..], |terms| terms.as_slice())
+    }
 }
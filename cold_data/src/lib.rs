@@ -12,32 +12,95 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate futures;
+extern crate regex;
+extern crate chrono;
 
 use actix::{Actor, Addr, Handler, SyncArbiter, SyncContext};
 use diesel::mysql::MysqlConnection;
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::result::DatabaseErrorKind;
 use failure::Error;
 use futures::Future;
+use std::collections::HashMap;
 use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 pub mod cache;
+pub mod matcher;
 pub mod models;
 
 mod schema;
 use cache::CommandCache;
 use models::ListCommands;
+use schema::channel_settings;
 use schema::commands::dsl::*;
+use schema::command_aliases;
+use schema::command_responses;
+use schema::counters;
+use schema::moderation_rules;
+use schema::moderation_terms;
+use schema::points;
+use schema::queue_entries;
+use schema::queues;
+use schema::poll_options;
+use schema::polls;
+use schema::quotes;
+use schema::raffle_entries;
+use schema::raffle_winners;
+use schema::raffles;
+use schema::script_store;
+use schema::timers;
+
+no_arg_sql_function!(
+    last_insert_id,
+    diesel::sql_types::Unsigned<diesel::sql_types::Bigint>,
+    "Id of the row last inserted by the connection"
+);
+
+/// Times a write is tried when concurrent ones keep taking its unique key
+const MAX_WRITE_ATTEMPTS: usize = 3;
+
+/// Counts database writes that are still running, so shutdown can wait for them
+#[derive(Clone, Default)]
+pub struct PendingWrites(Arc<AtomicUsize>);
+
+impl PendingWrites {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of writes currently running
+    pub fn count(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Marks a write as running until the returned guard is dropped
+    fn start(&self) -> PendingWrite {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        PendingWrite(self.0.clone())
+    }
+}
+
+struct PendingWrite(Arc<AtomicUsize>);
+
+impl Drop for PendingWrite {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
 
 /// A database connection pool in order to properly utilize the actor system
 pub struct DbConnectionPool {
     connection: Pool<ConnectionManager<MysqlConnection>>,
     command_cache: CommandCache,
+    pending_writes: PendingWrites,
 }
 
 impl DbConnectionPool {
     /// Connect to database and establish a connection pool
-    pub fn connect(command_cache: CommandCache) -> Addr<DbConnectionPool> {
+    pub fn connect(command_cache: CommandCache, pending_writes: PendingWrites) -> Addr<DbConnectionPool> {
         //This would eventually become a builder if more than one cache is needed
         dotenv::dotenv().ok();
         let database_url = env::var("DATABASE_URL").expect("Database url not set");
@@ -48,9 +111,14 @@ impl DbConnectionPool {
             .build(connection)
             .expect("Failed to crate db pool");
 
+        if let Err(err) = command_cache.load(&pool) {
+            println!("Could not load commands {:?}", err);
+        }
+
         SyncArbiter::start(3, move || Self {
             connection: pool.clone(),
             command_cache: command_cache.clone(),
+            pending_writes: pending_writes.clone(),
         })
     }
 }
@@ -69,14 +137,62 @@ impl Handler<models::CreateCommand> for DbConnectionPool {
     ) -> <Self as Handler<models::CreateCommand>>::Result {
         println!("{:?}", msg.match_expr);
 
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        // Only the response is replaced so settings like the permission survive
+        let row_change = diesel::sql_query(
+            "INSERT INTO commands (channel, match_expr, command) VALUES (?, ?, ?) \
+             ON DUPLICATE KEY UPDATE command = VALUES(command)",
+        ).bind::<diesel::sql_types::Varchar, _>(msg.channel)
+            .bind::<diesel::sql_types::Varchar, _>(msg.match_expr)
+            .bind::<diesel::sql_types::Varchar, _>(msg.command)
+            .execute(&connection)?;
+
+        if row_change > 0 {
+            self.command_cache.update(self)?;
+        }
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::EditCommand> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::EditCommand,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::EditCommand>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let row_change = diesel::update(commands.find((msg.channel, msg.match_expr)))
+            .set(command.eq(msg.command))
+            .execute(&connection)?;
+
+        if row_change > 0 {
+            self.command_cache.update(self)?;
+        }
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::RenameCommand> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::RenameCommand,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::RenameCommand>>::Result {
+        let _write = self.pending_writes.start();
         let connection = self.connection.get()?;
 
-        let row_change = diesel::replace_into(commands)
-            .values(&vec![(
-                channel.eq(msg.channel),
-                match_expr.eq(msg.match_expr),
-                command.eq(msg.command),
-            )])
+        let row_change = diesel::update(commands.find((msg.channel, msg.match_expr)))
+            .set(match_expr.eq(msg.new_match_expr))
             .execute(&connection)?;
 
         if row_change > 0 {
@@ -91,6 +207,7 @@ impl Handler<models::RemoveCommand> for DbConnectionPool {
     type Result = Result<usize, Error>;
 
     fn handle(&mut self, msg: models::RemoveCommand, _ctx: &mut Self::Context) -> <Self as Handler<models::RemoveCommand>>::Result {
+        let _write = self.pending_writes.start();
         let connection = self.connection.get()?;
 
         let row_change = diesel::delete(commands.filter((channel.eq(msg.channel).and(match_expr.eq(msg.match_expr)))))
@@ -104,6 +221,1407 @@ impl Handler<models::RemoveCommand> for DbConnectionPool {
     }
 }
 
+impl Handler<models::SetCommandPrefix> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SetCommandPrefix,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SetCommandPrefix>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        ensure_channel_settings(&connection, &msg.channel)?;
+
+        let row_change = diesel::update(channel_settings::table.find(msg.channel))
+            .set(channel_settings::command_prefix.eq(msg.prefix))
+            .execute(&connection)?;
+
+        self.command_cache.update(self)?;
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::SetDenyReply> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SetDenyReply,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SetDenyReply>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        ensure_channel_settings(&connection, &msg.channel)?;
+
+        let row_change = diesel::update(channel_settings::table.find(msg.channel))
+            .set(channel_settings::deny_reply.eq(msg.deny_reply))
+            .execute(&connection)?;
+
+        self.command_cache.update(self)?;
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::SetCommandPermission> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SetCommandPermission,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SetCommandPermission>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let row_change = diesel::update(commands.find((msg.channel, msg.match_expr)))
+            .set(permission.eq(msg.permission))
+            .execute(&connection)?;
+
+        if row_change > 0 {
+            self.command_cache.update(self)?;
+        }
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::SetCommandCooldowns> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SetCommandCooldowns,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SetCommandCooldowns>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let row_change = diesel::update(commands.find((msg.channel, msg.match_expr)))
+            .set((
+                global_cooldown.eq(msg.global_cooldown),
+                user_cooldown.eq(msg.user_cooldown),
+                cooldown_mod_exempt.eq(msg.cooldown_mod_exempt),
+            ))
+            .execute(&connection)?;
+
+        if row_change > 0 {
+            self.command_cache.update(self)?;
+        }
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::SetCommandMatchMode> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SetCommandMatchMode,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SetCommandMatchMode>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let row_change = diesel::update(commands.find((msg.channel, msg.match_expr)))
+            .set(match_mode.eq(msg.match_mode.as_str()))
+            .execute(&connection)?;
+
+        if row_change > 0 {
+            self.command_cache.update(self)?;
+        }
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::AddCommandResponse> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::AddCommandResponse,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::AddCommandResponse>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let exists = commands
+            .find((&msg.channel, &msg.match_expr))
+            .select(match_expr)
+            .first::<String>(&connection)
+            .optional()?
+            .is_some();
+        if !exists {
+            return Ok(0);
+        }
+
+        let row_change = diesel::insert_into(command_responses::table)
+            .values((
+                command_responses::channel.eq(&msg.channel),
+                command_responses::match_expr.eq(&msg.match_expr),
+                command_responses::response.eq(&msg.response),
+                command_responses::weight.eq(msg.weight),
+            ))
+            .execute(&connection)?;
+
+        self.command_cache.update(self)?;
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::RemoveCommandResponse> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::RemoveCommandResponse,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::RemoveCommandResponse>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let response_id = command_responses::table
+            .filter(command_responses::channel.eq(&msg.channel))
+            .filter(command_responses::match_expr.eq(&msg.match_expr))
+            .order(command_responses::id)
+            .select(command_responses::id)
+            .offset(msg.number.saturating_sub(1) as i64)
+            .first::<i32>(&connection)
+            .optional()?;
+
+        let row_change = match response_id {
+            Some(response_id) if msg.number > 0 => {
+                diesel::delete(command_responses::table.find(response_id)).execute(&connection)?
+            }
+            _ => 0,
+        };
+
+        if row_change > 0 {
+            self.command_cache.update(self)?;
+        }
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::SetCommandResponseWeight> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SetCommandResponseWeight,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SetCommandResponseWeight>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let row_change = if msg.number == 1 {
+            diesel::update(commands.find((msg.channel, msg.match_expr)))
+                .set(response_weight.eq(msg.weight))
+                .execute(&connection)?
+        } else {
+            let response_id = command_responses::table
+                .filter(command_responses::channel.eq(&msg.channel))
+                .filter(command_responses::match_expr.eq(&msg.match_expr))
+                .order(command_responses::id)
+                .select(command_responses::id)
+                .offset(msg.number.saturating_sub(2) as i64)
+                .first::<i32>(&connection)
+                .optional()?;
+
+            match response_id {
+                Some(response_id) if msg.number > 1 => {
+                    diesel::update(command_responses::table.find(response_id))
+                        .set(command_responses::weight.eq(msg.weight))
+                        .execute(&connection)?
+                }
+                _ => 0,
+            }
+        };
+
+        if row_change > 0 {
+            self.command_cache.update(self)?;
+        }
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::SetCommandResponseMode> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SetCommandResponseMode,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SetCommandResponseMode>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let row_change = diesel::update(commands.find((msg.channel, msg.match_expr)))
+            .set(response_mode.eq(msg.response_mode.as_str()))
+            .execute(&connection)?;
+
+        if row_change > 0 {
+            self.command_cache.update(self)?;
+        }
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::SetCommandScript> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SetCommandScript,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SetCommandScript>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let row_change = diesel::update(commands.find((msg.channel, msg.match_expr)))
+            .set(script.eq(msg.script))
+            .execute(&connection)?;
+
+        if row_change > 0 {
+            self.command_cache.update(self)?;
+        }
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::LoadScriptStore> for DbConnectionPool {
+    type Result = Result<HashMap<String, String>, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::LoadScriptStore,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::LoadScriptStore>>::Result {
+        let connection = self.connection.get()?;
+
+        let values = script_store::table
+            .filter(script_store::channel.eq(msg.channel))
+            .select((script_store::store_key, script_store::store_value))
+            .load::<(String, String)>(&connection)?;
+
+        Ok(values.into_iter().collect())
+    }
+}
+
+impl Handler<models::SaveScriptStore> for DbConnectionPool {
+    type Result = Result<(), Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SaveScriptStore,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SaveScriptStore>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        connection.transaction::<_, Error, _>(|| {
+            for (key, value) in msg.changes {
+                match value {
+                    Some(value) => {
+                        diesel::sql_query(
+                            "INSERT INTO script_store (channel, store_key, store_value) VALUES (?, ?, ?) \
+                             ON DUPLICATE KEY UPDATE store_value = VALUES(store_value)",
+                        ).bind::<diesel::sql_types::Varchar, _>(&msg.channel)
+                            .bind::<diesel::sql_types::Varchar, _>(key)
+                            .bind::<diesel::sql_types::Varchar, _>(value)
+                            .execute(&connection)?;
+                    }
+                    None => {
+                        diesel::delete(script_store::table.find((&msg.channel, key)))
+                            .execute(&connection)?;
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Handler<models::IncrementCommandUses> for DbConnectionPool {
+    type Result = Result<i32, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::IncrementCommandUses,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::IncrementCommandUses>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        // The cache is not refreshed, uses are not worth reloading every command for
+        let count = connection.transaction::<_, Error, _>(|| {
+            diesel::update(commands.find((&msg.channel, &msg.match_expr)))
+                .set(use_count.eq(use_count + 1))
+                .execute(&connection)?;
+
+            Ok(commands
+                .find((&msg.channel, &msg.match_expr))
+                .select(use_count)
+                .first::<i32>(&connection)?)
+        })?;
+
+        Ok(count)
+    }
+}
+
+impl Handler<models::CreateAlias> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::CreateAlias,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::CreateAlias>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let row_change = diesel::insert_or_ignore_into(command_aliases::table)
+            .values((
+                command_aliases::channel.eq(msg.channel),
+                command_aliases::alias.eq(msg.alias),
+                command_aliases::match_expr.eq(msg.match_expr),
+            ))
+            .execute(&connection)?;
+
+        if row_change > 0 {
+            self.command_cache.update(self)?;
+        }
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::RemoveAlias> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::RemoveAlias,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::RemoveAlias>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let row_change = diesel::delete(command_aliases::table.find((msg.channel, msg.alias)))
+            .execute(&connection)?;
+
+        if row_change > 0 {
+            self.command_cache.update(self)?;
+        }
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::CreateCounter> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::CreateCounter,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::CreateCounter>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let row_change = diesel::insert_or_ignore_into(counters::table)
+            .values((counters::channel.eq(&msg.channel), counters::name.eq(&msg.name)))
+            .execute(&connection)?;
+
+        if row_change > 0 {
+            self.command_cache.set_counter(&msg.channel, &msg.name, Some(0))?;
+        }
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::RemoveCounter> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::RemoveCounter,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::RemoveCounter>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let row_change = diesel::delete(counters::table.find((&msg.channel, &msg.name)))
+            .execute(&connection)?;
+
+        if row_change > 0 {
+            self.command_cache.set_counter(&msg.channel, &msg.name, None)?;
+        }
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::ChangeCounter> for DbConnectionPool {
+    type Result = Result<Option<i32>, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::ChangeCounter,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::ChangeCounter>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        // The change is done by the database so concurrent changes are never lost
+        let new_value = connection.transaction::<_, Error, _>(|| {
+            let target = counters::table.find((&msg.channel, &msg.name));
+            match msg.change {
+                models::CounterChange::Add(amount) => diesel::update(target)
+                    .set(counters::value.eq(counters::value + amount))
+                    .execute(&connection)?,
+                models::CounterChange::Set(new_value) => diesel::update(target)
+                    .set(counters::value.eq(new_value))
+                    .execute(&connection)?,
+            };
+
+            Ok(counters::table
+                .find((&msg.channel, &msg.name))
+                .select(counters::value)
+                .first::<i32>(&connection)
+                .optional()?)
+        })?;
+
+        if let Some(new_value) = new_value {
+            self.command_cache
+                .set_counter(&msg.channel, &msg.name, Some(new_value))?;
+        }
+
+        Ok(new_value)
+    }
+}
+
+impl Handler<models::SetTimer> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SetTimer,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SetTimer>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        Ok(diesel::replace_into(timers::table)
+            .values(&msg.0)
+            .execute(&connection)?)
+    }
+}
+
+impl Handler<models::RemoveTimer> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::RemoveTimer,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::RemoveTimer>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        Ok(diesel::delete(timers::table.find((msg.channel, msg.name))).execute(&connection)?)
+    }
+}
+
+impl Handler<models::SetTimerEnabled> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SetTimerEnabled,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SetTimerEnabled>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        Ok(diesel::update(timers::table.find((msg.channel, msg.name)))
+            .set(timers::enabled.eq(msg.enabled))
+            .execute(&connection)?)
+    }
+}
+
+impl Handler<models::ListTimers> for DbConnectionPool {
+    type Result = Result<Vec<models::Timer>, Error>;
+
+    fn handle(
+        &mut self,
+        _msg: models::ListTimers,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::ListTimers>>::Result {
+        let connection = self.connection.get()?;
+
+        Ok(timers::table.load::<models::Timer>(&connection)?)
+    }
+}
+
+impl Handler<models::SetPermitSettings> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SetPermitSettings,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SetPermitSettings>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        ensure_channel_settings(&connection, &msg.channel)?;
+
+        let row_change = diesel::update(channel_settings::table.find(msg.channel))
+            .set((
+                channel_settings::permit_seconds.eq(msg.permit_seconds),
+                channel_settings::permit_links.eq(msg.permit_links),
+            ))
+            .execute(&connection)?;
+
+        self.command_cache.update(self)?;
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::SetModerationRule> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SetModerationRule,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SetModerationRule>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let row_change = diesel::replace_into(moderation_rules::table)
+            .values(&msg.0)
+            .execute(&connection)?;
+
+        self.command_cache.update(self)?;
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::AddModerationTerm> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::AddModerationTerm,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::AddModerationTerm>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let row_change = diesel::insert_or_ignore_into(moderation_terms::table)
+            .values((
+                moderation_terms::channel.eq(msg.channel),
+                moderation_terms::list.eq(msg.list),
+                moderation_terms::term.eq(msg.term),
+            ))
+            .execute(&connection)?;
+
+        if row_change > 0 {
+            self.command_cache.update(self)?;
+        }
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::RemoveModerationTerm> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::RemoveModerationTerm,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::RemoveModerationTerm>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let row_change =
+            diesel::delete(moderation_terms::table.find((msg.channel, msg.list, msg.term)))
+                .execute(&connection)?;
+
+        if row_change > 0 {
+            self.command_cache.update(self)?;
+        }
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::AddQuote> for DbConnectionPool {
+    type Result = Result<i32, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::AddQuote,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::AddQuote>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        // Removed numbers are not reused unless they were the last ones,
+        // a quote added at the same time can take the number so it is picked again
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let result = connection.transaction::<_, diesel::result::Error, _>(|| {
+                let last = quotes::table
+                    .filter(quotes::channel.eq(&msg.channel))
+                    .select(diesel::dsl::max(quotes::number))
+                    .first::<Option<i32>>(&connection)?;
+                let number = last.unwrap_or(0) + 1;
+
+                diesel::insert_into(quotes::table)
+                    .values((
+                        quotes::channel.eq(&msg.channel),
+                        quotes::number.eq(number),
+                        quotes::quote.eq(&msg.quote),
+                        quotes::added_by.eq(&msg.added_by),
+                        quotes::game.eq(&msg.game),
+                    ))
+                    .execute(&connection)?;
+
+                Ok(number)
+            });
+
+            match result {
+                Err(ref err) if is_duplicate_key(err) && attempts < MAX_WRITE_ATTEMPTS => continue,
+                result => return Ok(result?),
+            }
+        }
+    }
+}
+
+impl Handler<models::GetQuote> for DbConnectionPool {
+    type Result = Result<Option<models::Quote>, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::GetQuote,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::GetQuote>>::Result {
+        let connection = self.connection.get()?;
+
+        let quote = match msg.number {
+            Some(number) => quotes::table
+                .find((msg.channel, number))
+                .first::<models::Quote>(&connection),
+            None => quotes::table
+                .filter(quotes::channel.eq(msg.channel))
+                .order(diesel::dsl::sql::<diesel::sql_types::Double>("RAND()"))
+                .first::<models::Quote>(&connection),
+        };
+
+        Ok(quote.optional()?)
+    }
+}
+
+impl Handler<models::SearchQuotes> for DbConnectionPool {
+    type Result = Result<Vec<models::Quote>, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SearchQuotes,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SearchQuotes>>::Result {
+        let connection = self.connection.get()?;
+
+        let escaped = msg
+            .text
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+
+        Ok(quotes::table
+            .filter(quotes::channel.eq(msg.channel))
+            .filter(quotes::quote.like(format!("%{}%", escaped)))
+            .order(quotes::number)
+            .load::<models::Quote>(&connection)?)
+    }
+}
+
+impl Handler<models::EditQuote> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::EditQuote,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::EditQuote>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        Ok(diesel::update(quotes::table.find((msg.channel, msg.number)))
+            .set((quotes::quote.eq(msg.quote), quotes::game.eq(msg.game)))
+            .execute(&connection)?)
+    }
+}
+
+impl Handler<models::RemoveQuote> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::RemoveQuote,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::RemoveQuote>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        Ok(diesel::delete(quotes::table.find((msg.channel, msg.number))).execute(&connection)?)
+    }
+}
+
+impl Handler<models::ListQuotes> for DbConnectionPool {
+    type Result = Result<Vec<models::Quote>, Error>;
+
+    fn handle(
+        &mut self,
+        _msg: models::ListQuotes,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::ListQuotes>>::Result {
+        let connection = self.connection.get()?;
+
+        Ok(quotes::table
+            .order((quotes::channel, quotes::number))
+            .load::<models::Quote>(&connection)?)
+    }
+}
+
+impl Handler<models::SavePoll> for DbConnectionPool {
+    type Result = Result<(), Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SavePoll,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SavePoll>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        connection.transaction::<_, Error, _>(|| {
+            diesel::insert_into(polls::table)
+                .values((
+                    polls::channel.eq(&msg.channel),
+                    polls::question.eq(&msg.question),
+                    polls::started_by.eq(&msg.started_by),
+                    polls::duration_seconds.eq(msg.duration_seconds),
+                ))
+                .execute(&connection)?;
+
+            let poll_id = diesel::select(last_insert_id).first::<u64>(&connection)? as i32;
+
+            let options: Vec<_> = msg
+                .options
+                .iter()
+                .enumerate()
+                .map(|(position, (text, votes))| {
+                    (
+                        poll_options::poll_id.eq(poll_id),
+                        poll_options::position.eq(position as i32),
+                        poll_options::option_text.eq(text),
+                        poll_options::votes.eq(*votes),
+                    )
+                })
+                .collect();
+
+            diesel::insert_into(poll_options::table)
+                .values(&options)
+                .execute(&connection)?;
+
+            Ok(())
+        })
+    }
+}
+
+impl Handler<models::OpenRaffle> for DbConnectionPool {
+    type Result = Result<i32, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::OpenRaffle,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::OpenRaffle>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        connection.transaction::<_, Error, _>(|| {
+            diesel::insert_into(raffles::table)
+                .values((
+                    raffles::channel.eq(&msg.channel),
+                    raffles::keyword.eq(&msg.keyword),
+                    raffles::sub_luck.eq(msg.sub_luck),
+                    raffles::seed.eq(&msg.seed),
+                    raffles::opened_by.eq(&msg.opened_by),
+                    raffles::cost.eq(msg.cost),
+                ))
+                .execute(&connection)?;
+
+            Ok(diesel::select(last_insert_id).first::<u64>(&connection)? as i32)
+        })
+    }
+}
+
+impl Handler<models::AddRaffleEntry> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::AddRaffleEntry,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::AddRaffleEntry>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        Ok(diesel::insert_or_ignore_into(raffle_entries::table)
+            .values((
+                raffle_entries::raffle_id.eq(msg.raffle_id),
+                raffle_entries::user.eq(msg.user),
+                raffle_entries::position.eq(msg.position),
+                raffle_entries::tickets.eq(msg.tickets),
+            ))
+            .execute(&connection)?)
+    }
+}
+
+impl Handler<models::CloseRaffle> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::CloseRaffle,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::CloseRaffle>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        Ok(diesel::update(
+            raffles::table
+                .find(msg.raffle_id)
+                .filter(raffles::closed_at.is_null()),
+        ).set(raffles::closed_at.eq(diesel::dsl::now.nullable()))
+            .execute(&connection)?)
+    }
+}
+
+impl Handler<models::AddRaffleWinner> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::AddRaffleWinner,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::AddRaffleWinner>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        Ok(diesel::insert_into(raffle_winners::table)
+            .values((
+                raffle_winners::raffle_id.eq(msg.raffle_id),
+                raffle_winners::draw.eq(msg.draw),
+                raffle_winners::user.eq(msg.user),
+                raffle_winners::claimed.eq(msg.claimed),
+            ))
+            .execute(&connection)?)
+    }
+}
+
+impl Handler<models::ClaimRaffleWin> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::ClaimRaffleWin,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::ClaimRaffleWin>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        Ok(diesel::update(raffle_winners::table.find((msg.raffle_id, msg.draw)))
+            .set(raffle_winners::claimed.eq(true))
+            .execute(&connection)?)
+    }
+}
+
+impl Handler<models::ListRaffles> for DbConnectionPool {
+    type Result = Result<Vec<models::RaffleHistory>, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::ListRaffles,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::ListRaffles>>::Result {
+        let connection = self.connection.get()?;
+
+        let raffles = raffles::table
+            .order(raffles::id.desc())
+            .limit(msg.limit)
+            .load::<models::Raffle>(&connection)?;
+        let ids: Vec<i32> = raffles.iter().map(|raffle| raffle.id).collect();
+
+        let mut entries: HashMap<i32, i64> = HashMap::new();
+        for raffle_id in raffle_entries::table
+            .filter(raffle_entries::raffle_id.eq_any(&ids))
+            .select(raffle_entries::raffle_id)
+            .load::<i32>(&connection)?
+        {
+            *entries.entry(raffle_id).or_insert(0) += 1;
+        }
+
+        let mut winners = HashMap::new();
+        for winner in raffle_winners::table
+            .filter(raffle_winners::raffle_id.eq_any(&ids))
+            .order((raffle_winners::raffle_id, raffle_winners::draw))
+            .load::<models::RaffleWinner>(&connection)?
+        {
+            winners
+                .entry(winner.raffle_id)
+                .or_insert_with(Vec::new)
+                .push(winner);
+        }
+
+        Ok(raffles
+            .into_iter()
+            .map(|raffle| models::RaffleHistory {
+                entries: entries.get(&raffle.id).cloned().unwrap_or(0),
+                winners: winners.remove(&raffle.id).unwrap_or_default(),
+                raffle,
+            })
+            .collect())
+    }
+}
+
+impl Handler<models::ListRaffleEntries> for DbConnectionPool {
+    type Result = Result<Vec<models::RaffleEntry>, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::ListRaffleEntries,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::ListRaffleEntries>>::Result {
+        let connection = self.connection.get()?;
+
+        Ok(raffle_entries::table
+            .filter(raffle_entries::raffle_id.eq(msg.raffle_id))
+            .order(raffle_entries::position)
+            .load::<models::RaffleEntry>(&connection)?)
+    }
+}
+
+impl Handler<models::GetPoints> for DbConnectionPool {
+    type Result = Result<i32, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::GetPoints,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::GetPoints>>::Result {
+        let connection = self.connection.get()?;
+
+        let balance = points::table
+            .find((msg.channel, msg.user))
+            .select(points::balance)
+            .first::<i32>(&connection)
+            .optional()?;
+
+        Ok(balance.unwrap_or(0))
+    }
+}
+
+impl Handler<models::EarnPoints> for DbConnectionPool {
+    type Result = Result<(), Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::EarnPoints,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::EarnPoints>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        connection.transaction::<_, Error, _>(|| {
+            for (channel_name, user_name, amount) in &msg.earnings {
+                add_points(&connection, channel_name, user_name, *amount)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Handler<models::AddPoints> for DbConnectionPool {
+    type Result = Result<i32, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::AddPoints,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::AddPoints>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        connection.transaction::<_, Error, _>(|| {
+            add_points(&connection, &msg.channel, &msg.user, msg.amount)?;
+
+            Ok(points::table
+                .find((&msg.channel, &msg.user))
+                .select(points::balance)
+                .first::<i32>(&connection)?)
+        })
+    }
+}
+
+impl Handler<models::SpendPoints> for DbConnectionPool {
+    type Result = Result<bool, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SpendPoints,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SpendPoints>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        Ok(spend_points(&connection, &msg.channel, &msg.user, msg.amount)? > 0)
+    }
+}
+
+impl Handler<models::GivePoints> for DbConnectionPool {
+    type Result = Result<bool, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::GivePoints,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::GivePoints>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        connection.transaction::<_, Error, _>(|| {
+            if spend_points(&connection, &msg.channel, &msg.from, msg.amount)? == 0 {
+                return Ok(false);
+            }
+
+            add_points(&connection, &msg.channel, &msg.to, msg.amount)?;
+            Ok(true)
+        })
+    }
+}
+
+impl Handler<models::SetPointRates> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SetPointRates,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SetPointRates>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        ensure_channel_settings(&connection, &msg.channel)?;
+
+        let row_change = diesel::update(channel_settings::table.find(msg.channel))
+            .set((
+                channel_settings::points_presence.eq(msg.presence),
+                channel_settings::points_chat.eq(msg.chat),
+                channel_settings::points_sub_multiplier.eq(msg.sub_multiplier),
+            ))
+            .execute(&connection)?;
+
+        self.command_cache.update(self)?;
+
+        Ok(row_change)
+    }
+}
+
+impl Handler<models::JoinQueue> for DbConnectionPool {
+    type Result = Result<models::QueueJoin, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::JoinQueue,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::JoinQueue>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        let result = connection.transaction::<_, diesel::result::Error, _>(|| {
+            let queue = load_queue(&connection, &msg.channel)?;
+            if let Some(position) = queue.position(&msg.user) {
+                return Ok(models::QueueJoin::AlreadyIn(position));
+            }
+            if !queue.open {
+                return Ok(models::QueueJoin::Closed);
+            }
+
+            diesel::insert_into(queue_entries::table)
+                .values((
+                    queue_entries::channel.eq(&msg.channel),
+                    queue_entries::user.eq(&msg.user),
+                    queue_entries::subscriber.eq(msg.subscriber),
+                ))
+                .execute(&connection)?;
+
+            let position = load_queue(&connection, &msg.channel)?
+                .position(&msg.user)
+                .unwrap_or(queue.entries.len() + 1);
+            Ok(models::QueueJoin::Joined(position))
+        });
+
+        match result {
+            // Joined by a concurrent message, read again outside the transaction to see it
+            Err(ref err) if is_duplicate_key(err) => {
+                let queue = load_queue(&connection, &msg.channel)?;
+                let position = queue.position(&msg.user).unwrap_or(queue.entries.len().max(1));
+                Ok(models::QueueJoin::AlreadyIn(position))
+            }
+            result => Ok(result?),
+        }
+    }
+}
+
+impl Handler<models::LeaveQueue> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::LeaveQueue,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::LeaveQueue>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        Ok(diesel::delete(
+            queue_entries::table
+                .filter(queue_entries::channel.eq(msg.channel))
+                .filter(queue_entries::user.eq(msg.user)),
+        ).execute(&connection)?)
+    }
+}
+
+impl Handler<models::GetQueue> for DbConnectionPool {
+    type Result = Result<models::ViewerQueue, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::GetQueue,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::GetQueue>>::Result {
+        let connection = self.connection.get()?;
+
+        Ok(load_queue(&connection, &msg.channel)?)
+    }
+}
+
+impl Handler<models::NextInQueue> for DbConnectionPool {
+    type Result = Result<Vec<models::QueueEntry>, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::NextInQueue,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::NextInQueue>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        connection.transaction::<_, Error, _>(|| {
+            // Locked so users picked by a concurrent call are not picked again
+            queue_entries::table
+                .filter(queue_entries::channel.eq(&msg.channel))
+                .select(queue_entries::id)
+                .for_update()
+                .load::<i32>(&connection)?;
+
+            let mut entries = load_queue(&connection, &msg.channel)?.entries;
+            entries.truncate(msg.count.max(0) as usize);
+
+            let ids: Vec<i32> = entries.iter().map(|entry| entry.id).collect();
+            diesel::delete(queue_entries::table.filter(queue_entries::id.eq_any(&ids)))
+                .execute(&connection)?;
+
+            Ok(entries)
+        })
+    }
+}
+
+impl Handler<models::SetQueueState> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::SetQueueState,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::SetQueueState>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        connection.transaction::<_, Error, _>(|| {
+            diesel::insert_or_ignore_into(queues::table)
+                .values(queues::channel.eq(&msg.channel))
+                .execute(&connection)?;
+
+            let mut row_change = 0;
+            if let Some(open) = msg.open {
+                row_change += diesel::update(queues::table.find(&msg.channel))
+                    .set(queues::open.eq(open))
+                    .execute(&connection)?;
+            }
+            if let Some(sub_priority) = msg.sub_priority {
+                row_change += diesel::update(queues::table.find(&msg.channel))
+                    .set(queues::sub_priority.eq(sub_priority))
+                    .execute(&connection)?;
+            }
+
+            Ok(row_change)
+        })
+    }
+}
+
+impl Handler<models::ClearQueue> for DbConnectionPool {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        msg: models::ClearQueue,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::ClearQueue>>::Result {
+        let _write = self.pending_writes.start();
+        let connection = self.connection.get()?;
+
+        Ok(
+            diesel::delete(queue_entries::table.filter(queue_entries::channel.eq(msg.channel)))
+                .execute(&connection)?,
+        )
+    }
+}
+
+impl Handler<models::ListQueues> for DbConnectionPool {
+    type Result = Result<Vec<models::ViewerQueue>, Error>;
+
+    fn handle(
+        &mut self,
+        _msg: models::ListQueues,
+        _ctx: &mut Self::Context,
+    ) -> <Self as Handler<models::ListQueues>>::Result {
+        let connection = self.connection.get()?;
+
+        let mut channels: Vec<String> = queues::table
+            .select(queues::channel)
+            .load::<String>(&connection)?;
+        channels.extend(
+            queue_entries::table
+                .select(queue_entries::channel)
+                .distinct()
+                .load::<String>(&connection)?,
+        );
+        channels.sort();
+        channels.dedup();
+
+        Ok(channels
+            .iter()
+            .map(|channel_name| load_queue(&connection, channel_name))
+            .collect::<QueryResult<_>>()?)
+    }
+}
+
+/// Whether a write lost the race for a unique key against a concurrent one
+fn is_duplicate_key(err: &diesel::result::Error) -> bool {
+    match *err {
+        diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => true,
+        _ => false,
+    }
+}
+
+/// Settings are updated one column at a time, so a channel needs its row with defaults first
+fn ensure_channel_settings(connection: &MysqlConnection, channel_name: &str) -> QueryResult<usize> {
+    diesel::insert_or_ignore_into(channel_settings::table)
+        .values(channel_settings::channel.eq(channel_name))
+        .execute(connection)
+}
+
+/// Add to the balance of a user, creating it if needed, balances stay between zero and INT max
+fn add_points(
+    connection: &MysqlConnection,
+    channel_name: &str,
+    user_name: &str,
+    amount: i32,
+) -> QueryResult<usize> {
+    diesel::sql_query(
+        "insert into points (channel, user, balance) values (?, ?, greatest(?, 0)) \
+         on duplicate key update balance = least(greatest(balance + ?, 0), 2147483647)",
+    ).bind::<diesel::sql_types::Varchar, _>(channel_name)
+        .bind::<diesel::sql_types::Varchar, _>(user_name)
+        .bind::<diesel::sql_types::Integer, _>(amount)
+        .bind::<diesel::sql_types::Integer, _>(amount)
+        .execute(connection)
+}
+
+/// Remove points from a user only if they have enough, in a single statement so concurrent spends can't overdraw
+fn spend_points(
+    connection: &MysqlConnection,
+    channel_name: &str,
+    user_name: &str,
+    amount: i32,
+) -> QueryResult<usize> {
+    diesel::update(
+        points::table
+            .find((channel_name, user_name))
+            .filter(points::balance.ge(amount)),
+    ).set(points::balance.eq(points::balance - amount))
+        .execute(connection)
+}
+
+/// Queue of a channel in the order users are picked, closed and empty if it was never opened
+fn load_queue(connection: &MysqlConnection, channel_name: &str) -> QueryResult<models::ViewerQueue> {
+    let (open, sub_priority) = queues::table
+        .find(channel_name)
+        .select((queues::open, queues::sub_priority))
+        .first::<(bool, bool)>(connection)
+        .optional()?
+        .unwrap_or((false, false));
+
+    let mut entries = queue_entries::table
+        .filter(queue_entries::channel.eq(channel_name))
+        .order(queue_entries::id)
+        .load::<models::QueueEntry>(connection)?;
+    if sub_priority {
+        // Stable, so subscribers and other users each keep the order they joined in
+        entries.sort_by_key(|entry| !entry.subscriber);
+    }
+
+    Ok(models::ViewerQueue {
+        channel: channel_name.to_owned(),
+        open,
+        sub_priority,
+        entries,
+    })
+}
+
 impl Handler<ListCommands> for DbConnectionPool {
     type Result = Result<Vec<models::Command>, Error>;
 
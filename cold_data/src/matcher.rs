@@ -0,0 +1,133 @@
+use regex::{escape, Regex, RegexBuilder};
+use std::collections::HashMap;
+
+/// Limit on the compiled size of a pattern, so a user can't make the bot compile a huge automaton
+const PATTERN_SIZE_LIMIT: usize = 1 << 16;
+
+/// Longest pattern accepted, matches the database column
+const MAX_PATTERN_LENGTH: usize = 200;
+
+#[derive(Debug, Fail)]
+pub enum MatcherError {
+    #[fail(display = "unknown match mode {}, expected exact, prefix, word, glob or regex", 0)]
+    UnknownMode(String),
+    #[fail(display = "pattern is longer than {} characters", 0)]
+    TooLong(usize),
+    #[fail(display = "invalid pattern: {}", 0)]
+    InvalidPattern(String),
+}
+
+/// How the match expression of a stored command is compared to chat messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchMode {
+    /// Used as `prefix` + keyword, like built-in commands
+    Exact,
+    /// Message starts with the expression
+    Prefix,
+    /// Message contains the expression as a whole word
+    Word,
+    /// Whole message matches a glob using `*` and `?`
+    Glob,
+    /// Message contains a match of a regular expression, its groups are available to the response
+    Regex,
+}
+
+impl MatchMode {
+    pub fn parse(mode: &str) -> Result<Self, MatcherError> {
+        match mode.to_lowercase().as_str() {
+            "exact" => Ok(MatchMode::Exact),
+            "prefix" => Ok(MatchMode::Prefix),
+            "word" => Ok(MatchMode::Word),
+            "glob" => Ok(MatchMode::Glob),
+            "regex" => Ok(MatchMode::Regex),
+            _ => Err(MatcherError::UnknownMode(mode.to_owned())),
+        }
+    }
+
+    /// Name stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchMode::Exact => "exact",
+            MatchMode::Prefix => "prefix",
+            MatchMode::Word => "word",
+            MatchMode::Glob => "glob",
+            MatchMode::Regex => "regex",
+        }
+    }
+}
+
+/// Assertion keeping an expression from matching inside a longer word, for one of its edges
+/// `\b` never matches next to a non-word edge like the `!` of `!hi`, `\B` is used there instead.
+fn boundary(edge: Option<char>) -> &'static str {
+    match edge {
+        Some(c) if c.is_alphanumeric() || c == '_' => r"\b",
+        _ => r"\B",
+    }
+}
+
+/// A compiled match expression for the modes other than exact
+/// Every mode ignores case.
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    regex: Regex,
+}
+
+impl Matcher {
+    /// Compile an expression, `None` for exact commands which are looked up by keyword
+    pub fn compile(mode: MatchMode, expression: &str) -> Result<Option<Self>, MatcherError> {
+        if expression.len() > MAX_PATTERN_LENGTH {
+            return Err(MatcherError::TooLong(MAX_PATTERN_LENGTH));
+        }
+
+        let pattern = match mode {
+            MatchMode::Exact => return Ok(None),
+            MatchMode::Prefix => format!("^{}", escape(expression)),
+            MatchMode::Word => format!(
+                "{}{}{}",
+                boundary(expression.chars().next()),
+                escape(expression),
+                boundary(expression.chars().last())
+            ),
+            MatchMode::Glob => {
+                let pattern: String = expression
+                    .chars()
+                    .map(|c| match c {
+                        '*' => ".*".to_owned(),
+                        '?' => ".".to_owned(),
+                        c => escape(&c.to_string()),
+                    })
+                    .collect();
+                format!("^{}$", pattern)
+            }
+            MatchMode::Regex => expression.to_owned(),
+        };
+
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(true)
+            .size_limit(PATTERN_SIZE_LIMIT)
+            .dfa_size_limit(PATTERN_SIZE_LIMIT)
+            .build()
+            .map_err(|err| MatcherError::InvalidPattern(err.to_string()))?;
+
+        Ok(Some(Self { regex }))
+    }
+
+    /// Groups captured in the message if it matches, by index and by name
+    pub fn captures(&self, message: &str) -> Option<HashMap<String, String>> {
+        let captures = self.regex.captures(message)?;
+
+        let mut groups = HashMap::new();
+        for (index, group) in captures.iter().enumerate() {
+            if let Some(group) = group {
+                groups.insert(index.to_string(), group.as_str().to_owned());
+            }
+        }
+        for name in self.regex.capture_names().filter_map(|name| name) {
+            if let Some(group) = captures.name(name) {
+                groups.insert(name.to_owned(), group.as_str().to_owned());
+            }
+        }
+
+        Some(groups)
+    }
+}
@@ -1,6 +1,11 @@
+use super::matcher::MatchMode;
 use super::schema::commands;
+use super::schema::moderation_rules;
+use super::schema::timers;
 use actix::prelude::*;
+use chrono::NaiveDateTime;
 use failure::Error;
+use std::collections::HashMap;
 
 /// Create a bot command in the database
 #[derive(PartialEq, Eq, Insertable)]
@@ -15,6 +20,28 @@ impl Message for CreateCommand {
     type Result = Result<usize, Error>;
 }
 
+/// Replace the response of an existing command
+pub struct EditCommand {
+    pub channel: String,
+    pub match_expr: String,
+    pub command: String,
+}
+
+impl Message for EditCommand {
+    type Result = Result<usize, Error>;
+}
+
+/// Change the match expression of an existing command
+pub struct RenameCommand {
+    pub channel: String,
+    pub match_expr: String,
+    pub new_match_expr: String,
+}
+
+impl Message for RenameCommand {
+    type Result = Result<usize, Error>;
+}
+
 pub struct RemoveCommand {
     pub channel: String,
     pub match_expr: String,
@@ -29,6 +56,214 @@ pub struct Command {
     pub channel: String,
     pub match_expr: String,
     pub command: String,
+    /// Lowest permission level that can use the command
+    pub permission: i32,
+    /// Only counted for responses that show it
+    pub use_count: i32,
+    /// Seconds before anyone can use the command again
+    pub global_cooldown: i32,
+    /// Seconds before the same user can use the command again
+    pub user_cooldown: i32,
+    /// Moderators ignore the cooldowns
+    pub cooldown_mod_exempt: bool,
+    /// How `match_expr` is compared to chat messages, see `MatchMode`
+    pub match_mode: String,
+    /// Script run instead of sending `command`
+    pub script: Option<String>,
+    /// How a response is picked when the command has variants, see `ResponseMode`
+    pub response_mode: String,
+    /// Weight of `command` when responses are picked by weight
+    pub response_weight: i32,
+}
+
+impl Command {
+    /// Unknown modes are treated as exact
+    pub fn match_mode(&self) -> MatchMode {
+        MatchMode::parse(&self.match_mode).unwrap_or(MatchMode::Exact)
+    }
+
+    /// Unknown modes are treated as random
+    pub fn response_mode(&self) -> ResponseMode {
+        ResponseMode::parse(&self.response_mode).unwrap_or(ResponseMode::Random)
+    }
+}
+
+/// How one of the responses of a command with variants is picked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseMode {
+    Random,
+    /// Each response in turn, starting over after the last one
+    RoundRobin,
+    /// Randomly, in proportion to the weight of each response
+    Weighted,
+}
+
+impl ResponseMode {
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode.to_lowercase().as_str() {
+            "random" => Some(ResponseMode::Random),
+            "roundrobin" => Some(ResponseMode::RoundRobin),
+            "weighted" => Some(ResponseMode::Weighted),
+            _ => None,
+        }
+    }
+
+    /// Name stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResponseMode::Random => "random",
+            ResponseMode::RoundRobin => "roundrobin",
+            ResponseMode::Weighted => "weighted",
+        }
+    }
+}
+
+/// Longest response variant, matches the database column
+pub const MAX_RESPONSE_LENGTH: usize = 200;
+
+/// Another response a command can send instead of `command`
+#[derive(Serialize, Queryable, Clone)]
+pub struct CommandResponse {
+    pub id: i32,
+    pub channel: String,
+    pub match_expr: String,
+    pub response: String,
+    /// Only used by weighted commands
+    pub weight: i32,
+}
+
+/// Add a response variant to a stored command
+pub struct AddCommandResponse {
+    pub channel: String,
+    pub match_expr: String,
+    pub response: String,
+    pub weight: i32,
+}
+
+impl Message for AddCommandResponse {
+    type Result = Result<usize, Error>;
+}
+
+/// Remove a response variant, numbered from 1 in the order they were added
+pub struct RemoveCommandResponse {
+    pub channel: String,
+    pub match_expr: String,
+    pub number: usize,
+}
+
+impl Message for RemoveCommandResponse {
+    type Result = Result<usize, Error>;
+}
+
+/// Set the weight of a response, numbered from 1 like listed, 1 being the command's own response
+pub struct SetCommandResponseWeight {
+    pub channel: String,
+    pub match_expr: String,
+    pub number: usize,
+    pub weight: i32,
+}
+
+impl Message for SetCommandResponseWeight {
+    type Result = Result<usize, Error>;
+}
+
+/// Set how the response of a command with variants is picked
+pub struct SetCommandResponseMode {
+    pub channel: String,
+    pub match_expr: String,
+    pub response_mode: ResponseMode,
+}
+
+impl Message for SetCommandResponseMode {
+    type Result = Result<usize, Error>;
+}
+
+/// Set how a stored command is matched
+pub struct SetCommandMatchMode {
+    pub channel: String,
+    pub match_expr: String,
+    pub match_mode: MatchMode,
+}
+
+impl Message for SetCommandMatchMode {
+    type Result = Result<usize, Error>;
+}
+
+/// Longest script a command can hold
+pub const MAX_SCRIPT_LENGTH: usize = 4000;
+
+/// Set or clear the script of a stored command
+pub struct SetCommandScript {
+    pub channel: String,
+    pub match_expr: String,
+    pub script: Option<String>,
+}
+
+impl Message for SetCommandScript {
+    type Result = Result<usize, Error>;
+}
+
+/// Check that a script compiles, without running it
+/// Handled by the script runner of the bot, the error is the Lua error.
+pub struct CheckScript {
+    pub script: String,
+}
+
+impl Message for CheckScript {
+    type Result = Result<(), Error>;
+}
+
+/// Load every value scripts stored in a channel
+pub struct LoadScriptStore {
+    pub channel: String,
+}
+
+impl Message for LoadScriptStore {
+    type Result = Result<HashMap<String, String>, Error>;
+}
+
+/// Save values changed by a script, `None` removes the key
+pub struct SaveScriptStore {
+    pub channel: String,
+    pub changes: HashMap<String, Option<String>>,
+}
+
+impl Message for SaveScriptStore {
+    type Result = Result<(), Error>;
+}
+
+/// Set the cooldowns of a stored command
+pub struct SetCommandCooldowns {
+    pub channel: String,
+    pub match_expr: String,
+    pub global_cooldown: i32,
+    pub user_cooldown: i32,
+    pub cooldown_mod_exempt: bool,
+}
+
+impl Message for SetCommandCooldowns {
+    type Result = Result<usize, Error>;
+}
+
+/// Count a use of a stored command, results in the new count
+pub struct IncrementCommandUses {
+    pub channel: String,
+    pub match_expr: String,
+}
+
+impl Message for IncrementCommandUses {
+    type Result = Result<i32, Error>;
+}
+
+/// Set the permission level needed to use a stored command
+pub struct SetCommandPermission {
+    pub channel: String,
+    pub match_expr: String,
+    pub permission: i32,
+}
+
+impl Message for SetCommandPermission {
+    type Result = Result<usize, Error>;
 }
 
 
@@ -38,3 +273,645 @@ impl Message for ListCommands {
     type Result = Result<Vec<Command>, Error>;
 }
 
+/// Another trigger for a stored command of the same channel
+#[derive(Serialize, Queryable)]
+pub struct Alias {
+    pub channel: String,
+    pub alias: String,
+    /// Match expression of the command the alias uses
+    pub match_expr: String,
+}
+
+/// Add an alias, nothing changes if it already exists
+pub struct CreateAlias {
+    pub channel: String,
+    pub alias: String,
+    pub match_expr: String,
+}
+
+impl Message for CreateAlias {
+    type Result = Result<usize, Error>;
+}
+
+pub struct RemoveAlias {
+    pub channel: String,
+    pub alias: String,
+}
+
+impl Message for RemoveAlias {
+    type Result = Result<usize, Error>;
+}
+
+/// A tally shown and changed from chat, like a death counter
+#[derive(Serialize, Queryable)]
+pub struct Counter {
+    pub channel: String,
+    pub name: String,
+    pub value: i32,
+}
+
+/// Create a counter starting at 0, nothing changes if it already exists
+pub struct CreateCounter {
+    pub channel: String,
+    pub name: String,
+}
+
+impl Message for CreateCounter {
+    type Result = Result<usize, Error>;
+}
+
+pub struct RemoveCounter {
+    pub channel: String,
+    pub name: String,
+}
+
+impl Message for RemoveCounter {
+    type Result = Result<usize, Error>;
+}
+
+/// How a counter is changed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CounterChange {
+    Add(i32),
+    Set(i32),
+}
+
+/// Change an existing counter, results in its new value or `None` if it does not exist
+pub struct ChangeCounter {
+    pub channel: String,
+    pub name: String,
+    pub change: CounterChange,
+}
+
+impl Message for ChangeCounter {
+    type Result = Result<Option<i32>, Error>;
+}
+
+/// Shortest interval of a timer, so announcements can't flood a channel
+pub const MIN_TIMER_INTERVAL_SECONDS: i32 = 60;
+
+/// Longest timer message, matches the database column
+pub const MAX_TIMER_MESSAGE_LENGTH: usize = 500;
+
+/// A message sent to a channel at an interval
+#[derive(Clone, Serialize, Queryable, Insertable)]
+#[table_name = "timers"]
+pub struct Timer {
+    pub channel: String,
+    pub name: String,
+    pub message: String,
+    pub interval_seconds: i32,
+    /// Chat messages needed since the timer was last sent before it is sent again
+    pub min_messages: i32,
+    pub enabled: bool,
+}
+
+/// Create a timer or replace it
+pub struct SetTimer(pub Timer);
+
+impl Message for SetTimer {
+    type Result = Result<usize, Error>;
+}
+
+pub struct RemoveTimer {
+    pub channel: String,
+    pub name: String,
+}
+
+impl Message for RemoveTimer {
+    type Result = Result<usize, Error>;
+}
+
+/// Pause or resume a timer
+pub struct SetTimerEnabled {
+    pub channel: String,
+    pub name: String,
+    pub enabled: bool,
+}
+
+impl Message for SetTimerEnabled {
+    type Result = Result<usize, Error>;
+}
+
+pub struct ListTimers {}
+
+impl Message for ListTimers {
+    type Result = Result<Vec<Timer>, Error>;
+}
+
+/// Load the timers again after they were changed
+/// Handled by the timer scheduler of the bot.
+pub struct ReloadTimers;
+
+impl Message for ReloadTimers {
+    type Result = ();
+}
+
+/// Settings of a spam filter rule in a channel, rules without a row are disabled
+#[derive(Clone, Serialize, Queryable, Insertable)]
+#[table_name = "moderation_rules"]
+pub struct ModerationRule {
+    pub channel: String,
+    /// Name of the rule, e.g. `links` or `caps`
+    pub rule: String,
+    pub enabled: bool,
+    /// Limit checked by the rule, its meaning depends on the rule
+    pub threshold: i32,
+    /// Users at this permission level or higher are not checked
+    pub exempt_level: i32,
+    /// Action taken the first time a user breaks the rule
+    pub action: String,
+    /// Strongest action repeated offenses escalate to
+    pub max_action: String,
+    pub timeout_seconds: i32,
+}
+
+/// Create a rule or replace its settings
+pub struct SetModerationRule(pub ModerationRule);
+
+impl Message for SetModerationRule {
+    type Result = Result<usize, Error>;
+}
+
+/// A term of a channel's filter list, like an allowed domain or a banned phrase
+#[derive(Serialize, Queryable)]
+pub struct ModerationTerm {
+    pub channel: String,
+    pub list: String,
+    pub term: String,
+}
+
+/// Longest term of a filter list, matches the database column
+pub const MAX_MODERATION_TERM_LENGTH: usize = 200;
+
+/// Add a term to a filter list, nothing changes if it is already there
+pub struct AddModerationTerm {
+    pub channel: String,
+    pub list: String,
+    pub term: String,
+}
+
+impl Message for AddModerationTerm {
+    type Result = Result<usize, Error>;
+}
+
+pub struct RemoveModerationTerm {
+    pub channel: String,
+    pub list: String,
+    pub term: String,
+}
+
+impl Message for RemoveModerationTerm {
+    type Result = Result<usize, Error>;
+}
+
+/// Longest quote, matches the database column
+pub const MAX_QUOTE_LENGTH: usize = 400;
+
+/// Longest game name of a quote, matches the database column
+pub const MAX_QUOTE_GAME_LENGTH: usize = 100;
+
+/// Something said on stream, numbered from 1 in each channel
+#[derive(Serialize, Queryable)]
+pub struct Quote {
+    pub channel: String,
+    pub number: i32,
+    pub quote: String,
+    /// User who added the quote
+    pub added_by: String,
+    pub added_at: NaiveDateTime,
+    /// Game or category being played when the quote was said
+    pub game: Option<String>,
+}
+
+/// Add a quote with the next number of the channel, results in that number
+pub struct AddQuote {
+    pub channel: String,
+    pub quote: String,
+    pub added_by: String,
+    pub game: Option<String>,
+}
+
+impl Message for AddQuote {
+    type Result = Result<i32, Error>;
+}
+
+/// Find a quote by number, or a random one of the channel without a number
+pub struct GetQuote {
+    pub channel: String,
+    pub number: Option<i32>,
+}
+
+impl Message for GetQuote {
+    type Result = Result<Option<Quote>, Error>;
+}
+
+/// Quotes of a channel containing some text, by number
+pub struct SearchQuotes {
+    pub channel: String,
+    pub text: String,
+}
+
+impl Message for SearchQuotes {
+    type Result = Result<Vec<Quote>, Error>;
+}
+
+/// Replace the text and game of a quote
+pub struct EditQuote {
+    pub channel: String,
+    pub number: i32,
+    pub quote: String,
+    pub game: Option<String>,
+}
+
+impl Message for EditQuote {
+    type Result = Result<usize, Error>;
+}
+
+/// Remove a quote, the numbers of the others don't change
+pub struct RemoveQuote {
+    pub channel: String,
+    pub number: i32,
+}
+
+impl Message for RemoveQuote {
+    type Result = Result<usize, Error>;
+}
+
+pub struct ListQuotes {}
+
+impl Message for ListQuotes {
+    type Result = Result<Vec<Quote>, Error>;
+}
+
+/// Longest poll question, matches the database column
+pub const MAX_POLL_QUESTION_LENGTH: usize = 200;
+
+/// Longest poll option, matches the database column
+pub const MAX_POLL_OPTION_LENGTH: usize = 100;
+
+/// Save a finished poll and its votes to the history
+pub struct SavePoll {
+    pub channel: String,
+    pub question: String,
+    pub started_by: String,
+    pub duration_seconds: i32,
+    /// Text and votes of each option, in order
+    pub options: Vec<(String, i32)>,
+}
+
+impl Message for SavePoll {
+    type Result = Result<(), Error>;
+}
+
+/// Longest raffle keyword, matches the database column
+pub const MAX_RAFFLE_KEYWORD_LENGTH: usize = 50;
+
+/// A raffle, kept with its entries and winners so draws can be audited
+/// Draws can be repeated from the seed and the entries in the order they entered.
+#[derive(Serialize, Queryable)]
+pub struct Raffle {
+    pub id: i32,
+    pub channel: String,
+    /// Message users send to enter
+    pub keyword: String,
+    /// Tickets of each subscriber, other users have one
+    pub sub_luck: i32,
+    /// Hex seed of the random generator drawing the winners
+    pub seed: String,
+    pub opened_by: String,
+    pub opened_at: NaiveDateTime,
+    /// When entries were closed, `None` while the raffle is open
+    pub closed_at: Option<NaiveDateTime>,
+    /// Points each user paid to enter
+    pub cost: i32,
+}
+
+#[derive(Serialize, Queryable)]
+pub struct RaffleEntry {
+    pub raffle_id: i32,
+    pub user: String,
+    /// Order in which the user entered, starting at 0
+    pub position: i32,
+    pub tickets: i32,
+}
+
+#[derive(Serialize, Queryable)]
+pub struct RaffleWinner {
+    pub raffle_id: i32,
+    /// Order in which the winner was drawn, starting at 1, including redraws
+    pub draw: i32,
+    pub user: String,
+    pub drawn_at: NaiveDateTime,
+    /// The winner responded in time, or did not have to
+    pub claimed: bool,
+}
+
+/// Save a newly opened raffle, results in its id
+pub struct OpenRaffle {
+    pub channel: String,
+    pub keyword: String,
+    pub sub_luck: i32,
+    pub seed: String,
+    pub opened_by: String,
+    pub cost: i32,
+}
+
+impl Message for OpenRaffle {
+    type Result = Result<i32, Error>;
+}
+
+/// Save the entry of a user in a raffle
+pub struct AddRaffleEntry {
+    pub raffle_id: i32,
+    pub user: String,
+    pub position: i32,
+    pub tickets: i32,
+}
+
+impl Message for AddRaffleEntry {
+    type Result = Result<usize, Error>;
+}
+
+/// Save the time entries of a raffle were closed
+pub struct CloseRaffle {
+    pub raffle_id: i32,
+}
+
+impl Message for CloseRaffle {
+    type Result = Result<usize, Error>;
+}
+
+/// Save a drawn winner of a raffle
+pub struct AddRaffleWinner {
+    pub raffle_id: i32,
+    pub draw: i32,
+    pub user: String,
+    pub claimed: bool,
+}
+
+impl Message for AddRaffleWinner {
+    type Result = Result<usize, Error>;
+}
+
+/// Save that a drawn winner responded in time
+pub struct ClaimRaffleWin {
+    pub raffle_id: i32,
+    pub draw: i32,
+}
+
+impl Message for ClaimRaffleWin {
+    type Result = Result<usize, Error>;
+}
+
+/// A raffle with its number of entries and its winners, as shown in the dashboard
+#[derive(Serialize)]
+pub struct RaffleHistory {
+    #[serde(flatten)]
+    pub raffle: Raffle,
+    pub entries: i64,
+    pub winners: Vec<RaffleWinner>,
+}
+
+/// Latest raffles of every channel, newest first
+pub struct ListRaffles {
+    pub limit: i64,
+}
+
+impl Message for ListRaffles {
+    type Result = Result<Vec<RaffleHistory>, Error>;
+}
+
+/// Entries of a raffle in the order they entered
+pub struct ListRaffleEntries {
+    pub raffle_id: i32,
+}
+
+impl Message for ListRaffleEntries {
+    type Result = Result<Vec<RaffleEntry>, Error>;
+}
+
+/// Settings of a channel, channels without a row use the defaults
+#[derive(Serialize, Queryable)]
+pub struct ChannelSettings {
+    pub channel: String,
+    pub command_prefix: String,
+    /// Reply when a user is not allowed to use a command instead of ignoring them
+    pub deny_reply: bool,
+    /// Seconds a permitted user has to post links
+    pub permit_seconds: i32,
+    /// Messages with links a permitted user can post
+    pub permit_links: i32,
+    /// Points earned by viewers at every payout while the channel is live
+    pub points_presence: i32,
+    /// Points earned at every payout by viewers who chatted since the last one
+    pub points_chat: i32,
+    /// Subscribers earn this many times more points
+    pub points_sub_multiplier: i32,
+}
+
+/// Set the prefix that starts commands in a channel
+pub struct SetCommandPrefix {
+    pub channel: String,
+    pub prefix: String,
+}
+
+impl Message for SetCommandPrefix {
+    type Result = Result<usize, Error>;
+}
+
+/// Set whether users are told they are not allowed to use a command
+pub struct SetDenyReply {
+    pub channel: String,
+    pub deny_reply: bool,
+}
+
+impl Message for SetDenyReply {
+    type Result = Result<usize, Error>;
+}
+
+/// Set how long a permit lasts and how many links it allows
+pub struct SetPermitSettings {
+    pub channel: String,
+    pub permit_seconds: i32,
+    pub permit_links: i32,
+}
+
+impl Message for SetPermitSettings {
+    type Result = Result<usize, Error>;
+}
+
+/// Points of a user in a channel, zero if they never earned any
+pub struct GetPoints {
+    pub channel: String,
+    pub user: String,
+}
+
+impl Message for GetPoints {
+    type Result = Result<i32, Error>;
+}
+
+/// Points earned by viewers since the last payout, saved together
+pub struct EarnPoints {
+    /// Channel, user and points earned
+    pub earnings: Vec<(String, String, i32)>,
+}
+
+impl Message for EarnPoints {
+    type Result = Result<(), Error>;
+}
+
+/// Add points to a user, or remove them with a negative amount, results in the new balance
+/// Balances never go below zero.
+pub struct AddPoints {
+    pub channel: String,
+    pub user: String,
+    pub amount: i32,
+}
+
+impl Message for AddPoints {
+    type Result = Result<i32, Error>;
+}
+
+/// Remove points from a user only if they have enough, results in whether they did
+pub struct SpendPoints {
+    pub channel: String,
+    pub user: String,
+    pub amount: i32,
+}
+
+impl Message for SpendPoints {
+    type Result = Result<bool, Error>;
+}
+
+/// Move points from a user to another one if they have enough, results in whether they did
+pub struct GivePoints {
+    pub channel: String,
+    pub from: String,
+    pub to: String,
+    pub amount: i32,
+}
+
+impl Message for GivePoints {
+    type Result = Result<bool, Error>;
+}
+
+/// Set how many points viewers of a channel earn
+pub struct SetPointRates {
+    pub channel: String,
+    pub presence: i32,
+    pub chat: i32,
+    pub sub_multiplier: i32,
+}
+
+impl Message for SetPointRates {
+    type Result = Result<usize, Error>;
+}
+
+/// A user waiting in the viewer queue of a channel
+#[derive(Serialize, Queryable)]
+pub struct QueueEntry {
+    /// Increases in the order users joined
+    pub id: i32,
+    pub channel: String,
+    pub user: String,
+    /// Subscribers go first when the queue gives them priority
+    pub subscriber: bool,
+    pub joined_at: NaiveDateTime,
+}
+
+/// The viewer queue of a channel, in the order users are picked
+#[derive(Serialize)]
+pub struct ViewerQueue {
+    pub channel: String,
+    /// Users can join
+    pub open: bool,
+    pub sub_priority: bool,
+    pub entries: Vec<QueueEntry>,
+}
+
+impl ViewerQueue {
+    /// Position of a user, starting at 1
+    pub fn position(&self, user: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| entry.user == user)
+            .map(|index| index + 1)
+    }
+}
+
+/// What happened when a user tried to join a queue
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueueJoin {
+    /// Joined at the position
+    Joined(usize),
+    /// Was already at the position
+    AlreadyIn(usize),
+    Closed,
+}
+
+/// Add a user at the end of the queue of a channel, if it is open
+pub struct JoinQueue {
+    pub channel: String,
+    pub user: String,
+    pub subscriber: bool,
+}
+
+impl Message for JoinQueue {
+    type Result = Result<QueueJoin, Error>;
+}
+
+/// Remove a user from the queue of a channel
+pub struct LeaveQueue {
+    pub channel: String,
+    pub user: String,
+}
+
+impl Message for LeaveQueue {
+    type Result = Result<usize, Error>;
+}
+
+pub struct GetQueue {
+    pub channel: String,
+}
+
+impl Message for GetQueue {
+    type Result = Result<ViewerQueue, Error>;
+}
+
+/// Remove and return the first users of the queue of a channel
+pub struct NextInQueue {
+    pub channel: String,
+    pub count: i64,
+}
+
+impl Message for NextInQueue {
+    type Result = Result<Vec<QueueEntry>, Error>;
+}
+
+/// Open or close the queue of a channel, or change whether subscribers go first
+pub struct SetQueueState {
+    pub channel: String,
+    pub open: Option<bool>,
+    pub sub_priority: Option<bool>,
+}
+
+impl Message for SetQueueState {
+    type Result = Result<usize, Error>;
+}
+
+/// Remove every user from the queue of a channel
+pub struct ClearQueue {
+    pub channel: String,
+}
+
+impl Message for ClearQueue {
+    type Result = Result<usize, Error>;
+}
+
+/// Queues of every channel that has one
+pub struct ListQueues {}
+
+impl Message for ListQueues {
+    type Result = Result<Vec<ViewerQueue>, Error>;
+}
@@ -3,5 +3,183 @@ table! {
         channel -> Varchar,
         match_expr -> Varchar,
         command -> Varchar,
+        permission -> Integer,
+        use_count -> Integer,
+        global_cooldown -> Integer,
+        user_cooldown -> Integer,
+        cooldown_mod_exempt -> Bool,
+        match_mode -> Varchar,
+        script -> Nullable<Text>,
+        response_mode -> Varchar,
+        response_weight -> Integer,
+    }
+}
+
+table! {
+    channel_settings (channel) {
+        channel -> Varchar,
+        command_prefix -> Varchar,
+        deny_reply -> Bool,
+        permit_seconds -> Integer,
+        permit_links -> Integer,
+        points_presence -> Integer,
+        points_chat -> Integer,
+        points_sub_multiplier -> Integer,
+    }
+}
+
+
+table! {
+    script_store (channel, store_key) {
+        channel -> Varchar,
+        store_key -> Varchar,
+        store_value -> Varchar,
+    }
+}
+
+table! {
+    counters (channel, name) {
+        channel -> Varchar,
+        name -> Varchar,
+        value -> Integer,
+    }
+}
+
+table! {
+    command_aliases (channel, alias) {
+        channel -> Varchar,
+        alias -> Varchar,
+        match_expr -> Varchar,
+    }
+}
+
+table! {
+    timers (channel, name) {
+        channel -> Varchar,
+        name -> Varchar,
+        message -> Varchar,
+        interval_seconds -> Integer,
+        min_messages -> Integer,
+        enabled -> Bool,
+    }
+}
+table! {
+    moderation_rules (channel, rule) {
+        channel -> Varchar,
+        rule -> Varchar,
+        enabled -> Bool,
+        threshold -> Integer,
+        exempt_level -> Integer,
+        action -> Varchar,
+        max_action -> Varchar,
+        timeout_seconds -> Integer,
+    }
+}
+
+table! {
+    moderation_terms (channel, list, term) {
+        channel -> Varchar,
+        list -> Varchar,
+        term -> Varchar,
+    }
+}
+
+table! {
+    quotes (channel, number) {
+        channel -> Varchar,
+        number -> Integer,
+        quote -> Varchar,
+        added_by -> Varchar,
+        added_at -> Timestamp,
+        game -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    polls (id) {
+        id -> Integer,
+        channel -> Varchar,
+        question -> Varchar,
+        started_by -> Varchar,
+        duration_seconds -> Integer,
+        ended_at -> Timestamp,
+    }
+}
+
+table! {
+    poll_options (poll_id, position) {
+        poll_id -> Integer,
+        position -> Integer,
+        option_text -> Varchar,
+        votes -> Integer,
+    }
+}
+
+table! {
+    raffles (id) {
+        id -> Integer,
+        channel -> Varchar,
+        keyword -> Varchar,
+        sub_luck -> Integer,
+        seed -> Varchar,
+        opened_by -> Varchar,
+        opened_at -> Timestamp,
+        closed_at -> Nullable<Timestamp>,
+        cost -> Integer,
+    }
+}
+
+table! {
+    raffle_entries (raffle_id, user) {
+        raffle_id -> Integer,
+        user -> Varchar,
+        position -> Integer,
+        tickets -> Integer,
+    }
+}
+
+table! {
+    raffle_winners (raffle_id, draw) {
+        raffle_id -> Integer,
+        draw -> Integer,
+        user -> Varchar,
+        drawn_at -> Timestamp,
+        claimed -> Bool,
+    }
+}
+
+table! {
+    points (channel, user) {
+        channel -> Varchar,
+        user -> Varchar,
+        balance -> Integer,
+    }
+}
+
+table! {
+    queues (channel) {
+        channel -> Varchar,
+        open -> Bool,
+        sub_priority -> Bool,
+    }
+}
+
+table! {
+    queue_entries (id) {
+        id -> Integer,
+        channel -> Varchar,
+        user -> Varchar,
+        subscriber -> Bool,
+        joined_at -> Timestamp,
+    }
+}
+
+table! {
+    command_responses (id) {
+        id -> Integer,
+        channel -> Varchar,
+        match_expr -> Varchar,
+        response -> Varchar,
+        weight -> Integer,
     }
 }
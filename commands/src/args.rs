@@ -0,0 +1,464 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// Error found while parsing the arguments of a command
+#[derive(Debug, Fail, PartialEq)]
+pub enum ArgsError {
+    #[fail(display = "quote at position {} is never closed", _0)]
+    UnclosedQuote(usize),
+    #[fail(display = "{} is missing", _0)]
+    Missing(&'static str),
+    #[fail(display = "{} should be {}, not {}", name, expected, value)]
+    Invalid {
+        name: &'static str,
+        expected: String,
+        value: String,
+    },
+    #[fail(display = "--{} is not an option", _0)]
+    UnknownOption(String),
+    #[fail(display = "--{} needs a value", _0)]
+    MissingValue(&'static str),
+    #[fail(display = "{} was not expected", _0)]
+    Unexpected(String),
+}
+
+/// A word of the arguments, quotes and escapes already removed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub value: String,
+    /// Byte offset of the token in the arguments
+    pub start: usize,
+    /// Quoted tokens are never options
+    pub quoted: bool,
+}
+
+/// Split arguments on whitespace, keeping "quoted text" and 'quoted text' together
+/// A backslash escapes the next character, inside or outside quotes.
+pub fn tokenize(input: &str) -> Result<Vec<Token>, ArgsError> {
+    let mut tokens = Vec::new();
+    let mut position = skip_whitespace(input, 0);
+
+    while position < input.len() {
+        let (token, end) = read_token(input, position)?;
+        tokens.push(token);
+        position = skip_whitespace(input, end);
+    }
+
+    Ok(tokens)
+}
+
+/// Read the token starting at `start`, returning it with the offset right after it
+/// Quotes only open at the start of a token, so words like I'm are kept as written.
+fn read_token(input: &str, start: usize) -> Result<(Token, usize), ArgsError> {
+    let mut value = String::new();
+    let mut quoted = false;
+    let mut quote: Option<(char, usize)> = None;
+    let mut end = input.len();
+    let mut chars = input[start..]
+        .char_indices()
+        .map(|(offset, c)| (start + offset, c));
+
+    while let Some((index, c)) = chars.next() {
+        match (quote, c) {
+            (None, c) if c.is_whitespace() => {
+                end = index;
+                break;
+            }
+            (_, '\\') => match chars.next() {
+                Some((_, escaped)) => value.push(escaped),
+                None => value.push('\\'),
+            },
+            (None, '"') | (None, '\'') if index == start => {
+                quote = Some((c, index));
+                quoted = true;
+            }
+            (Some((open, _)), c) if c == open => quote = None,
+            (_, c) => value.push(c),
+        }
+    }
+
+    if let Some((_, position)) = quote {
+        return Err(ArgsError::UnclosedQuote(position));
+    }
+
+    Ok((
+        Token {
+            value,
+            start,
+            quoted,
+        },
+        end,
+    ))
+}
+
+/// Offset of the first character from `from` that is not whitespace, the length if there is none
+fn skip_whitespace(input: &str, from: usize) -> usize {
+    input[from..]
+        .find(|c: char| !c.is_whitespace())
+        .map_or(input.len(), |offset| from + offset)
+}
+
+/// What an argument accepts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArgKind {
+    /// A single word, or quoted text
+    Word,
+    /// A Twitch user name, with or without @
+    User,
+    Integer,
+    /// Seconds, or a duration like 1h30m
+    Duration,
+    /// One of the given words
+    Choice(&'static [&'static str]),
+    /// Everything left, exactly as it was written
+    Rest,
+    /// An option that is either present or not, only for `Arg::flag`
+    Flag,
+}
+
+impl ArgKind {
+    fn expected(&self) -> String {
+        match *self {
+            ArgKind::Word | ArgKind::Rest | ArgKind::Flag => "text".to_owned(),
+            ArgKind::User => "a user name".to_owned(),
+            ArgKind::Integer => "a number".to_owned(),
+            ArgKind::Duration => "a duration like 30, 5m or 1h30m".to_owned(),
+            ArgKind::Choice(choices) => choices.join(" or "),
+        }
+    }
+
+    fn parse(&self, name: &'static str, value: &str) -> Result<Value, ArgsError> {
+        let parsed = match *self {
+            ArgKind::Word | ArgKind::Rest => Some(Value::Text(value.to_owned())),
+            ArgKind::User => parse_user(value).map(Value::Text),
+            ArgKind::Integer => value.parse().ok().map(Value::Integer),
+            ArgKind::Duration => parse_duration(value).map(Value::Duration),
+            ArgKind::Choice(choices) => choices
+                .iter()
+                .find(|choice| choice.eq_ignore_ascii_case(value))
+                .map(|choice| Value::Text((*choice).to_owned())),
+            ArgKind::Flag => None,
+        };
+
+        parsed.ok_or_else(|| ArgsError::Invalid {
+            name,
+            expected: self.expected(),
+            value: value.to_owned(),
+        })
+    }
+}
+
+/// An argument a command takes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arg {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub required: bool,
+    /// Given as `--name value` instead of by position
+    pub option: bool,
+}
+
+impl Arg {
+    pub fn required(name: &'static str, kind: ArgKind) -> Self {
+        Arg {
+            name,
+            kind,
+            required: true,
+            option: false,
+        }
+    }
+
+    pub fn optional(name: &'static str, kind: ArgKind) -> Self {
+        Arg {
+            name,
+            kind,
+            required: false,
+            option: false,
+        }
+    }
+
+    /// `--name value`, anywhere before a rest argument
+    pub fn option(name: &'static str, kind: ArgKind) -> Self {
+        Arg {
+            name,
+            kind,
+            required: false,
+            option: true,
+        }
+    }
+
+    /// `--name` alone
+    pub fn flag(name: &'static str) -> Self {
+        Self::option(name, ArgKind::Flag)
+    }
+
+    fn usage(&self) -> String {
+        let value = match self.kind {
+            ArgKind::Choice(choices) => choices.join("|"),
+            ArgKind::Rest => format!("{}...", self.name),
+            _ => self.name.to_owned(),
+        };
+
+        match (self.option, self.kind, self.required) {
+            (true, ArgKind::Flag, _) => format!("[--{}]", self.name),
+            (true, _, _) => format!("[--{} {}]", self.name, value),
+            (false, _, true) => format!("<{}>", value),
+            (false, _, false) => format!("[{}]", value),
+        }
+    }
+}
+
+/// How a command is used, generated from its arguments
+pub fn usage(name: &str, args: &[Arg]) -> String {
+    args.iter().fold(name.to_owned(), |usage, arg| usage + " " + &arg.usage())
+}
+
+/// A parsed argument
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Integer(i64),
+    Duration(Duration),
+}
+
+/// Arguments of a command, by name
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Args {
+    values: HashMap<&'static str, Value>,
+    flags: HashSet<&'static str>,
+}
+
+impl Args {
+    /// Text of a word, user, choice or rest argument, empty if it was not given
+    pub fn text(&self, name: &str) -> &str {
+        match self.values.get(name) {
+            Some(Value::Text(text)) => text,
+            _ => "",
+        }
+    }
+
+    pub fn integer(&self, name: &str) -> Option<i64> {
+        match self.values.get(name) {
+            Some(Value::Integer(integer)) => Some(*integer),
+            _ => None,
+        }
+    }
+
+    pub fn duration(&self, name: &str) -> Option<Duration> {
+        match self.values.get(name) {
+            Some(Value::Duration(duration)) => Some(*duration),
+            _ => None,
+        }
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.values.contains_key(name) || self.flags.contains(name)
+    }
+}
+
+/// Parse arguments following the given specs
+/// Positional arguments are taken in order, options can be anywhere before a rest argument.
+/// Once the rest argument starts, even text looking like an unknown option belongs to it.
+/// A rest argument is never split in tokens, so quotes in free text don't need to be closed.
+pub fn parse(specs: &[Arg], input: &str) -> Result<Args, ArgsError> {
+    let mut args = Args::default();
+    let mut positional = specs.iter().filter(|spec| !spec.option).peekable();
+    let mut position = skip_whitespace(input, 0);
+
+    while position < input.len() {
+        let rest = &input[position..];
+        let is_option =
+            rest.starts_with("--") && rest[2..].starts_with(|c: char| !c.is_whitespace());
+
+        // Only known options are read where a rest argument can start, so text like --> is kept
+        let at_rest = positional.peek().map_or(false, |spec| spec.kind == ArgKind::Rest);
+        if at_rest && !(is_option && is_known_option(specs, &rest[2..])) {
+            if let Some(spec) = positional.next() {
+                args.values
+                    .insert(spec.name, Value::Text(rest.trim_right().to_owned()));
+            }
+            break;
+        }
+
+        let (token, end) = read_token(input, position)?;
+        position = skip_whitespace(input, end);
+
+        if is_option {
+            let name = &token.value[2..];
+            let spec = specs
+                .iter()
+                .find(|spec| spec.option && spec.name == name)
+                .ok_or_else(|| ArgsError::UnknownOption(name.to_owned()))?;
+
+            if spec.kind == ArgKind::Flag {
+                args.flags.insert(spec.name);
+            } else {
+                if position >= input.len() {
+                    return Err(ArgsError::MissingValue(spec.name));
+                }
+                let (value, end) = read_token(input, position)?;
+                position = skip_whitespace(input, end);
+                args.values
+                    .insert(spec.name, spec.kind.parse(spec.name, &value.value)?);
+            }
+            continue;
+        }
+
+        let spec = positional
+            .next()
+            .ok_or_else(|| ArgsError::Unexpected(token.value.clone()))?;
+
+        args.values
+            .insert(spec.name, spec.kind.parse(spec.name, &token.value)?);
+    }
+
+    match positional.find(|spec| spec.required) {
+        Some(missing) => Err(ArgsError::Missing(missing.name)),
+        None => Ok(args),
+    }
+}
+
+/// Whether `text`, following `--`, starts with the name of one of the options
+fn is_known_option(specs: &[Arg], text: &str) -> bool {
+    let name = text.split_whitespace().next().unwrap_or("");
+    specs.iter().any(|spec| spec.option && spec.name == name)
+}
+
+/// User name without @, lowercased like IRC user names
+fn parse_user(value: &str) -> Option<String> {
+    let name = value.trim_left_matches('@');
+    let valid = !name.is_empty()
+        && name.len() <= 25
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if valid {
+        Some(name.to_lowercase())
+    } else {
+        None
+    }
+}
+
+/// `90`, `90s`, `5m`, `1h30m` or `2d`, plain numbers are seconds
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return None,
+        };
+        let amount: u64 = number.parse().ok()?;
+        seconds = seconds.checked_add(amount.checked_mul(unit)?)?;
+        number.clear();
+    }
+
+    if number.is_empty() && !value.is_empty() {
+        Some(Duration::from_secs(seconds))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(input: &str) -> Vec<String> {
+        tokenize(input)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.value)
+            .collect()
+    }
+
+    fn set_specs() -> Vec<Arg> {
+        vec![
+            Arg::required("match_expression", ArgKind::Word),
+            Arg::required("command", ArgKind::Rest),
+            Arg::flag("global"),
+        ]
+    }
+
+    #[test]
+    fn quotes_group_words() {
+        assert_eq!(values(r#"one "two three" 'four five'"#), vec!["one", "two three", "four five"]);
+        assert_eq!(values(r#""""#), vec![""]);
+    }
+
+    #[test]
+    fn contractions_are_not_quotes() {
+        assert_eq!(values("I'm here, it's fine"), vec!["I'm", "here,", "it's", "fine"]);
+        assert_eq!(values(r#"say "I'm here""#), vec!["say", "I'm here"]);
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(values(r#"a\ b \"c\" "d \" e" f\"#), vec!["a b", "\"c\"", "d \" e", "f\\"]);
+    }
+
+    #[test]
+    fn unclosed_quotes() {
+        assert_eq!(tokenize(r#"one "two"#), Err(ArgsError::UnclosedQuote(4)));
+        assert_eq!(tokenize("'two"), Err(ArgsError::UnclosedQuote(0)));
+    }
+
+    #[test]
+    fn rest_is_taken_as_written() {
+        let args = parse(&set_specs(), "hi I'm here, \"really ").unwrap();
+        assert_eq!(args.text("match_expression"), "hi");
+        assert_eq!(args.text("command"), "I'm here, \"really");
+        assert!(!args.has("global"));
+    }
+
+    #[test]
+    fn options_before_rest() {
+        let specs = vec![
+            Arg::required("name", ArgKind::Word),
+            Arg::option("weight", ArgKind::Integer),
+            Arg::flag("global"),
+            Arg::optional("value", ArgKind::Rest),
+        ];
+
+        let args = parse(&specs, "8ball --weight 3 --global It's certain").unwrap();
+        assert_eq!(args.text("name"), "8ball");
+        assert_eq!(args.integer("weight"), Some(3));
+        assert!(args.has("global"));
+        assert_eq!(args.text("value"), "It's certain");
+
+        assert_eq!(parse(&specs, "8ball --weight"), Err(ArgsError::MissingValue("weight")));
+        assert_eq!(
+            parse(&specs, "--other 8ball"),
+            Err(ArgsError::UnknownOption("other".to_owned()))
+        );
+    }
+
+    #[test]
+    fn rest_keeps_dashes() {
+        let args = parse(&set_specs(), "arrow --> there").unwrap();
+        assert_eq!(args.text("command"), "--> there");
+
+        let args = parse(&set_specs(), "arrow --global --other --global").unwrap();
+        assert!(args.has("global"));
+        assert_eq!(args.text("command"), "--other --global");
+    }
+
+    #[test]
+    fn missing_and_unexpected() {
+        assert_eq!(parse(&set_specs(), "hi"), Err(ArgsError::Missing("command")));
+        let specs = vec![Arg::optional("page", ArgKind::Integer)];
+        assert_eq!(parse(&specs, "1 2"), Err(ArgsError::Unexpected("2".to_owned())));
+        assert_eq!(parse(&specs, "").unwrap().integer("page"), None);
+    }
+}
@@ -0,0 +1,56 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::AddPoints as AddUserPoints;
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+
+/// Most points added or removed at once
+const MAX_POINTS_CHANGE: i64 = 1_000_000;
+
+/// Add points to a user, or remove them with a negative amount
+pub struct AddPoints;
+
+impl BotCommand for AddPoints {
+    fn name(&self) -> &'static str {
+        "addpoints"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("user", ArgKind::User),
+            Arg::required("amount", ArgKind::Integer),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let user = args.text("user").to_owned();
+        let amount = args.integer("amount").unwrap_or(0);
+
+        if amount == 0 || amount.abs() > MAX_POINTS_CHANGE {
+            ctx.reply(format!(
+                "Points are added or removed {} at most at once!",
+                MAX_POINTS_CHANGE
+            ));
+            return done();
+        }
+
+        let add_points = AddUserPoints {
+            channel: ctx.channel.clone(),
+            user: user.clone(),
+            amount: amount as i32,
+        };
+
+        Box::new(ctx.query(add_points).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(balance) => ctx.reply(format!("{} now has {} points!", user, balance)),
+                Err(err) => ctx.reply_failure("Points could not be changed", err),
+            }
+            Ok(())
+        }))
+    }
+}
@@ -0,0 +1,98 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::{CreateAlias, RemoveAlias};
+use command::{done, BotCommand, CommandContext, CommandFuture, KeywordUse};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+
+/// Add or remove another trigger for a stored command
+pub struct Alias;
+
+impl BotCommand for Alias {
+    fn name(&self) -> &'static str {
+        "alias"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("action", ArgKind::Choice(&["add", "remove"])),
+            Arg::required("alias", ArgKind::Word),
+            Arg::optional("match_expression", ArgKind::Word),
+            Arg::flag("global"),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let channel = match ctx.target_channel(self, &args) {
+            Some(channel) => channel,
+            None => return done(),
+        };
+
+        let alias = args.text("alias").to_owned();
+
+        if args.text("action") == "remove" {
+            let remove = RemoveAlias {
+                channel,
+                alias: alias.clone(),
+            };
+
+            return Box::new(ctx.query(remove).then(move |result| -> Result<(), Error> {
+                match result {
+                    Ok(removed) if removed > 0 => ctx.reply(format!("Alias {} has been removed!", alias)),
+                    Ok(_) => ctx.reply(format!("Alias {} does not exist!", alias)),
+                    Err(err) => ctx.reply_failure("Alias could not be removed", err),
+                }
+                Ok(())
+            }));
+        }
+
+        let target = args.text("match_expression").to_owned();
+        if target.is_empty() {
+            ctx.usage(self);
+            return done();
+        }
+
+        let target_exists = ctx
+            .commands
+            .read()
+            .expect("READ ERROR")
+            .find(&channel, &target)
+            .is_some();
+
+        if !target_exists {
+            ctx.reply(format!("Command {} does not exist!", target));
+            return done();
+        }
+
+        let taken = ctx
+            .keyword_uses(&channel, &alias)
+            .into_iter()
+            .find(|keyword_use| *keyword_use != KeywordUse::Global);
+
+        if let Some(keyword_use) = taken {
+            ctx.reply(format!("{} is already {}!", alias, keyword_use.describe()));
+            return done();
+        }
+
+        let create = CreateAlias {
+            channel,
+            alias: alias.clone(),
+            match_expr: target.clone(),
+        };
+
+        Box::new(ctx.query(create).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(created) if created > 0 => {
+                    ctx.reply(format!("{}{} now uses {}!", ctx.prefix, alias, target))
+                }
+                Ok(_) => ctx.reply(format!("Alias {} already exists!", alias)),
+                Err(err) => ctx.reply_failure("Alias could not be added", err),
+            }
+            Ok(())
+        }))
+    }
+}
@@ -0,0 +1,73 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::SetCommandCooldowns;
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+use std::time::Duration;
+
+/// Set the cooldowns of a stored command
+pub struct Cooldown;
+
+impl BotCommand for Cooldown {
+    fn name(&self) -> &'static str {
+        "cooldown"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("match_expression", ArgKind::Word),
+            Arg::required("global", ArgKind::Duration),
+            Arg::optional("per_user", ArgKind::Duration),
+            Arg::flag("strict"),
+            Arg::flag("global"),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let channel = match ctx.target_channel(self, &args) {
+            Some(channel) => channel,
+            None => return done(),
+        };
+
+        let keyword = args.text("match_expression").to_owned();
+        let global_cooldown = seconds(args.duration("global"));
+        let user_cooldown = seconds(args.duration("per_user"));
+        // Moderators are exempt unless the cooldown is strict
+        let mod_exempt = !args.has("strict");
+
+        if let Some(required) = ctx.protected_level(&channel, &keyword) {
+            ctx.deny(self.name(), required);
+            return done();
+        }
+
+        let set_cooldowns = SetCommandCooldowns {
+            channel,
+            match_expr: keyword.clone(),
+            global_cooldown,
+            user_cooldown,
+            cooldown_mod_exempt: mod_exempt,
+        };
+
+        Box::new(ctx.query(set_cooldowns).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(changed) if changed > 0 => ctx.reply(format!(
+                    "{} now has a {}s cooldown and a {}s cooldown per user!",
+                    keyword, global_cooldown, user_cooldown
+                )),
+                Ok(_) => ctx.reply(format!("Command {} does not exist!", keyword)),
+                Err(err) => ctx.reply_failure("Cooldown could not be set", err),
+            }
+            Ok(())
+        }))
+    }
+}
+
+/// Whole seconds stored for a cooldown, no cooldown when it is missing
+fn seconds(duration: Option<Duration>) -> i32 {
+    duration.map_or(0, |duration| duration.as_secs().min(i32::max_value() as u64) as i32)
+}
@@ -0,0 +1,82 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::{CreateCounter, RemoveCounter};
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use counter::{valid_name, MAX_COUNTER_NAME_LENGTH};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+
+/// Create a counter used from chat, or remove it
+pub struct Counter;
+
+impl BotCommand for Counter {
+    fn name(&self) -> &'static str {
+        "counter"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![Arg::required("name", ArgKind::Word), Arg::flag("remove")]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let name = args.text("name").to_lowercase();
+
+        if args.has("remove") {
+            let remove = RemoveCounter {
+                channel: ctx.channel.clone(),
+                name: name.clone(),
+            };
+
+            return Box::new(ctx.query(remove).then(move |result| -> Result<(), Error> {
+                match result {
+                    Ok(removed) if removed > 0 => {
+                        ctx.send_counter_removed(&name)?;
+                        ctx.reply(format!("Counter {} has been removed!", name));
+                    }
+                    Ok(_) => ctx.reply(format!("Counter {} does not exist!", name)),
+                    Err(err) => ctx.reply_failure("Counter could not be removed", err),
+                }
+                Ok(())
+            }));
+        }
+
+        if !valid_name(&name) {
+            ctx.reply(format!(
+                "Counter names have 1 to {} letters, digits or underscores!",
+                MAX_COUNTER_NAME_LENGTH
+            ));
+            return done();
+        }
+
+        // Counters are used before stored commands, even global ones, so they can't share a name
+        if let Some(keyword_use) = ctx.keyword_uses(&ctx.channel, &name).first() {
+            ctx.reply(format!("{} is already {}!", name, keyword_use.describe()));
+            return done();
+        }
+
+        let create = CreateCounter {
+            channel: ctx.channel.clone(),
+            name: name.clone(),
+        };
+
+        Box::new(ctx.query(create).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(created) if created > 0 => {
+                    ctx.send_counter_update(&name, 0)?;
+                    ctx.reply(format!(
+                        "Counter created, use {p}{n}, {p}{n}+, {p}{n}- or {p}{n} set value!",
+                        p = ctx.prefix,
+                        n = name
+                    ));
+                }
+                Ok(_) => ctx.reply(format!("Counter {} already exists!", name)),
+                Err(err) => ctx.reply_failure("Counter could not be created", err),
+            }
+            Ok(())
+        }))
+    }
+}
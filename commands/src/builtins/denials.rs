@@ -0,0 +1,41 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::SetDenyReply;
+use command::{BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+
+/// Choose whether users are told they can't use a command or silently ignored
+pub struct Denials;
+
+impl BotCommand for Denials {
+    fn name(&self) -> &'static str {
+        "denials"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Broadcaster
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![Arg::required("answer", ArgKind::Choice(&["reply", "silent"]))]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let deny_reply = args.text("answer") == "reply";
+
+        let set_deny_reply = SetDenyReply {
+            channel: ctx.channel.clone(),
+            deny_reply,
+        };
+
+        Box::new(ctx.query(set_deny_reply).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(_) if deny_reply => ctx.reply("Denied commands will now be answered"),
+                Ok(_) => ctx.reply("Denied commands will now be ignored"),
+                Err(err) => ctx.reply_failure("Setting could not be changed", err),
+            }
+            Ok(())
+        }))
+    }
+}
@@ -0,0 +1,69 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::EditCommand;
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+use template::Template;
+
+/// Replace the response of an existing command
+pub struct Edit;
+
+impl BotCommand for Edit {
+    fn name(&self) -> &'static str {
+        "edit"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("match_expression", ArgKind::Word),
+            Arg::required("command", ArgKind::Rest),
+            Arg::flag("global"),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let channel = match ctx.target_channel(self, &args) {
+            Some(channel) => channel,
+            None => return done(),
+        };
+
+        let (keyword, response) = (args.text("match_expression"), args.text("command"));
+
+        if let Some(required) = ctx.protected_level(&channel, keyword) {
+            ctx.deny(self.name(), required);
+            return done();
+        }
+
+        if let Err(err) = Template::parse(response) {
+            ctx.reply(format!("Command was not edited, {}!", err));
+            return done();
+        }
+
+        let keyword = keyword.to_owned();
+        let edit = EditCommand {
+            channel,
+            match_expr: keyword.clone(),
+            command: response.to_owned(),
+        };
+
+        Box::new(ctx.query(edit).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(changed) if changed > 0 => {
+                    ctx.send_commands_update()?;
+                    ctx.reply("Command has been edited!");
+                }
+                Ok(_) => ctx.reply(format!(
+                    "Command {} does not exist, use {}set to create it!",
+                    keyword, ctx.prefix
+                )),
+                Err(err) => ctx.reply_failure("Command could not be edited", err),
+            }
+            Ok(())
+        }))
+    }
+}
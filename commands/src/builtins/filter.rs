@@ -0,0 +1,147 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::{ModerationRule, SetModerationRule};
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+use moderation::{Action, Rule, ACTIONS, MAX_TIMEOUT, RULES};
+use permissions::PermissionLevel;
+
+/// Levels that can be exempt from a rule
+const LEVELS: &[&str] = &["everyone", "sub", "vip", "mod", "broadcaster", "owner"];
+
+/// Show or change a rule of the channel's spam filter
+pub struct Filter;
+
+impl BotCommand for Filter {
+    fn name(&self) -> &'static str {
+        "filter"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("rule", ArgKind::Choice(RULES)),
+            Arg::optional("state", ArgKind::Choice(&["on", "off"])),
+            Arg::option("threshold", ArgKind::Integer),
+            Arg::option("exempt", ArgKind::Choice(LEVELS)),
+            Arg::option("action", ArgKind::Choice(ACTIONS)),
+            Arg::option("max", ArgKind::Choice(ACTIONS)),
+            Arg::option("timeout", ArgKind::Duration),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let rule_name = args.text("rule").to_owned();
+        let rule = match Rule::parse(&rule_name) {
+            Some(rule) => rule,
+            None => {
+                ctx.usage(self);
+                return done();
+            }
+        };
+
+        let current = ctx
+            .commands
+            .read()
+            .expect("READ ERROR")
+            .moderation_rule(&ctx.channel, &rule_name)
+            .cloned();
+
+        let changed = ["state", "threshold", "exempt", "action", "max", "timeout"]
+            .iter()
+            .any(|name| args.has(name));
+
+        let mut settings = match current {
+            Some(settings) => settings,
+            None if !changed => {
+                ctx.reply(format!("Filter {} is off!", rule_name));
+                return done();
+            }
+            None => ModerationRule {
+                channel: ctx.channel.clone(),
+                rule: rule_name.clone(),
+                enabled: true,
+                threshold: rule.default_threshold(),
+                exempt_level: PermissionLevel::Moderator as i32,
+                action: "warn".to_owned(),
+                max_action: "timeout".to_owned(),
+                timeout_seconds: 600,
+            },
+        };
+
+        if !changed {
+            ctx.reply(describe(&settings));
+            return done();
+        }
+
+        if args.has("state") {
+            settings.enabled = args.text("state") == "on";
+        }
+
+        if let Some(threshold) = args.integer("threshold") {
+            if threshold > i32::max_value() as i64 || !rule.valid_threshold(threshold as i32) {
+                ctx.reply(format!("{} is not a valid threshold for {}!", threshold, rule_name));
+                return done();
+            }
+            settings.threshold = threshold as i32;
+        }
+
+        if let Some(exempt) = PermissionLevel::parse(args.text("exempt")) {
+            settings.exempt_level = exempt as i32;
+        }
+
+        if args.has("action") {
+            settings.action = args.text("action").to_owned();
+        }
+        if args.has("max") {
+            settings.max_action = args.text("max").to_owned();
+        }
+
+        if let Some(timeout) = args.duration("timeout") {
+            if timeout.as_secs() == 0 || timeout > MAX_TIMEOUT {
+                ctx.reply(format!(
+                    "Timeouts last between 1s and {}s!",
+                    MAX_TIMEOUT.as_secs()
+                ));
+                return done();
+            }
+            settings.timeout_seconds = timeout.as_secs() as i32;
+        }
+
+        let reply = describe(&settings);
+        Box::new(ctx.query(SetModerationRule(settings)).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(_) => ctx.reply(reply),
+                Err(err) => ctx.reply_failure("Filter could not be changed", err),
+            }
+            Ok(())
+        }))
+    }
+}
+
+/// Settings of a rule as shown in chat
+fn describe(settings: &ModerationRule) -> String {
+    if !settings.enabled {
+        return format!("Filter {} is off!", settings.rule);
+    }
+
+    let first = Action::parse(&settings.action).unwrap_or(Action::Warn);
+    let strongest = Action::parse(&settings.max_action).unwrap_or(first).max(first);
+    let actions = if strongest == first {
+        settings.action.clone()
+    } else {
+        format!("{} up to {}", settings.action, settings.max_action)
+    };
+
+    format!(
+        "Filter {} is on, threshold {}, {} and higher are exempt, {} ({}s timeout)!",
+        settings.rule,
+        settings.threshold,
+        PermissionLevel::from_i32(settings.exempt_level),
+        actions,
+        settings.timeout_seconds
+    )
+}
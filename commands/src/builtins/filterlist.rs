@@ -0,0 +1,99 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::{AddModerationTerm, RemoveModerationTerm, MAX_MODERATION_TERM_LENGTH};
+use command::{done, paginate, BotCommand, CommandContext, CommandFuture, MAX_CHAT_MESSAGE_LENGTH};
+use failure::Error;
+use futures::Future;
+use moderation::{normalize_domain, ALLOWED_DOMAINS, BANNED_PHRASES};
+use permissions::PermissionLevel;
+
+/// Manage the allowed domains and banned phrases of the spam filter
+pub struct FilterList;
+
+impl BotCommand for FilterList {
+    fn name(&self) -> &'static str {
+        "filterlist"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("list", ArgKind::Choice(&[ALLOWED_DOMAINS, BANNED_PHRASES])),
+            Arg::required("action", ArgKind::Choice(&["add", "remove", "show"])),
+            Arg::optional("term", ArgKind::Rest),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let list = args.text("list").to_owned();
+
+        if args.text("action") == "show" {
+            let terms = ctx
+                .commands
+                .read()
+                .expect("READ ERROR")
+                .moderation_terms(&ctx.channel, &list)
+                .to_vec();
+
+            // Leaves room for the mention and header
+            match paginate(&terms, MAX_CHAT_MESSAGE_LENGTH - 100).first() {
+                Some(page) => ctx.reply(format!("Filter {}: {}", list, page)),
+                None => ctx.reply(format!("Filter {} is empty!", list)),
+            }
+            return done();
+        }
+
+        let term = if list == ALLOWED_DOMAINS {
+            normalize_domain(args.text("term"))
+        } else {
+            args.text("term").trim().to_lowercase()
+        };
+
+        if term.is_empty() {
+            ctx.usage(self);
+            return done();
+        }
+
+        if term.len() > MAX_MODERATION_TERM_LENGTH {
+            ctx.reply(format!(
+                "Filter terms can't be longer than {} characters!",
+                MAX_MODERATION_TERM_LENGTH
+            ));
+            return done();
+        }
+
+        if args.text("action") == "remove" {
+            let remove = RemoveModerationTerm {
+                channel: ctx.channel.clone(),
+                list: list.clone(),
+                term: term.clone(),
+            };
+
+            return Box::new(ctx.query(remove).then(move |result| -> Result<(), Error> {
+                match result {
+                    Ok(removed) if removed > 0 => ctx.reply(format!("{} was removed from {}!", term, list)),
+                    Ok(_) => ctx.reply(format!("{} is not in {}!", term, list)),
+                    Err(err) => ctx.reply_failure("Term could not be removed", err),
+                }
+                Ok(())
+            }));
+        }
+
+        let add = AddModerationTerm {
+            channel: ctx.channel.clone(),
+            list: list.clone(),
+            term: term.clone(),
+        };
+
+        Box::new(ctx.query(add).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(added) if added > 0 => ctx.reply(format!("{} was added to {}!", term, list)),
+                Ok(_) => ctx.reply(format!("{} is already in {}!", term, list)),
+                Err(err) => ctx.reply_failure("Term could not be added", err),
+            }
+            Ok(())
+        }))
+    }
+}
@@ -0,0 +1,53 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::GivePoints;
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+
+/// Give some of the user's points to another user
+pub struct Give;
+
+impl BotCommand for Give {
+    fn name(&self) -> &'static str {
+        "give"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("user", ArgKind::User),
+            Arg::required("amount", ArgKind::Integer),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let to = args.text("user").to_owned();
+        let from = ctx.user.to_lowercase();
+        let amount = args.integer("amount").unwrap_or(0);
+
+        if to == from {
+            ctx.reply("You can't give points to yourself!");
+            return done();
+        }
+
+        if amount < 1 || amount > i64::from(i32::max_value()) {
+            ctx.reply("You can only give a positive number of points!");
+            return done();
+        }
+
+        let give_points = GivePoints {
+            channel: ctx.channel.clone(),
+            from,
+            to: to.clone(),
+            amount: amount as i32,
+        };
+
+        Box::new(ctx.query(give_points).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(true) => ctx.reply(format!("You gave {} points to {}!", amount, to)),
+                Ok(false) => ctx.reply(format!("You don't have {} points!", amount)),
+                Err(err) => ctx.reply_failure("Points could not be given", err),
+            }
+            Ok(())
+        }))
+    }
+}
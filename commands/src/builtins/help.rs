@@ -0,0 +1,53 @@
+use args::{Arg, ArgKind, Args};
+use command::{done, BotCommand, CommandContext, CommandFuture};
+
+/// List the built-in commands, or show how one is used
+pub struct Help;
+
+impl BotCommand for Help {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["commands"]
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![Arg::optional("command", ArgKind::Word)]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        match Some(args.text("command")).filter(|topic| !topic.is_empty()) {
+            Some(topic) => {
+                let topic = topic.trim_left_matches(ctx.prefix.as_str());
+                match ctx.registry.get(topic) {
+                    Some(command) => ctx.reply(format!(
+                        "{}{} ({} and higher)",
+                        ctx.prefix,
+                        command.usage(),
+                        command.permission()
+                    )),
+                    None => ctx.reply(format!("{} is not a built-in command!", topic)),
+                }
+            }
+            None => {
+                let available: Vec<String> = ctx
+                    .registry
+                    .commands()
+                    .iter()
+                    .filter(|command| ctx.level >= command.permission())
+                    .map(|command| format!("{}{}", ctx.prefix, command.name()))
+                    .collect();
+
+                ctx.reply(format!(
+                    "Built-in commands: {}. Use {}help <command> for details!",
+                    available.join(", "),
+                    ctx.prefix
+                ));
+            }
+        }
+
+        done()
+    }
+}
@@ -0,0 +1,56 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::cache::GLOBAL_CHANNEL;
+use command::{done, BotCommand, CommandContext, CommandFuture, MAX_CHAT_MESSAGE_LENGTH};
+use permissions::PermissionLevel;
+
+/// Show the settings and response of a stored command
+pub struct Info;
+
+impl BotCommand for Info {
+    fn name(&self) -> &'static str {
+        "info"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![Arg::required("match_expression", ArgKind::Word)]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let keyword = args.text("match_expression");
+
+        let message = {
+            let commands = ctx.commands.read().expect("READ ERROR");
+            match commands.resolve(&ctx.channel, keyword) {
+                Some(cmd) => {
+                    let variants = match commands.responses(cmd).len() {
+                        0 => String::new(),
+                        count => {
+                            format!(", {} more responses ({})", count, cmd.response_mode().as_str())
+                        }
+                    };
+                    let info = format!(
+                        "{}{}: {} match, {} and higher, {}s cooldown, {}s per user{}{}{}, response: ",
+                        cmd.match_expr,
+                        if cmd.channel == GLOBAL_CHANNEL { " (global)" } else { "" },
+                        cmd.match_mode().as_str(),
+                        PermissionLevel::from_i32(cmd.permission),
+                        cmd.global_cooldown,
+                        cmd.user_cooldown,
+                        if cmd.cooldown_mod_exempt { " (mods exempt)" } else { "" },
+                        if cmd.script.is_some() { ", runs a script" } else { "" },
+                        variants
+                    );
+                    // Leaves room for the mention
+                    let room = (MAX_CHAT_MESSAGE_LENGTH - 30).saturating_sub(info.len());
+                    let response: String = cmd.command.chars().take(room).collect();
+                    info + &response
+                }
+                None => format!("Command {} does not exist!", keyword),
+            }
+        };
+
+        ctx.reply(message);
+
+        done()
+    }
+}
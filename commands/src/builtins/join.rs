@@ -0,0 +1,36 @@
+use args::Args;
+use cold_data::models::{JoinQueue, QueueJoin};
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use futures::Future;
+
+/// Join the viewer queue of the channel
+pub struct Join;
+
+impl BotCommand for Join {
+    fn name(&self) -> &'static str {
+        "join"
+    }
+
+    fn execute(&self, ctx: CommandContext, _args: Args) -> CommandFuture {
+        let join_queue = JoinQueue {
+            channel: ctx.channel.clone(),
+            user: ctx.user.to_lowercase(),
+            subscriber: ctx.subscriber,
+        };
+
+        Box::new(ctx.query(join_queue).then(move |result| -> CommandFuture {
+            match result {
+                Ok(QueueJoin::Joined(position)) => {
+                    ctx.reply(format!("You joined the queue at #{}!", position));
+                    return ctx.send_queue_update();
+                }
+                Ok(QueueJoin::AlreadyIn(position)) => {
+                    ctx.reply(format!("You are already #{} in the queue!", position))
+                }
+                Ok(QueueJoin::Closed) => ctx.reply("The queue is closed!"),
+                Err(err) => ctx.reply_failure("Queue could not be joined", err),
+            }
+            done()
+        }))
+    }
+}
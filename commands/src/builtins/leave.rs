@@ -0,0 +1,32 @@
+use args::Args;
+use cold_data::models::LeaveQueue;
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use futures::Future;
+
+/// Leave the viewer queue of the channel
+pub struct Leave;
+
+impl BotCommand for Leave {
+    fn name(&self) -> &'static str {
+        "leave"
+    }
+
+    fn execute(&self, ctx: CommandContext, _args: Args) -> CommandFuture {
+        let leave_queue = LeaveQueue {
+            channel: ctx.channel.clone(),
+            user: ctx.user.to_lowercase(),
+        };
+
+        Box::new(ctx.query(leave_queue).then(move |result| -> CommandFuture {
+            match result {
+                Ok(0) => ctx.reply("You are not in the queue!"),
+                Ok(_) => {
+                    ctx.reply("You left the queue!");
+                    return ctx.send_queue_update();
+                }
+                Err(err) => ctx.reply_failure("Queue could not be left", err),
+            }
+            done()
+        }))
+    }
+}
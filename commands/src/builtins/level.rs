@@ -0,0 +1,73 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::SetCommandPermission;
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+
+/// Levels a command can be given
+const LEVELS: &[&str] = &["everyone", "sub", "vip", "mod", "broadcaster", "owner"];
+
+/// Set the level needed to use a stored command
+pub struct Level;
+
+impl BotCommand for Level {
+    fn name(&self) -> &'static str {
+        "level"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("match_expression", ArgKind::Word),
+            Arg::required("level", ArgKind::Choice(LEVELS)),
+            Arg::flag("global"),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let channel = match ctx.target_channel(self, &args) {
+            Some(channel) => channel,
+            None => return done(),
+        };
+
+        let keyword = args.text("match_expression").to_owned();
+        let new_level = match PermissionLevel::parse(args.text("level")) {
+            Some(new_level) => new_level,
+            None => {
+                ctx.usage(self);
+                return done();
+            }
+        };
+
+        // Users can't protect a command above their own level
+        let required = ctx
+            .protected_level(&channel, &keyword)
+            .or_else(|| if ctx.level < new_level { Some(new_level) } else { None });
+
+        if let Some(required) = required {
+            ctx.deny(self.name(), required);
+            return done();
+        }
+
+        let set_permission = SetCommandPermission {
+            channel,
+            match_expr: keyword.clone(),
+            permission: new_level as i32,
+        };
+
+        Box::new(ctx.query(set_permission).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(changed) if changed > 0 => {
+                    ctx.reply(format!("{} can now be used by {} and higher!", keyword, new_level))
+                }
+                Ok(_) => ctx.reply(format!("Command {} does not exist!", keyword)),
+                Err(err) => ctx.reply_failure("Level could not be set", err),
+            }
+            Ok(())
+        }))
+    }
+}
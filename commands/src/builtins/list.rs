@@ -0,0 +1,49 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::matcher::MatchMode;
+use command::{done, paginate, BotCommand, CommandContext, CommandFuture, MAX_CHAT_MESSAGE_LENGTH};
+
+/// List the triggers of a channel, a page at a time
+pub struct List;
+
+impl BotCommand for List {
+    fn name(&self) -> &'static str {
+        "list"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![Arg::optional("page", ArgKind::Integer)]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let page = match args.integer("page").unwrap_or(1) {
+            page if page > 0 => page as usize,
+            _ => {
+                ctx.usage(self);
+                return done();
+            }
+        };
+
+        let triggers: Vec<String> = {
+            let commands = ctx.commands.read().expect("READ ERROR");
+            commands
+                .available(&ctx.channel)
+                .into_iter()
+                .map(|cmd| match cmd.match_mode() {
+                    MatchMode::Exact => format!("{}{}", ctx.prefix, cmd.match_expr),
+                    _ => cmd.match_expr.clone(),
+                })
+                .collect()
+        };
+
+        // Leaves room for the mention and page header
+        let pages = paginate(&triggers, MAX_CHAT_MESSAGE_LENGTH - 100);
+
+        match pages.get(page - 1) {
+            Some(commands) => ctx.reply(format!("Commands ({}/{}): {}", page, pages.len(), commands)),
+            None if pages.is_empty() => ctx.reply("This channel has no commands yet!"),
+            None => ctx.reply(format!("There are only {} pages of commands!", pages.len())),
+        }
+
+        done()
+    }
+}
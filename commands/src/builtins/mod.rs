@@ -0,0 +1,109 @@
+//! Commands available in every channel
+
+mod addpoints;
+mod alias;
+mod cooldown;
+mod counter;
+mod denials;
+mod edit;
+mod filter;
+mod filterlist;
+mod give;
+mod help;
+mod info;
+mod join;
+mod leave;
+mod level;
+mod list;
+mod mode;
+mod next;
+mod permit;
+mod permitlimits;
+mod pointrates;
+mod points;
+mod poll;
+mod position;
+mod prefix;
+mod queue;
+mod quote;
+mod raffle;
+mod remove;
+mod response;
+mod rename;
+mod script;
+mod set;
+mod timer;
+
+pub use self::addpoints::AddPoints;
+pub use self::alias::Alias;
+pub use self::cooldown::Cooldown;
+pub use self::counter::Counter;
+pub use self::denials::Denials;
+pub use self::edit::Edit;
+pub use self::filter::Filter;
+pub use self::filterlist::FilterList;
+pub use self::give::Give;
+pub use self::help::Help;
+pub use self::info::Info;
+pub use self::join::Join;
+pub use self::leave::Leave;
+pub use self::level::Level;
+pub use self::list::List;
+pub use self::mode::Mode;
+pub use self::next::Next;
+pub use self::permit::Permit;
+pub use self::permitlimits::PermitLimits;
+pub use self::pointrates::PointRates;
+pub use self::points::Points;
+pub use self::poll::Poll;
+pub use self::position::Position;
+pub use self::prefix::Prefix;
+pub use self::queue::Queue;
+pub use self::quote::Quote;
+pub use self::raffle::Raffle;
+pub use self::remove::Remove;
+pub use self::response::Response;
+pub use self::rename::Rename;
+pub use self::script::Script;
+pub use self::set::Set;
+pub use self::timer::Timer;
+
+use command::CommandRegistry;
+
+/// Register every built-in command, in the order shown by help
+pub fn register(registry: &mut CommandRegistry) {
+    registry
+        .register(Help)
+        .register(List)
+        .register(Info)
+        .register(Set)
+        .register(Edit)
+        .register(Rename)
+        .register(Remove)
+        .register(Alias)
+        .register(Level)
+        .register(Cooldown)
+        .register(Mode)
+        .register(Response)
+        .register(Script)
+        .register(Counter)
+        .register(Timer)
+        .register(Quote)
+        .register(Poll)
+        .register(Raffle)
+        .register(Points)
+        .register(Give)
+        .register(AddPoints)
+        .register(PointRates)
+        .register(Join)
+        .register(Leave)
+        .register(Position)
+        .register(Next)
+        .register(Queue)
+        .register(Filter)
+        .register(FilterList)
+        .register(Permit)
+        .register(PermitLimits)
+        .register(Prefix)
+        .register(Denials);
+}
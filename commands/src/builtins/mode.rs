@@ -0,0 +1,71 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::matcher::{MatchMode, Matcher};
+use cold_data::models::SetCommandMatchMode;
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+
+/// Set how a stored command is matched against chat messages
+pub struct Mode;
+
+impl BotCommand for Mode {
+    fn name(&self) -> &'static str {
+        "mode"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("match_expression", ArgKind::Word),
+            Arg::required("mode", ArgKind::Choice(&["exact", "prefix", "word", "glob", "regex"])),
+            Arg::flag("global"),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let channel = match ctx.target_channel(self, &args) {
+            Some(channel) => channel,
+            None => return done(),
+        };
+
+        let keyword = args.text("match_expression").to_owned();
+        let match_mode = match MatchMode::parse(args.text("mode")) {
+            Ok(match_mode) => match_mode,
+            Err(err) => {
+                ctx.reply(format!("Mode was not set, {}!", err));
+                return done();
+            }
+        };
+
+        if let Some(required) = ctx.protected_level(&channel, &keyword) {
+            ctx.deny(self.name(), required);
+            return done();
+        }
+
+        if let Err(err) = Matcher::compile(match_mode, &keyword) {
+            ctx.reply(format!("Mode was not set, {}!", err));
+            return done();
+        }
+
+        let set_mode = SetCommandMatchMode {
+            channel,
+            match_expr: keyword.clone(),
+            match_mode,
+        };
+
+        Box::new(ctx.query(set_mode).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(changed) if changed > 0 => {
+                    ctx.reply(format!("{} now uses {} matching!", keyword, match_mode.as_str()))
+                }
+                Ok(_) => ctx.reply(format!("Command {} does not exist!", keyword)),
+                Err(err) => ctx.reply_failure("Mode could not be set", err),
+            }
+            Ok(())
+        }))
+    }
+}
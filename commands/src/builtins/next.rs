@@ -0,0 +1,57 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::NextInQueue;
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use futures::Future;
+use permissions::PermissionLevel;
+
+/// Users picked at once, to keep the reply within one chat message
+const MAX_NEXT_USERS: i64 = 20;
+
+/// Pick the next users of the viewer queue, removing them from it
+pub struct Next;
+
+impl BotCommand for Next {
+    fn name(&self) -> &'static str {
+        "next"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![Arg::optional("count", ArgKind::Integer)]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let count = args.integer("count").unwrap_or(1);
+        if count < 1 || count > MAX_NEXT_USERS {
+            ctx.reply(format!(
+                "You can pick between 1 and {} users at once!",
+                MAX_NEXT_USERS
+            ));
+            return done();
+        }
+
+        let next_in_queue = NextInQueue {
+            channel: ctx.channel.clone(),
+            count,
+        };
+
+        Box::new(ctx.query(next_in_queue).then(move |result| -> CommandFuture {
+            match result {
+                Ok(ref entries) if entries.is_empty() => ctx.reply("The queue is empty!"),
+                Ok(entries) => {
+                    let users: Vec<String> = entries
+                        .iter()
+                        .map(|entry| format!("@{}", entry.user))
+                        .collect();
+                    ctx.say(format!("Up next: {}!", users.join(", ")));
+                    return ctx.send_queue_update();
+                }
+                Err(err) => ctx.reply_failure("Next users could not be picked", err),
+            }
+            done()
+        }))
+    }
+}
@@ -0,0 +1,47 @@
+use args::{Arg, ArgKind, Args};
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use permissions::PermissionLevel;
+use std::time::Duration;
+
+/// Let a user post links for a while, even if the spam filter blocks them
+pub struct Permit;
+
+impl BotCommand for Permit {
+    fn name(&self) -> &'static str {
+        "permit"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![Arg::required("user", ArgKind::User)]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let user = args.text("user").to_owned();
+        let (seconds, links) = ctx
+            .commands
+            .read()
+            .expect("READ ERROR")
+            .permit_settings(&ctx.channel);
+        let (seconds, links) = (seconds.max(1), links.max(1));
+
+        ctx.permits.grant(
+            &ctx.channel,
+            &user,
+            links as u32,
+            Duration::from_secs(seconds as u64),
+        );
+
+        let links = if links > 1 {
+            format!("{} links", links)
+        } else {
+            "a link".to_owned()
+        };
+        ctx.reply(format!("{} can post {} in the next {}s!", user, links, seconds));
+
+        done()
+    }
+}
@@ -0,0 +1,61 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::SetPermitSettings;
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+
+/// Most messages with links a permit can allow
+const MAX_PERMIT_LINKS: i64 = 100;
+/// Longest a permit can last, in seconds
+const MAX_PERMIT_SECONDS: u64 = 60 * 60;
+
+/// Choose how long permits last and how many links they allow
+pub struct PermitLimits;
+
+impl BotCommand for PermitLimits {
+    fn name(&self) -> &'static str {
+        "permitlimits"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("time", ArgKind::Duration),
+            Arg::optional("links", ArgKind::Integer),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let seconds = args.duration("time").map_or(0, |time| time.as_secs());
+        let links = args.integer("links").unwrap_or(1);
+
+        if seconds == 0 || seconds > MAX_PERMIT_SECONDS || links < 1 || links > MAX_PERMIT_LINKS {
+            ctx.reply(format!(
+                "Permits last 1s to {}s and allow 1 to {} links!",
+                MAX_PERMIT_SECONDS, MAX_PERMIT_LINKS
+            ));
+            return done();
+        }
+
+        let set_permit_settings = SetPermitSettings {
+            channel: ctx.channel.clone(),
+            permit_seconds: seconds as i32,
+            permit_links: links as i32,
+        };
+
+        Box::new(ctx.query(set_permit_settings).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(_) => ctx.reply(format!(
+                    "Permits now last {}s and allow {} messages with links!",
+                    seconds, links
+                )),
+                Err(err) => ctx.reply_failure("Permit limits could not be set", err),
+            }
+            Ok(())
+        }))
+    }
+}
@@ -0,0 +1,76 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::SetPointRates;
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+
+/// Most points earned at a payout for being present or for chatting
+const MAX_POINT_RATE: i64 = 1000;
+/// Most times more points subscribers can earn
+const MAX_SUB_MULTIPLIER: i64 = 10;
+
+/// Choose how many points viewers earn every few minutes while the channel is live
+pub struct PointRates;
+
+impl BotCommand for PointRates {
+    fn name(&self) -> &'static str {
+        "pointrates"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("presence", ArgKind::Integer),
+            Arg::required("chat", ArgKind::Integer),
+            Arg::optional("sub_multiplier", ArgKind::Integer),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let presence = args.integer("presence").unwrap_or(0);
+        let chat = args.integer("chat").unwrap_or(0);
+        let (_, _, current_multiplier) = ctx
+            .commands
+            .read()
+            .expect("READ ERROR")
+            .point_rates(&ctx.channel);
+        let sub_multiplier = args
+            .integer("sub_multiplier")
+            .unwrap_or_else(|| i64::from(current_multiplier));
+
+        let valid_rate = |rate: i64| rate >= 0 && rate <= MAX_POINT_RATE;
+        if !valid_rate(presence)
+            || !valid_rate(chat)
+            || sub_multiplier < 1
+            || sub_multiplier > MAX_SUB_MULTIPLIER
+        {
+            ctx.reply(format!(
+                "Viewers earn 0 to {} points and subscribers 1 to {} times more!",
+                MAX_POINT_RATE, MAX_SUB_MULTIPLIER
+            ));
+            return done();
+        }
+
+        let set_point_rates = SetPointRates {
+            channel: ctx.channel.clone(),
+            presence: presence as i32,
+            chat: chat as i32,
+            sub_multiplier: sub_multiplier as i32,
+        };
+
+        Box::new(ctx.query(set_point_rates).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(_) => ctx.reply(format!(
+                    "Viewers now earn {} points, {} more if they chatted, subscribers {} times more!",
+                    presence, chat, sub_multiplier
+                )),
+                Err(err) => ctx.reply_failure("Point rates could not be set", err),
+            }
+            Ok(())
+        }))
+    }
+}
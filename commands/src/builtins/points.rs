@@ -0,0 +1,41 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::GetPoints;
+use command::{BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+
+/// Show the points of the user, or of another user
+pub struct Points;
+
+impl BotCommand for Points {
+    fn name(&self) -> &'static str {
+        "points"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![Arg::optional("user", ArgKind::User)]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let user = match args.text("user") {
+            "" => ctx.user.to_lowercase(),
+            user => user.to_owned(),
+        };
+
+        let get_points = GetPoints {
+            channel: ctx.channel.clone(),
+            user: user.clone(),
+        };
+
+        Box::new(ctx.query(get_points).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(balance) if user == ctx.user.to_lowercase() => {
+                    ctx.reply(format!("You have {} points!", balance))
+                }
+                Ok(balance) => ctx.reply(format!("{} has {} points!", user, balance)),
+                Err(err) => ctx.reply_failure("Points could not be found", err),
+            }
+            Ok(())
+        }))
+    }
+}
@@ -0,0 +1,135 @@
+use args::{parse_duration, Arg, ArgKind, Args};
+use cold_data::models::{MAX_POLL_OPTION_LENGTH, MAX_POLL_QUESTION_LENGTH};
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+use polls::{
+    EndPoll, StartPoll, DEFAULT_POLL_DURATION, MAX_POLL_DURATION, MAX_POLL_OPTIONS,
+    MIN_POLL_DURATION, MIN_POLL_OPTIONS,
+};
+use std::time::Duration;
+
+/// Longest question and options together, so the announcement fits in a chat message
+const MAX_POLL_TEXT_LENGTH: usize = 350;
+
+/// Start a poll in the channel, or end the running one
+pub struct Poll;
+
+impl BotCommand for Poll {
+    fn name(&self) -> &'static str {
+        "poll"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("question", ArgKind::Word),
+            Arg::optional("options", ArgKind::Rest),
+        ]
+    }
+
+    fn usage(&self) -> String {
+        "poll <\"question\"|end|cancel> [option | option... [duration]]".to_owned()
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let question = args.text("question").trim().to_owned();
+        let (options, duration) = split_duration(args.text("options"));
+
+        let options: Vec<String> = options
+            .split('|')
+            .map(|option| option.trim().to_owned())
+            .filter(|option| !option.is_empty())
+            .collect();
+
+        if options.is_empty() && (question == "end" || question == "cancel") {
+            return end(ctx, question == "cancel");
+        }
+
+        if options.len() < MIN_POLL_OPTIONS || options.len() > MAX_POLL_OPTIONS {
+            ctx.reply(format!(
+                "Polls have {} to {} options separated by |!",
+                MIN_POLL_OPTIONS, MAX_POLL_OPTIONS
+            ));
+            return done();
+        }
+
+        let text_length = question.len() + options.iter().map(String::len).sum::<usize>();
+        if question.len() > MAX_POLL_QUESTION_LENGTH
+            || options.iter().any(|option| option.len() > MAX_POLL_OPTION_LENGTH)
+            || text_length > MAX_POLL_TEXT_LENGTH
+        {
+            ctx.reply(format!(
+                "Poll is too long, the question and options can have {} characters!",
+                MAX_POLL_TEXT_LENGTH
+            ));
+            return done();
+        }
+
+        let duration = duration.unwrap_or(DEFAULT_POLL_DURATION);
+        if duration < MIN_POLL_DURATION || duration > MAX_POLL_DURATION {
+            ctx.reply(format!(
+                "Polls last {}s to {}s!",
+                MIN_POLL_DURATION.as_secs(),
+                MAX_POLL_DURATION.as_secs()
+            ));
+            return done();
+        }
+
+        let start_poll = StartPoll {
+            channel: ctx.channel.clone(),
+            question,
+            options,
+            duration,
+            started_by: ctx.user.clone(),
+        };
+
+        Box::new(ctx.polls.send(start_poll).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => ctx.reply(format!("Poll was not started, {}!", err)),
+                Err(err) => ctx.reply_failure("Poll could not be started", err),
+            }
+            Ok(())
+        }))
+    }
+}
+
+/// End the running poll with its result, or cancel it
+fn end(ctx: CommandContext, cancel: bool) -> CommandFuture {
+    let end_poll = EndPoll {
+        channel: ctx.channel.clone(),
+        cancel,
+    };
+
+    Box::new(ctx.polls.send(end_poll).then(move |result| -> Result<(), Error> {
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => ctx.reply(format!("Poll was not ended, {}!", err)),
+            Err(err) => ctx.reply_failure("Poll could not be ended", err),
+        }
+        Ok(())
+    }))
+}
+
+/// Split the duration ending the options, e.g. `yes | no 2m`
+/// Plain numbers stay in the last option so `Top 10` is not read as 10 seconds.
+fn split_duration(options: &str) -> (&str, Option<Duration>) {
+    let options = options.trim();
+
+    match options.rfind(char::is_whitespace) {
+        Some(index) => {
+            let last = &options[index + 1..];
+            let has_unit = last.chars().last().map_or(false, |c| c.is_alphabetic());
+            match parse_duration(last) {
+                Some(duration) if has_unit => (&options[..index], Some(duration)),
+                _ => (options, None),
+            }
+        }
+        None => (options, None),
+    }
+}
@@ -0,0 +1,35 @@
+use args::Args;
+use cold_data::models::GetQueue;
+use command::{BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+
+/// Show where the user is in the viewer queue of the channel
+pub struct Position;
+
+impl BotCommand for Position {
+    fn name(&self) -> &'static str {
+        "position"
+    }
+
+    fn execute(&self, ctx: CommandContext, _args: Args) -> CommandFuture {
+        let get_queue = GetQueue {
+            channel: ctx.channel.clone(),
+        };
+
+        Box::new(ctx.query(get_queue).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(queue) => match queue.position(&ctx.user.to_lowercase()) {
+                    Some(position) => ctx.reply(format!(
+                        "You are #{} of {} in the queue!",
+                        position,
+                        queue.entries.len()
+                    )),
+                    None => ctx.reply("You are not in the queue!"),
+                },
+                Err(err) => ctx.reply_failure("Queue could not be found", err),
+            }
+            Ok(())
+        }))
+    }
+}
@@ -0,0 +1,50 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::SetCommandPrefix;
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+
+/// Longest prefix a channel can choose, matches the database column
+const MAX_PREFIX_LENGTH: usize = 10;
+
+/// Choose the prefix that starts commands in the channel
+pub struct Prefix;
+
+impl BotCommand for Prefix {
+    fn name(&self) -> &'static str {
+        "prefix"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Broadcaster
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![Arg::required("new_prefix", ArgKind::Word)]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let new_prefix = args.text("new_prefix").to_owned();
+        if new_prefix.is_empty() || new_prefix.len() > MAX_PREFIX_LENGTH {
+            ctx.reply(format!(
+                "Prefix should have 1 to {} characters!",
+                MAX_PREFIX_LENGTH
+            ));
+            return done();
+        }
+
+        let set_prefix = SetCommandPrefix {
+            channel: ctx.channel.clone(),
+            prefix: new_prefix.clone(),
+        };
+
+        Box::new(ctx.query(set_prefix).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(_) => ctx.reply(format!("Commands now start with {}", new_prefix)),
+                Err(err) => ctx.reply_failure("Prefix could not be set", err),
+            }
+            Ok(())
+        }))
+    }
+}
@@ -0,0 +1,121 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::{ClearQueue, GetQueue, SetQueueState};
+use command::{done, paginate, BotCommand, CommandContext, CommandFuture, MAX_CHAT_MESSAGE_LENGTH};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+
+/// Open, close, clear or show the viewer queue of the channel
+/// Subscribers go first when sub priority is on.
+pub struct Queue;
+
+impl BotCommand for Queue {
+    fn name(&self) -> &'static str {
+        "queue"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required(
+                "action",
+                ArgKind::Choice(&["open", "close", "clear", "list", "subpriority"]),
+            ),
+            Arg::optional("state", ArgKind::Choice(&["on", "off"])),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        match args.text("action") {
+            "open" => set_state(ctx, Some(true), None, "The queue is open, use join to get in!"),
+            "close" => set_state(ctx, Some(false), None, "The queue is closed!"),
+            "subpriority" => match args.text("state") {
+                "on" => set_state(ctx, None, Some(true), "Subscribers go first in the queue!"),
+                "off" => set_state(ctx, None, Some(false), "Subscribers wait like everyone!"),
+                _ => {
+                    ctx.usage(self);
+                    done()
+                }
+            },
+            "clear" => clear(ctx),
+            _ => list(ctx),
+        }
+    }
+}
+
+fn set_state(
+    ctx: CommandContext,
+    open: Option<bool>,
+    sub_priority: Option<bool>,
+    message: &'static str,
+) -> CommandFuture {
+    let set_queue_state = SetQueueState {
+        channel: ctx.channel.clone(),
+        open,
+        sub_priority,
+    };
+
+    Box::new(ctx.query(set_queue_state).then(move |result| -> CommandFuture {
+        match result {
+            Ok(_) => {
+                ctx.say(message);
+                ctx.send_queue_update()
+            }
+            Err(err) => {
+                ctx.reply_failure("Queue could not be changed", err);
+                done()
+            }
+        }
+    }))
+}
+
+fn clear(ctx: CommandContext) -> CommandFuture {
+    let clear_queue = ClearQueue {
+        channel: ctx.channel.clone(),
+    };
+
+    Box::new(ctx.query(clear_queue).then(move |result| -> CommandFuture {
+        match result {
+            Ok(removed) => {
+                ctx.reply(format!("Removed {} users from the queue!", removed));
+                ctx.send_queue_update()
+            }
+            Err(err) => {
+                ctx.reply_failure("Queue could not be cleared", err);
+                done()
+            }
+        }
+    }))
+}
+
+fn list(ctx: CommandContext) -> CommandFuture {
+    let get_queue = GetQueue {
+        channel: ctx.channel.clone(),
+    };
+
+    Box::new(ctx.query(get_queue).then(move |result| -> Result<(), Error> {
+        match result {
+            Ok(ref queue) if queue.entries.is_empty() => ctx.reply(format!(
+                "The queue is {} and empty!",
+                if queue.open { "open" } else { "closed" }
+            )),
+            Ok(queue) => {
+                let users: Vec<String> = queue
+                    .entries
+                    .iter()
+                    .enumerate()
+                    .map(|(index, entry)| format!("{}. {}", index + 1, entry.user))
+                    .collect();
+                // Leaves room for the mention and header
+                if let Some(page) = paginate(&users, MAX_CHAT_MESSAGE_LENGTH - 100).first() {
+                    ctx.reply(format!("Queue ({} waiting): {}", users.len(), page));
+                }
+            }
+            Err(err) => ctx.reply_failure("Queue could not be found", err),
+        }
+        Ok(())
+    }))
+}
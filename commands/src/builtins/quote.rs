@@ -0,0 +1,220 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::{
+    AddQuote, GetQuote, Quote as StoredQuote, RemoveQuote, SearchQuotes, MAX_QUOTE_GAME_LENGTH,
+    MAX_QUOTE_LENGTH,
+};
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::{future, Future};
+use permissions::PermissionLevel;
+
+/// Most quote numbers listed by a search
+const MAX_SEARCH_RESULTS: usize = 20;
+
+/// Show, add, search and remove the quotes of the channel
+pub struct Quote;
+
+impl BotCommand for Quote {
+    fn name(&self) -> &'static str {
+        "quote"
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::optional("action", ArgKind::Word),
+            Arg::option("game", ArgKind::Word),
+            Arg::optional("text", ArgKind::Rest),
+        ]
+    }
+
+    fn usage(&self) -> String {
+        "quote [number|add|search|remove] [--game game] [text...]".to_owned()
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let action = args.text("action").to_lowercase();
+        let text = args.text("text").trim().to_owned();
+
+        match action.as_str() {
+            "" => show(ctx, None),
+            "add" => {
+                if ctx.level < PermissionLevel::Moderator {
+                    ctx.deny("quote add", PermissionLevel::Moderator);
+                    return done();
+                }
+                let game = args.text("game").trim().to_owned();
+                add(ctx, self, text, game)
+            }
+            "search" if !text.is_empty() => search(ctx, text),
+            "remove" => {
+                if ctx.level < PermissionLevel::Moderator {
+                    ctx.deny("quote remove", PermissionLevel::Moderator);
+                    return done();
+                }
+                match text.parse() {
+                    Ok(number) => remove(ctx, number),
+                    Err(_) => {
+                        ctx.usage(self);
+                        done()
+                    }
+                }
+            }
+            _ => match action.trim_left_matches('#').parse() {
+                Ok(number) => show(ctx, Some(number)),
+                Err(_) => {
+                    ctx.usage(self);
+                    done()
+                }
+            },
+        }
+    }
+}
+
+/// Quote as shown in chat
+fn describe(quote: &StoredQuote) -> String {
+    let game = quote
+        .game
+        .as_ref()
+        .map_or(String::new(), |game| format!("{}, ", game));
+
+    format!(
+        "#{}: \"{}\" ({}{})",
+        quote.number,
+        quote.quote,
+        game,
+        quote.added_at.format("%Y-%m-%d")
+    )
+}
+
+/// Say a quote by number, or a random one
+fn show(ctx: CommandContext, number: Option<i32>) -> CommandFuture {
+    let get_quote = GetQuote {
+        channel: ctx.channel.clone(),
+        number,
+    };
+
+    Box::new(ctx.query(get_quote).then(move |result| -> Result<(), Error> {
+        match (result, number) {
+            (Ok(Some(quote)), _) => ctx.say(describe(&quote)),
+            (Ok(None), Some(number)) => ctx.reply(format!("Quote #{} does not exist!", number)),
+            (Ok(None), None) => ctx.reply("This channel has no quotes yet!"),
+            (Err(err), _) => ctx.reply_failure("Quote could not be found", err),
+        }
+        Ok(())
+    }))
+}
+
+fn add(ctx: CommandContext, command: &Quote, text: String, game: String) -> CommandFuture {
+    if text.is_empty() {
+        ctx.usage(command);
+        return done();
+    }
+
+    if text.len() > MAX_QUOTE_LENGTH || game.len() > MAX_QUOTE_GAME_LENGTH {
+        ctx.reply(format!(
+            "Quotes can have {} characters and games {} characters!",
+            MAX_QUOTE_LENGTH, MAX_QUOTE_GAME_LENGTH
+        ));
+        return done();
+    }
+
+    let game = quote_game(&ctx, game);
+
+    let add_quote = game.and_then(move |game| {
+        let add_quote = AddQuote {
+            channel: ctx.channel.clone(),
+            quote: text,
+            added_by: ctx.user.clone(),
+            game: game.map(|game| truncate(game, MAX_QUOTE_GAME_LENGTH)),
+        };
+        ctx.query(add_quote).then(move |result| -> Result<_, Error> { Ok((ctx, result)) })
+    });
+
+    Box::new(add_quote.and_then(|(ctx, result)| -> Result<(), Error> {
+        match result {
+            Ok(number) => ctx.reply(format!("Quote #{} has been added!", number)),
+            Err(err) => ctx.reply_failure("Quote could not be added", err),
+        }
+        Ok(())
+    }))
+}
+
+/// Game of a new quote, the channel's current category on twitch unless one was given
+fn quote_game(
+    ctx: &CommandContext,
+    game: String,
+) -> Box<dyn Future<Item = Option<String>, Error = Error>> {
+    if !game.is_empty() {
+        return Box::new(future::ok(Some(game)));
+    }
+
+    match (&ctx.helix, &ctx.room_id) {
+        (Some(helix), Some(room_id)) => Box::new(helix.current_game(room_id).or_else(|err| {
+            println!("Could not get the current game {:?}", err);
+            Ok(None)
+        })),
+        _ => Box::new(future::ok(None)),
+    }
+}
+
+/// Cut a game name fetched from twitch to what a quote can store
+fn truncate(mut game: String, length: usize) -> String {
+    if game.len() > length {
+        let mut end = length;
+        while !game.is_char_boundary(end) {
+            end -= 1;
+        }
+        game.truncate(end);
+    }
+    game
+}
+
+/// Say the only quote containing the text, or the numbers of those that do
+fn search(ctx: CommandContext, text: String) -> CommandFuture {
+    let search_quotes = SearchQuotes {
+        channel: ctx.channel.clone(),
+        text: text.clone(),
+    };
+
+    Box::new(ctx.query(search_quotes).then(move |result| -> Result<(), Error> {
+        match result {
+            Ok(ref quotes) if quotes.len() == 1 => ctx.say(describe(&quotes[0])),
+            Ok(ref quotes) if quotes.is_empty() => {
+                ctx.reply(format!("No quote contains \"{}\"!", text))
+            }
+            Ok(quotes) => {
+                let numbers: Vec<String> = quotes
+                    .iter()
+                    .take(MAX_SEARCH_RESULTS)
+                    .map(|quote| format!("#{}", quote.number))
+                    .collect();
+                let more = if quotes.len() > MAX_SEARCH_RESULTS { " and more" } else { "" };
+                ctx.reply(format!(
+                    "{} quotes contain \"{}\": {}{}",
+                    quotes.len(),
+                    text,
+                    numbers.join(", "),
+                    more
+                ));
+            }
+            Err(err) => ctx.reply_failure("Quotes could not be searched", err),
+        }
+        Ok(())
+    }))
+}
+
+fn remove(ctx: CommandContext, number: i32) -> CommandFuture {
+    let remove_quote = RemoveQuote {
+        channel: ctx.channel.clone(),
+        number,
+    };
+
+    Box::new(ctx.query(remove_quote).then(move |result| -> Result<(), Error> {
+        match result {
+            Ok(removed) if removed > 0 => ctx.reply(format!("Quote #{} has been removed!", number)),
+            Ok(_) => ctx.reply(format!("Quote #{} does not exist!", number)),
+            Err(err) => ctx.reply_failure("Quote could not be removed", err),
+        }
+        Ok(())
+    }))
+}
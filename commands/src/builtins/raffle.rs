@@ -0,0 +1,151 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::MAX_RAFFLE_KEYWORD_LENGTH;
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+use raffles::{
+    CloseEntries, DrawWinners, EndRaffle, StartRaffle, DEFAULT_CLAIM_TIMEOUT, MAX_CLAIM_TIMEOUT,
+    MAX_RAFFLE_COST, MAX_RAFFLE_WINNERS, MAX_SUB_LUCK,
+};
+
+/// Run a giveaway, users enter by sending its keyword
+pub struct Raffle;
+
+impl BotCommand for Raffle {
+    fn name(&self) -> &'static str {
+        "raffle"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("action", ArgKind::Choice(&["open", "close", "draw", "end"])),
+            Arg::option("luck", ArgKind::Integer),
+            Arg::option("cost", ArgKind::Integer),
+            Arg::option("timeout", ArgKind::Duration),
+            Arg::optional("value", ArgKind::Word),
+        ]
+    }
+
+    fn usage(&self) -> String {
+        "raffle <open keyword|close|draw [winners]|end> [--luck tickets] [--cost points] [--timeout time]"
+            .to_owned()
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let value = args.text("value").trim().to_owned();
+
+        match args.text("action") {
+            "open" => open(ctx, self, value, &args),
+            "close" => close(ctx),
+            "draw" => {
+                let count = if value.is_empty() { Ok(1) } else { value.parse() };
+                match count {
+                    Ok(count) if count >= 1 && count <= MAX_RAFFLE_WINNERS => draw(ctx, count),
+                    _ => {
+                        ctx.reply(format!("Raffles draw 1 to {} winners at once!", MAX_RAFFLE_WINNERS));
+                        done()
+                    }
+                }
+            }
+            _ => end(ctx),
+        }
+    }
+}
+
+fn open(ctx: CommandContext, command: &Raffle, keyword: String, args: &Args) -> CommandFuture {
+    if keyword.is_empty() || keyword.len() > MAX_RAFFLE_KEYWORD_LENGTH {
+        ctx.usage(command);
+        return done();
+    }
+
+    let luck = args.integer("luck").unwrap_or(1);
+    if luck < 1 || luck > i64::from(MAX_SUB_LUCK) {
+        ctx.reply(format!("Subscribers can have 1 to {} tickets!", MAX_SUB_LUCK));
+        return done();
+    }
+
+    let cost = args.integer("cost").unwrap_or(0);
+    if cost < 0 || cost > i64::from(MAX_RAFFLE_COST) {
+        ctx.reply(format!("Raffles can cost 0 to {} points!", MAX_RAFFLE_COST));
+        return done();
+    }
+
+    let claim_timeout = args.duration("timeout").unwrap_or(DEFAULT_CLAIM_TIMEOUT);
+    if claim_timeout > MAX_CLAIM_TIMEOUT {
+        ctx.reply(format!(
+            "Winners can have up to {}s to respond, 0 to not wait for them!",
+            MAX_CLAIM_TIMEOUT.as_secs()
+        ));
+        return done();
+    }
+
+    let start_raffle = StartRaffle {
+        channel: ctx.channel.clone(),
+        keyword,
+        sub_luck: luck as u32,
+        cost: cost as u32,
+        claim_timeout,
+        opened_by: ctx.user.clone(),
+    };
+
+    Box::new(ctx.raffles.send(start_raffle).then(move |result| -> Result<(), Error> {
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => ctx.reply(format!("Raffle was not opened, {}!", err)),
+            Err(err) => ctx.reply_failure("Raffle could not be opened", err),
+        }
+        Ok(())
+    }))
+}
+
+fn close(ctx: CommandContext) -> CommandFuture {
+    let close_entries = CloseEntries {
+        channel: ctx.channel.clone(),
+    };
+
+    Box::new(ctx.raffles.send(close_entries).then(move |result| -> Result<(), Error> {
+        match result {
+            Ok(Ok(entered)) => ctx.say(format!("Raffle entries are closed, {} users entered!", entered)),
+            Ok(Err(err)) => ctx.reply(format!("Raffle was not closed, {}!", err)),
+            Err(err) => ctx.reply_failure("Raffle could not be closed", err),
+        }
+        Ok(())
+    }))
+}
+
+/// Winners are announced by the raffle manager, it redraws those who don't respond
+fn draw(ctx: CommandContext, count: usize) -> CommandFuture {
+    let draw_winners = DrawWinners {
+        channel: ctx.channel.clone(),
+        count,
+    };
+
+    Box::new(ctx.raffles.send(draw_winners).then(move |result| -> Result<(), Error> {
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => ctx.reply(format!("No winner was drawn, {}!", err)),
+            Err(err) => ctx.reply_failure("Winners could not be drawn", err),
+        }
+        Ok(())
+    }))
+}
+
+fn end(ctx: CommandContext) -> CommandFuture {
+    let end_raffle = EndRaffle {
+        channel: ctx.channel.clone(),
+    };
+
+    Box::new(ctx.raffles.send(end_raffle).then(move |result| -> Result<(), Error> {
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => ctx.reply(format!("Raffle was not ended, {}!", err)),
+            Err(err) => ctx.reply_failure("Raffle could not be ended", err),
+        }
+        Ok(())
+    }))
+}
@@ -0,0 +1,57 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::RemoveCommand;
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+
+/// Remove a stored command
+pub struct Remove;
+
+impl BotCommand for Remove {
+    fn name(&self) -> &'static str {
+        "remove"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("match_expression", ArgKind::Word),
+            Arg::flag("global"),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let channel = match ctx.target_channel(self, &args) {
+            Some(channel) => channel,
+            None => return done(),
+        };
+
+        let keyword = args.text("match_expression").to_owned();
+
+        if let Some(required) = ctx.protected_level(&channel, &keyword) {
+            ctx.deny(self.name(), required);
+            return done();
+        }
+
+        let remove = RemoveCommand {
+            channel,
+            match_expr: keyword.clone(),
+        };
+
+        Box::new(ctx.query(remove).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(removed) if removed > 0 => {
+                    ctx.send_commands_update()?;
+                    ctx.reply("Command has been removed!");
+                }
+                Ok(_) => ctx.reply(format!("Command {} does not exist!", keyword)),
+                Err(err) => ctx.reply_failure("Command could not be removed", err),
+            }
+            Ok(())
+        }))
+    }
+}
@@ -0,0 +1,70 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::RenameCommand;
+use command::{done, BotCommand, CommandContext, CommandFuture, KeywordUse};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+
+/// Change the match expression of an existing command
+pub struct Rename;
+
+impl BotCommand for Rename {
+    fn name(&self) -> &'static str {
+        "rename"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("match_expression", ArgKind::Word),
+            Arg::required("new_match_expression", ArgKind::Word),
+            Arg::flag("global"),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let channel = match ctx.target_channel(self, &args) {
+            Some(channel) => channel,
+            None => return done(),
+        };
+
+        let keyword = args.text("match_expression").to_owned();
+        let new_keyword = args.text("new_match_expression").to_owned();
+
+        if let Some(required) = ctx.protected_level(&channel, &keyword) {
+            ctx.deny(self.name(), required);
+            return done();
+        }
+
+        let taken = ctx
+            .keyword_uses(&channel, &new_keyword)
+            .into_iter()
+            .find(|keyword_use| *keyword_use != KeywordUse::Global);
+
+        if let Some(keyword_use) = taken {
+            ctx.reply(format!("{} is already {}!", new_keyword, keyword_use.describe()));
+            return done();
+        }
+
+        let rename = RenameCommand {
+            channel,
+            match_expr: keyword.clone(),
+            new_match_expr: new_keyword.clone(),
+        };
+
+        Box::new(ctx.query(rename).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(changed) if changed > 0 => {
+                    ctx.send_commands_update()?;
+                    ctx.reply(format!("{} has been renamed to {}!", keyword, new_keyword));
+                }
+                Ok(_) => ctx.reply(format!("Command {} does not exist!", keyword)),
+                Err(err) => ctx.reply_failure("Command could not be renamed", err),
+            }
+            Ok(())
+        }))
+    }
+}
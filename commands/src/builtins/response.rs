@@ -0,0 +1,284 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::{
+    AddCommandResponse, RemoveCommandResponse, ResponseMode, SetCommandResponseMode,
+    SetCommandResponseWeight, MAX_RESPONSE_LENGTH,
+};
+use command::{done, paginate, BotCommand, CommandContext, CommandFuture, MAX_CHAT_MESSAGE_LENGTH};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+use template::Template;
+
+/// Highest weight of a response, responses weigh 1 unless told otherwise
+const MAX_RESPONSE_WEIGHT: i64 = 100;
+
+/// Add, remove or list the response variants of a stored command, or set how one is picked
+/// The response of the command is always the first one, changed with edit, its weight can be set too.
+pub struct Response;
+
+impl BotCommand for Response {
+    fn name(&self) -> &'static str {
+        "response"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required(
+                "action",
+                ArgKind::Choice(&["add", "remove", "list", "mode", "weight"]),
+            ),
+            Arg::required("match_expression", ArgKind::Word),
+            Arg::option("weight", ArgKind::Integer),
+            Arg::flag("global"),
+            Arg::optional("value", ArgKind::Rest),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let channel = match ctx.target_channel(self, &args) {
+            Some(channel) => channel,
+            None => return done(),
+        };
+
+        let keyword = args.text("match_expression").to_owned();
+        let value = args.text("value").to_owned();
+
+        let responses = {
+            let commands = ctx.commands.read().expect("READ ERROR");
+            commands.find(&channel, &keyword).map(|cmd| {
+                let own = format!("{} (weight {})", cmd.command, cmd.response_weight);
+                let mut responses = vec![own];
+                responses.extend(
+                    commands
+                        .responses(cmd)
+                        .iter()
+                        .map(|variant| format!("{} (weight {})", variant.response, variant.weight)),
+                );
+                (cmd.response_mode(), responses)
+            })
+        };
+
+        let (response_mode, responses) = match responses {
+            Some(responses) => responses,
+            None => {
+                ctx.reply(format!("Command {} does not exist!", keyword));
+                return done();
+            }
+        };
+
+        if args.text("action") == "list" {
+            let numbered: Vec<String> = responses
+                .iter()
+                .enumerate()
+                .map(|(index, response)| format!("{}. {}", index + 1, response))
+                .collect();
+            // Leaves room for the mention and header
+            if let Some(page) = paginate(&numbered, MAX_CHAT_MESSAGE_LENGTH - 100).first() {
+                ctx.reply(format!("{} ({}): {}", keyword, response_mode.as_str(), page));
+            }
+            return done();
+        }
+
+        if let Some(required) = ctx.protected_level(&channel, &keyword) {
+            ctx.deny(self.name(), required);
+            return done();
+        }
+
+        match args.text("action") {
+            "add" => match valid_weight(&ctx, args.integer("weight").unwrap_or(1)) {
+                Some(weight) => add(ctx, self, channel, keyword, value, weight),
+                None => done(),
+            },
+            "remove" => match value.parse::<usize>() {
+                Ok(1) => {
+                    ctx.reply(format!(
+                        "Response 1 is the command itself, use {}edit to change it!",
+                        ctx.prefix
+                    ));
+                    done()
+                }
+                Ok(number) if number > 1 && number <= responses.len() => {
+                    remove(ctx, channel, keyword, number)
+                }
+                _ => {
+                    ctx.reply(format!(
+                        "{} has responses 1 to {}!",
+                        keyword,
+                        responses.len()
+                    ));
+                    done()
+                }
+            },
+            "weight" => {
+                let numbers: Vec<i64> = value
+                    .split_whitespace()
+                    .map(|number| number.parse())
+                    .collect::<Result<_, _>>()
+                    .unwrap_or_default();
+                match numbers.as_slice() {
+                    &[number, weight] if number >= 1 && number as usize <= responses.len() => {
+                        let number = number as usize;
+                        match valid_weight(&ctx, weight) {
+                            Some(weight) => set_weight(ctx, channel, keyword, number, weight),
+                            None => done(),
+                        }
+                    }
+                    _ => {
+                        ctx.reply(format!(
+                            "Use {}response weight {} <number> <weight>, {} has responses 1 to {}!",
+                            ctx.prefix,
+                            keyword,
+                            keyword,
+                            responses.len()
+                        ));
+                        done()
+                    }
+                }
+            }
+            _ => match ResponseMode::parse(&value) {
+                Some(response_mode) => set_mode(ctx, channel, keyword, response_mode),
+                None => {
+                    ctx.reply("Mode should be random, roundrobin or weighted!");
+                    done()
+                }
+            },
+        }
+    }
+}
+
+fn add(
+    ctx: CommandContext,
+    command: &dyn BotCommand,
+    channel: String,
+    keyword: String,
+    response: String,
+    weight: i32,
+) -> CommandFuture {
+    if response.is_empty() {
+        ctx.usage(command);
+        return done();
+    }
+
+    if response.len() > MAX_RESPONSE_LENGTH {
+        ctx.reply(format!(
+            "Responses can't be longer than {} characters!",
+            MAX_RESPONSE_LENGTH
+        ));
+        return done();
+    }
+
+    if let Err(err) = Template::parse(&response) {
+        ctx.reply(format!("Response was not added, {}!", err));
+        return done();
+    }
+
+    let add_response = AddCommandResponse {
+        channel,
+        match_expr: keyword.clone(),
+        response,
+        weight,
+    };
+
+    Box::new(ctx.query(add_response).then(move |result| -> Result<(), Error> {
+        match result {
+            Ok(added) if added > 0 => {
+                ctx.send_commands_update()?;
+                ctx.reply(format!("Response added to {}!", keyword));
+            }
+            Ok(_) => ctx.reply(format!("Command {} does not exist!", keyword)),
+            Err(err) => ctx.reply_failure("Response could not be added", err),
+        }
+        Ok(())
+    }))
+}
+
+/// `number` counts the response of the command itself, which can't be removed
+fn remove(ctx: CommandContext, channel: String, keyword: String, number: usize) -> CommandFuture {
+    let remove_response = RemoveCommandResponse {
+        channel,
+        match_expr: keyword.clone(),
+        number: number - 1,
+    };
+
+    Box::new(ctx.query(remove_response).then(move |result| -> Result<(), Error> {
+        match result {
+            Ok(removed) if removed > 0 => {
+                ctx.send_commands_update()?;
+                ctx.reply(format!("Response {} of {} has been removed!", number, keyword));
+            }
+            Ok(_) => ctx.reply(format!("{} has no response {}!", keyword, number)),
+            Err(err) => ctx.reply_failure("Response could not be removed", err),
+        }
+        Ok(())
+    }))
+}
+
+/// `number` counts the response of the command itself, as listed
+fn set_weight(
+    ctx: CommandContext,
+    channel: String,
+    keyword: String,
+    number: usize,
+    weight: i32,
+) -> CommandFuture {
+    let set_weight = SetCommandResponseWeight {
+        channel,
+        match_expr: keyword.clone(),
+        number,
+        weight,
+    };
+
+    Box::new(ctx.query(set_weight).then(move |result| -> Result<(), Error> {
+        match result {
+            Ok(changed) if changed > 0 => {
+                ctx.send_commands_update()?;
+                ctx.reply(format!("Response {} of {} now weighs {}!", number, keyword, weight));
+            }
+            Ok(_) => ctx.reply(format!("{} has no response {}!", keyword, number)),
+            Err(err) => ctx.reply_failure("Weight could not be set", err),
+        }
+        Ok(())
+    }))
+}
+
+/// The weight if it is allowed, otherwise the user is told which are
+fn valid_weight(ctx: &CommandContext, weight: i64) -> Option<i32> {
+    if weight < 1 || weight > MAX_RESPONSE_WEIGHT {
+        ctx.reply(format!("Weight should be between 1 and {}!", MAX_RESPONSE_WEIGHT));
+        return None;
+    }
+    Some(weight as i32)
+}
+
+fn set_mode(
+    ctx: CommandContext,
+    channel: String,
+    keyword: String,
+    response_mode: ResponseMode,
+) -> CommandFuture {
+    let set_mode = SetCommandResponseMode {
+        channel,
+        match_expr: keyword.clone(),
+        response_mode,
+    };
+
+    Box::new(ctx.query(set_mode).then(move |result| -> Result<(), Error> {
+        match result {
+            Ok(changed) if changed > 0 => {
+                ctx.send_commands_update()?;
+                ctx.reply(format!(
+                    "{} now picks its responses in {} mode!",
+                    keyword,
+                    response_mode.as_str()
+                ));
+            }
+            Ok(_) => ctx.reply(format!("Command {} does not exist!", keyword)),
+            Err(err) => ctx.reply_failure("Mode could not be set", err),
+        }
+        Ok(())
+    }))
+}
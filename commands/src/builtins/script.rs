@@ -0,0 +1,99 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::{CheckScript, SetCommandScript};
+use command::{done, BotCommand, CommandContext, CommandFuture};
+use failure::Error;
+use futures::{future, Future};
+use permissions::PermissionLevel;
+
+/// Set the script run by an existing command instead of its response, or remove it
+pub struct Script;
+
+impl BotCommand for Script {
+    fn name(&self) -> &'static str {
+        "script"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("match_expression", ArgKind::Word),
+            Arg::optional("lua_code", ArgKind::Rest),
+            Arg::flag("global"),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let channel = match ctx.target_channel(self, &args) {
+            Some(channel) => channel,
+            None => return done(),
+        };
+
+        let (keyword, code) = (args.text("match_expression"), args.text("lua_code"));
+
+        if let Some(required) = ctx.protected_level(&channel, keyword) {
+            ctx.deny(self.name(), required);
+            return done();
+        }
+
+        let keyword = keyword.to_owned();
+        let set_script = SetCommandScript {
+            channel,
+            match_expr: keyword.clone(),
+            script: if code.is_empty() {
+                None
+            } else {
+                Some(code.to_owned())
+            },
+        };
+        let removed = set_script.script.is_none();
+
+        // Removing a script needs no check
+        let check: Box<dyn Future<Item = Result<(), String>, Error = Error>> = if removed {
+            Box::new(future::ok(Ok(())))
+        } else {
+            Box::new(
+                ctx.scripts
+                    .send(CheckScript {
+                        script: code.to_owned(),
+                    })
+                    .from_err()
+                    .map(|result| result.map_err(|err| err.to_string())),
+            )
+        };
+
+        let db = ctx.clone();
+        Box::new(
+            check
+                .and_then(move |checked| {
+                    let set: Box<dyn Future<Item = Result<usize, String>, Error = Error>> =
+                        match checked {
+                            Ok(()) => Box::new(db.query(set_script).map(Ok)),
+                            Err(err) => Box::new(future::ok(Err(err))),
+                        };
+                    set
+                })
+                .then(move |result| -> Result<(), Error> {
+                    match result {
+                        Ok(Ok(changed)) if changed > 0 => {
+                            ctx.send_commands_update()?;
+                            if removed {
+                                ctx.reply(format!("{} no longer runs a script!", keyword));
+                            } else {
+                                ctx.reply(format!("{} now runs a script!", keyword));
+                            }
+                        }
+                        Ok(Ok(_)) => ctx.reply(format!(
+                            "Command {} does not exist, use {}set to create it!",
+                            keyword, ctx.prefix
+                        )),
+                        Ok(Err(err)) => ctx.reply(format!("Script was not set, {}!", err)),
+                        Err(err) => ctx.reply_failure("Script could not be set", err),
+                    }
+                    Ok(())
+                }),
+        )
+    }
+}
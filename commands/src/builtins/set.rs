@@ -0,0 +1,114 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::CreateCommand;
+use command::{done, BotCommand, CommandContext, CommandFuture, KeywordUse};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+use template::Template;
+
+/// Create a command or replace its response
+pub struct Set;
+
+impl BotCommand for Set {
+    fn name(&self) -> &'static str {
+        "set"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required("match_expression", ArgKind::Word),
+            Arg::required("command", ArgKind::Rest),
+            Arg::flag("global"),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let channel = match ctx.target_channel(self, &args) {
+            Some(channel) => channel,
+            None => return done(),
+        };
+
+        let (keyword, response) = (args.text("match_expression"), args.text("command"));
+
+        if let Some(required) = ctx.protected_level(&channel, keyword) {
+            ctx.deny(self.name(), required);
+            return done();
+        }
+
+        // Setting an existing command replaces its response, channels may override global ones
+        let taken = ctx
+            .keyword_uses(&channel, keyword)
+            .into_iter()
+            .find(|keyword_use| match keyword_use {
+                KeywordUse::Command | KeywordUse::Global => false,
+                _ => true,
+            });
+
+        if let Some(keyword_use) = taken {
+            ctx.reply(format!("{} is already {}!", keyword, keyword_use.describe()));
+            return done();
+        }
+
+        if let Err(err) = Template::parse(response) {
+            ctx.reply(format!("Command was not set, {}!", err));
+            return done();
+        }
+
+        let create = CreateCommand {
+            channel,
+            match_expr: keyword.to_owned(),
+            command: response.to_owned(),
+        };
+
+        Box::new(ctx.query(create).then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(_) => {
+                    ctx.send_commands_update()?;
+                    ctx.reply("Command has been set!");
+                }
+                Err(err) => ctx.reply_failure("Command could not be set", err),
+            }
+            Ok(())
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use args;
+    use cold_data::cache::CommandCache;
+    use cold_data::models::Command;
+
+    #[test]
+    fn quoted_keyword_fires() {
+        let args = args::parse(&Set.args(), r#""good morning" Morning {user}!"#).unwrap();
+        let cache = CommandCache::new();
+        cache.write().unwrap().commands.push(Command {
+            channel: "channel".to_owned(),
+            match_expr: args.text("match_expression").to_owned(),
+            command: args.text("command").to_owned(),
+            permission: 0,
+            use_count: 0,
+            global_cooldown: 0,
+            user_cooldown: 0,
+            cooldown_mod_exempt: false,
+            match_mode: "exact".to_owned(),
+            script: None,
+            response_mode: "random".to_owned(),
+            response_weight: 1,
+        });
+
+        let commands = cache.read().unwrap();
+        let (cmd, keyword) = commands
+            .find_exact_prefix("channel", "good morning everyone")
+            .unwrap();
+        assert_eq!(keyword, "good morning");
+        assert_eq!(cmd.command, "Morning {user}!");
+        assert!(commands.find_exact_prefix("channel", "good evening").is_none());
+    }
+}
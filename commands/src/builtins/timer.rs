@@ -0,0 +1,153 @@
+use args::{Arg, ArgKind, Args};
+use cold_data::models::{
+    ListTimers, ReloadTimers, RemoveTimer, SetTimer, SetTimerEnabled, Timer as StoredTimer,
+    MAX_TIMER_MESSAGE_LENGTH,
+};
+use command::{done, paginate, BotCommand, CommandContext, CommandFuture, MAX_CHAT_MESSAGE_LENGTH};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+use timers::MIN_TIMER_INTERVAL;
+
+/// Manage the messages sent in the channel on an interval
+pub struct Timer;
+
+impl BotCommand for Timer {
+    fn name(&self) -> &'static str {
+        "timer"
+    }
+
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Moderator
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        vec![
+            Arg::required(
+                "action",
+                ArgKind::Choice(&["set", "remove", "enable", "disable", "list"]),
+            ),
+            Arg::optional("name", ArgKind::Word),
+            Arg::optional("interval", ArgKind::Duration),
+            Arg::option("messages", ArgKind::Integer),
+            Arg::optional("message", ArgKind::Rest),
+        ]
+    }
+
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture {
+        let action = args.text("action").to_owned();
+        let name = args.text("name").to_lowercase();
+
+        // The name is the page number when listing
+        if action == "list" {
+            return match name.parse::<usize>() {
+                Ok(page) if page > 0 => list(ctx, page),
+                _ if name.is_empty() => list(ctx, 1),
+                _ => {
+                    ctx.usage(self);
+                    done()
+                }
+            };
+        }
+
+        if name.is_empty() {
+            ctx.usage(self);
+            return done();
+        }
+
+        let change: Box<dyn Future<Item = usize, Error = Error>> = match action.as_str() {
+            "set" => {
+                let message = args.text("message").to_owned();
+                let interval = match args.duration("interval") {
+                    Some(interval) if !message.is_empty() => interval,
+                    _ => {
+                        ctx.usage(self);
+                        return done();
+                    }
+                };
+
+                if interval < MIN_TIMER_INTERVAL {
+                    ctx.reply(format!(
+                        "Timers can't be sent more often than every {}s!",
+                        MIN_TIMER_INTERVAL.as_secs()
+                    ));
+                    return done();
+                }
+
+                if message.len() > MAX_TIMER_MESSAGE_LENGTH {
+                    ctx.reply(format!(
+                        "Timer messages can't be longer than {} characters!",
+                        MAX_TIMER_MESSAGE_LENGTH
+                    ));
+                    return done();
+                }
+
+                let min_messages = args.integer("messages").unwrap_or(0);
+                ctx.query(SetTimer(StoredTimer {
+                    channel: ctx.channel.clone(),
+                    name: name.clone(),
+                    message,
+                    interval_seconds: interval.as_secs().min(i32::max_value() as u64) as i32,
+                    min_messages: min_messages.max(0).min(i32::max_value() as i64) as i32,
+                    enabled: true,
+                }))
+            }
+            "remove" => ctx.query(RemoveTimer {
+                channel: ctx.channel.clone(),
+                name: name.clone(),
+            }),
+            _ => ctx.query(SetTimerEnabled {
+                channel: ctx.channel.clone(),
+                name: name.clone(),
+                enabled: action == "enable",
+            }),
+        };
+
+        Box::new(change.then(move |result| -> Result<(), Error> {
+            match result {
+                Ok(changed) if changed > 0 => {
+                    ctx.timers.do_send(ReloadTimers);
+                    ctx.reply(match action.as_str() {
+                        "set" => format!("Timer {} has been set!", name),
+                        "remove" => format!("Timer {} has been removed!", name),
+                        "enable" => format!("Timer {} has been enabled!", name),
+                        _ => format!("Timer {} has been disabled!", name),
+                    });
+                }
+                Ok(_) => ctx.reply(format!("Timer {} does not exist!", name)),
+                Err(err) => ctx.reply_failure("Timer could not be changed", err),
+            }
+            Ok(())
+        }))
+    }
+}
+
+/// Reply with a page of the timers of the channel and their interval
+fn list(ctx: CommandContext, page: usize) -> CommandFuture {
+    Box::new(ctx.query(ListTimers {}).then(move |result| -> Result<(), Error> {
+        let timers: Vec<String> = match result {
+            Ok(timers) => timers
+                .into_iter()
+                .filter(|timer| timer.channel == ctx.channel)
+                .map(|timer| {
+                    let paused = if timer.enabled { "" } else { ", paused" };
+                    format!("{} ({}s{})", timer.name, timer.interval_seconds, paused)
+                })
+                .collect(),
+            Err(err) => {
+                ctx.reply_failure("Timers could not be listed", err);
+                return Ok(());
+            }
+        };
+
+        // Leaves room for the mention and page header
+        let pages = paginate(&timers, MAX_CHAT_MESSAGE_LENGTH - 100);
+
+        match pages.get(page - 1) {
+            Some(timers) => ctx.reply(format!("Timers ({}/{}): {}", page, pages.len(), timers)),
+            None if pages.is_empty() => ctx.reply("This channel has no timers yet!"),
+            None => ctx.reply(format!("There are only {} pages of timers!", pages.len())),
+        }
+        Ok(())
+    }))
+}
@@ -0,0 +1,347 @@
+use actix::dev::ToEnvelope;
+use args::{self, Arg, Args, ArgsError};
+use actix::{Actor, Addr, Handler, Message};
+use cold_data::cache::{CommandCache, GLOBAL_CHANNEL};
+use cold_data::models::GetQueue;
+use cold_data::DbConnectionPool;
+use failure::Error;
+use futures::{future, Future};
+use helix::HelixCredentials;
+use irc::client::{IrcClientWriter, SendChannelMessage};
+use moderation::Permits;
+use permissions::PermissionLevel;
+use polls::PollManager;
+use raffles::RaffleManager;
+use script::ScriptRunner;
+use serde_json;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+use timers::TimerScheduler;
+use web_frontend::ws_update::{Update, UpdateServer};
+
+/// Twitch rejects longer chat messages
+pub const MAX_CHAT_MESSAGE_LENGTH: usize = 500;
+
+/// Result of a command, errors are logged by the processor
+pub type CommandFuture = Box<dyn Future<Item = (), Error = Error>>;
+
+/// A command that is run when a user writes the channel prefix followed by its name or an alias
+pub trait BotCommand: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Other names the command can be used with
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Lowest level allowed to use the command
+    fn permission(&self) -> PermissionLevel {
+        PermissionLevel::Everyone
+    }
+
+    /// Arguments the command takes, used to parse them and to show how the command is used
+    fn args(&self) -> Vec<Arg> {
+        Vec::new()
+    }
+
+    /// How the command is used, without the prefix, e.g. `set <match_expression> <command...>`
+    fn usage(&self) -> String {
+        args::usage(self.name(), &self.args())
+    }
+
+    /// Run the command with its parsed arguments
+    fn execute(&self, ctx: CommandContext, args: Args) -> CommandFuture;
+}
+
+/// Commands available in every channel, by name and alias
+/// Commands registered later replace earlier ones with the same name.
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: Vec<Arc<dyn BotCommand>>,
+    names: HashMap<String, usize>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with every built-in command
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        ::builtins::register(&mut registry);
+        registry
+    }
+
+    pub fn register<C: BotCommand + 'static>(&mut self, command: C) -> &mut Self {
+        let index = self.commands.len();
+
+        self.names.insert(command.name().to_owned(), index);
+        for alias in command.aliases() {
+            self.names.insert((*alias).to_owned(), index);
+        }
+        self.commands.push(Arc::new(command));
+
+        self
+    }
+
+    /// Find a command by name or alias
+    pub fn get(&self, name: &str) -> Option<Arc<dyn BotCommand>> {
+        self.names
+            .get(name)
+            .map(|index| self.commands[*index].clone())
+    }
+
+    /// Every command that can still be used, in registration order
+    pub fn commands(&self) -> Vec<Arc<dyn BotCommand>> {
+        self.commands
+            .iter()
+            .enumerate()
+            .filter(|(index, command)| self.names.get(command.name()) == Some(index))
+            .map(|(_, command)| command.clone())
+            .collect()
+    }
+}
+
+/// Everything a command can use while it runs
+#[derive(Clone)]
+pub struct CommandContext {
+    pub db: Addr<DbConnectionPool>,
+    pub irc_writer: Addr<IrcClientWriter>,
+    pub commands: CommandCache,
+    pub update_server: Addr<UpdateServer>,
+    pub registry: Arc<CommandRegistry>,
+    pub scripts: Addr<ScriptRunner>,
+    pub timers: Addr<TimerScheduler>,
+    pub permits: Permits,
+    pub polls: Addr<PollManager>,
+    pub raffles: Addr<RaffleManager>,
+    pub helix: Option<HelixCredentials>,
+    pub channel: String,
+    /// Twitch id of the channel, from the message tags
+    pub room_id: Option<String>,
+    pub user: String,
+    /// Level of the user in the channel
+    pub level: PermissionLevel,
+    /// The user is subscribed to the channel, whatever their level
+    pub subscriber: bool,
+    /// Prefix of the channel, for messages that mention other commands
+    pub prefix: String,
+}
+
+/// Something already answering to a keyword in a channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeywordUse {
+    BuiltIn,
+    /// Counters are used before stored commands
+    Counter,
+    Command,
+    Alias,
+    /// A global command or alias, which channels may override
+    Global,
+}
+
+impl KeywordUse {
+    /// As said in replies
+    pub fn describe(&self) -> &'static str {
+        match self {
+            KeywordUse::BuiltIn => "a built-in command",
+            KeywordUse::Counter => "a counter",
+            KeywordUse::Command => "a command",
+            KeywordUse::Alias => "an alias",
+            KeywordUse::Global => "a global command",
+        }
+    }
+}
+
+impl CommandContext {
+    /// What already answers to `keyword` in a channel, in the order chat messages look them up
+    pub fn keyword_uses(&self, channel: &str, keyword: &str) -> Vec<KeywordUse> {
+        let commands = self.commands.read().expect("READ ERROR");
+        let global = channel != GLOBAL_CHANNEL
+            && (commands.find(GLOBAL_CHANNEL, keyword).is_some()
+                || commands.find_alias(GLOBAL_CHANNEL, keyword).is_some());
+        let uses = [
+            (KeywordUse::BuiltIn, self.registry.get(keyword).is_some()),
+            (KeywordUse::Counter, commands.counter(channel, keyword).is_some()),
+            (KeywordUse::Command, commands.find(channel, keyword).is_some()),
+            (KeywordUse::Alias, commands.find_alias(channel, keyword).is_some()),
+            (KeywordUse::Global, global),
+        ];
+
+        uses.iter()
+            .filter(|(_, used)| *used)
+            .map(|(keyword_use, _)| *keyword_use)
+            .collect()
+    }
+
+    /// Send a message to the channel
+    pub fn say<S: Into<String>>(&self, message: S) {
+        self.irc_writer.do_send(SendChannelMessage {
+            channel: self.channel.clone(),
+            message: message.into(),
+        });
+    }
+
+    /// Send a message to the channel mentioning the user
+    pub fn reply<S: AsRef<str>>(&self, message: S) {
+        self.say(format!("@{} {}", self.user, message.as_ref()));
+    }
+
+    /// Log an error and tell the user something went wrong
+    pub fn reply_failure<E: Debug>(&self, what: &str, err: E) {
+        println!("Error with command {:?}", err);
+        self.reply(format!("{}, ask the bot owner to check logs!", what));
+    }
+
+    /// Tell the user how a command is used
+    pub fn usage(&self, command: &dyn BotCommand) {
+        self.reply(format!(
+            "{} command should be in the form: \"{}{}\"!",
+            command.name(),
+            self.prefix,
+            command.usage()
+        ));
+    }
+
+    /// Tell the user why their arguments were rejected and how the command is used
+    pub fn invalid_args(&self, command: &dyn BotCommand, err: ArgsError) {
+        self.reply(format!(
+            "Invalid arguments, {}. {} command should be in the form: \"{}{}\"!",
+            err,
+            command.name(),
+            self.prefix,
+            command.usage()
+        ));
+    }
+
+    /// Tell the user they can't use a command, unless the channel ignores them silently
+    pub fn deny(&self, command: &str, required: PermissionLevel) {
+        let deny_reply = self
+            .commands
+            .read()
+            .expect("READ ERROR")
+            .deny_reply(&self.channel);
+
+        if deny_reply {
+            self.reply(format!(
+                "You need to be {} or higher to use {}!",
+                required, command
+            ));
+        }
+    }
+
+    /// Channel whose commands a built-in changes, every channel when `--global` is used
+    /// Only owners manage global commands, other users are denied and `None` is returned.
+    pub fn target_channel(&self, command: &dyn BotCommand, args: &Args) -> Option<String> {
+        if !args.has("global") {
+            return Some(self.channel.clone());
+        }
+
+        if self.level < PermissionLevel::Owner {
+            self.deny(&format!("{} --global", command.name()), PermissionLevel::Owner);
+            return None;
+        }
+
+        Some(GLOBAL_CHANNEL.to_owned())
+    }
+
+    /// Level of the stored command `keyword` of `channel` if the user is not allowed to change it
+    pub fn protected_level(&self, channel: &str, keyword: &str) -> Option<PermissionLevel> {
+        let commands = self.commands.read().expect("READ ERROR");
+        commands
+            .find(channel, keyword)
+            .map(|cmd| PermissionLevel::from_i32(cmd.permission))
+            .filter(|required| self.level < *required)
+    }
+
+    /// Send the JSON of every command to the frontend
+    pub fn send_commands_update(&self) -> Result<(), serde_json::Error> {
+        let commands = self.commands.read().expect("READ ERROR");
+        let update = Update::Commands {
+            commands: &commands.commands,
+        };
+        self.update_server.do_send(update.mass_send()?);
+        Ok(())
+    }
+
+    /// Send the new value of a counter to the frontend
+    pub fn send_counter_update(&self, name: &str, value: i32) -> Result<(), serde_json::Error> {
+        let update = Update::Counter {
+            channel: &self.channel,
+            name,
+            value,
+            removed: false,
+        };
+        self.update_server.do_send(update.mass_send()?);
+        Ok(())
+    }
+
+    /// Tell the frontend that a counter was removed
+    pub fn send_counter_removed(&self, name: &str) -> Result<(), serde_json::Error> {
+        let update = Update::Counter {
+            channel: &self.channel,
+            name,
+            value: 0,
+            removed: true,
+        };
+        self.update_server.do_send(update.mass_send()?);
+        Ok(())
+    }
+
+    /// Send the viewer queue of the channel to the frontend, errors are only logged
+    pub fn send_queue_update(&self) -> CommandFuture {
+        let update_server = self.update_server.clone();
+
+        Box::new(
+            self.query(GetQueue {
+                channel: self.channel.clone(),
+            }).then(move |result| -> Result<(), Error> {
+                match result.and_then(|queue| Ok(Update::Queue { queue: &queue }.mass_send()?)) {
+                    Ok(update) => update_server.do_send(update),
+                    Err(err) => println!("Could not send queue update {:?}", err),
+                }
+                Ok(())
+            }),
+        )
+    }
+
+    /// Send a message to the database, mailbox and query errors are both returned as errors
+    pub fn query<M, T>(&self, msg: M) -> Box<dyn Future<Item = T, Error = Error>>
+    where
+        M: Message<Result = Result<T, Error>> + Send + 'static,
+        T: Send + 'static,
+        DbConnectionPool: Handler<M>,
+        <DbConnectionPool as Actor>::Context: ToEnvelope<DbConnectionPool, M>,
+    {
+        Box::new(self.db.send(msg).from_err().and_then(|result| result))
+    }
+}
+
+/// A command that already finished
+pub fn done() -> CommandFuture {
+    Box::new(future::ok(()))
+}
+
+/// Split `items` in pages of at most `max_length` characters once joined by ", "
+pub fn paginate(items: &[String], max_length: usize) -> Vec<String> {
+    let mut pages = Vec::new();
+    let mut page = String::new();
+
+    for item in items {
+        if !page.is_empty() && page.len() + 2 + item.len() > max_length {
+            pages.push(page.split_off(0));
+        }
+        if !page.is_empty() {
+            page.push_str(", ");
+        }
+        page.push_str(item);
+    }
+
+    if !page.is_empty() {
+        pages.push(page);
+    }
+
+    pages
+}
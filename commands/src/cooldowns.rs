@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Entries are pruned once a map grows past this size
+const PRUNE_THRESHOLD: usize = 1000;
+
+/// When stored commands can be used again, kept in memory by the command processor
+#[derive(Clone, Default)]
+pub struct Cooldowns {
+    inner: Arc<Mutex<CooldownsInner>>,
+}
+
+#[derive(Default)]
+struct CooldownsInner {
+    /// (channel, trigger) -> when anyone can use it again
+    global: HashMap<(String, String), Instant>,
+    /// (channel, trigger, user) -> when the user can use it again
+    users: HashMap<(String, String, String), Instant>,
+}
+
+impl Cooldowns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a use of a command and returns true, or returns false if it is still on cooldown
+    pub fn try_use(
+        &self,
+        channel: &str,
+        keyword: &str,
+        user: &str,
+        global: Duration,
+        per_user: Duration,
+    ) -> bool {
+        let mut inner = self.inner.lock().expect("COOLDOWN LOCK");
+        let now = Instant::now();
+
+        let command_key = (channel.to_owned(), keyword.to_owned());
+        let user_key = (channel.to_owned(), keyword.to_owned(), user.to_owned());
+
+        let ready = |ready_at: Option<&Instant>| ready_at.map_or(true, |ready_at| *ready_at <= now);
+
+        if !ready(inner.global.get(&command_key)) || !ready(inner.users.get(&user_key)) {
+            return false;
+        }
+
+        if global > Duration::from_secs(0) {
+            inner.global.insert(command_key, now + global);
+        }
+        if per_user > Duration::from_secs(0) {
+            inner.users.insert(user_key, now + per_user);
+        }
+
+        inner.prune(now);
+
+        true
+    }
+}
+
+impl CooldownsInner {
+    /// Forget cooldowns that are over so the maps don't grow forever
+    fn prune(&mut self, now: Instant) {
+        if self.global.len() > PRUNE_THRESHOLD {
+            self.global.retain(|_, ready_at| *ready_at > now);
+        }
+        if self.users.len() > PRUNE_THRESHOLD {
+            self.users.retain(|_, ready_at| *ready_at > now);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use cold_data::models::{ChangeCounter, CounterChange};
+use command::{done, CommandContext, CommandFuture};
+use failure::Error;
+use futures::Future;
+use permissions::PermissionLevel;
+
+/// Longest counter name, matches the database column
+pub const MAX_COUNTER_NAME_LENGTH: usize = 60;
+
+/// Whether a counter can be named `name` and used from chat
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_COUNTER_NAME_LENGTH
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Use the counter `name` of the channel, `None` if it does not exist
+/// `name` shows the value, moderators change it with `name+`, `name-` and `name set value`.
+pub fn run(ctx: CommandContext, name: &str, args: &str) -> Option<CommandFuture> {
+    let (counter_name, step) = match name.chars().last() {
+        Some('+') => (&name[..name.len() - 1], Some(1)),
+        Some('-') => (&name[..name.len() - 1], Some(-1)),
+        _ => (name, None),
+    };
+    let counter_name = counter_name.to_lowercase();
+
+    let value = ctx
+        .commands
+        .read()
+        .expect("READ ERROR")
+        .counter(&ctx.channel, &counter_name)?;
+
+    let mut args = args.split_whitespace();
+    let change = match (step, args.next()) {
+        (Some(step), _) => CounterChange::Add(step),
+        (None, Some("set")) => match args.next().map(str::parse::<i32>) {
+            Some(Ok(new_value)) => CounterChange::Set(new_value),
+            _ => {
+                ctx.reply(format!(
+                    "Counter should be set with \"{}{} set value\"!",
+                    ctx.prefix, counter_name
+                ));
+                return Some(done());
+            }
+        },
+        (None, _) => {
+            ctx.say(format!("{}: {}", counter_name, value));
+            return Some(done());
+        }
+    };
+
+    if ctx.level < PermissionLevel::Moderator {
+        ctx.deny(name, PermissionLevel::Moderator);
+        return Some(done());
+    }
+
+    let change_counter = ChangeCounter {
+        channel: ctx.channel.clone(),
+        name: counter_name.clone(),
+        change,
+    };
+
+    Some(Box::new(ctx.query(change_counter).then(
+        move |result| -> Result<(), Error> {
+            match result {
+                Ok(Some(value)) => {
+                    ctx.send_counter_update(&counter_name, value)?;
+                    ctx.say(format!("{}: {}", counter_name, value));
+                }
+                Ok(None) => ctx.reply(format!("Counter {} does not exist!", counter_name)),
+                Err(err) => ctx.reply_failure("Counter could not be changed", err),
+            }
+            Ok(())
+        },
+    )))
+}
@@ -0,0 +1,78 @@
+use actix_web;
+use actix_web::client::{self, ClientRequest};
+use actix_web::HttpMessage;
+use failure::{err_msg, Error};
+use futures::{future, Future};
+
+/// Root of twitch's helix API
+pub const HELIX_URL: &str = "https://api.twitch.tv/helix";
+/// Information of a channel given as `broadcaster_id`
+const HELIX_CHANNELS_PATH: &str = "/channels";
+
+/// Channels returned by twitch
+#[derive(Deserialize)]
+struct Channels {
+    data: Vec<ChannelInformation>,
+}
+
+#[derive(Deserialize)]
+struct ChannelInformation {
+    /// Name of the current category, empty when none is set
+    game_name: String,
+}
+
+/// Credentials of a twitch application, every helix request needs both
+#[derive(Clone, Debug)]
+pub struct HelixCredentials {
+    pub client_id: String,
+    /// App access token, sent as a bearer token
+    pub app_token: String,
+}
+
+impl HelixCredentials {
+    /// Credentials when both the client id and the app token are configured
+    pub fn new(client_id: Option<String>, app_token: Option<String>) -> Option<Self> {
+        match (client_id, app_token) {
+            (Some(client_id), Some(app_token)) => Some(Self {
+                client_id,
+                app_token,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Authenticated GET request of a helix endpoint, `path` includes the query
+    pub fn get(&self, path: &str) -> Result<ClientRequest, actix_web::Error> {
+        client::get(format!("{}{}", HELIX_URL, path))
+            .header("Client-ID", self.client_id.as_str())
+            .header("Authorization", format!("Bearer {}", self.app_token))
+            .finish()
+    }
+
+    /// Current category of a channel, from its twitch id
+    pub fn current_game(
+        &self,
+        broadcaster_id: &str,
+    ) -> Box<dyn Future<Item = Option<String>, Error = Error>> {
+        let path = format!("{}?broadcaster_id={}", HELIX_CHANNELS_PATH, broadcaster_id);
+        let request = match self.get(&path) {
+            Ok(request) => request,
+            Err(err) => return Box::new(future::err(err_msg(err.to_string()))),
+        };
+
+        Box::new(
+            request
+                .send()
+                .from_err::<Error>()
+                .and_then(|response| response.json::<Channels>().from_err())
+                .map(|channels| {
+                    channels
+                        .data
+                        .into_iter()
+                        .next()
+                        .map(|channel| channel.game_name)
+                        .filter(|game| !game.is_empty())
+                }),
+        )
+    }
+}
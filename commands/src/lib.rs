@@ -1,28 +1,111 @@
 extern crate actix;
+extern crate actix_web;
 extern crate cold_data;
 extern crate futures;
 extern crate irc;
 extern crate web_frontend;
 extern crate serde;
+#[macro_use]
+extern crate serde_derive;
 extern crate serde_json;
+extern crate failure;
+#[macro_use]
+extern crate failure_derive;
+extern crate chrono;
+extern crate rand;
+extern crate rlua;
 
-use actix::SyncArbiter;
-use actix::SyncContext;
-use actix::{Actor, Addr, Arbiter, Context, Handler, Message};
+pub mod args;
+pub mod builtins;
+pub mod command;
+pub mod cooldowns;
+pub mod counter;
+pub mod helix;
+pub mod moderation;
+pub mod permissions;
+pub mod points;
+pub mod polls;
+pub mod raffles;
+pub mod script;
+pub mod template;
+pub mod timers;
+
+use actix::fut;
+use actix::prelude::*;
 use cold_data::cache::CommandCache;
-use cold_data::models::Command;
+use cold_data::models::{
+    GetPoints, IncrementCommandUses, LoadScriptStore, ResponseMode, SaveScriptStore,
+};
 use cold_data::DbConnectionPool;
-use futures::Future;
+use command::{done, CommandContext, CommandFuture, CommandRegistry};
+use cooldowns::Cooldowns;
+use failure::Error;
+use futures::{future, Future};
+use helix::HelixCredentials;
 use irc::client::IrcClientWriter;
+use moderation::{Permits, SpamFilter};
+use permissions::PermissionLevel;
+use points::{PointsManager, ViewerChatted};
+use polls::{PollManager, Vote};
+use raffles::{RaffleManager, RaffleMessage};
+use rand::Rng;
+use script::{RunScript, ScriptError, ScriptOutput, ScriptRunner};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use template::{plain_message, Template, TemplateContext};
+use timers::{ChatActivity, TimerScheduler};
 use web_frontend::ws_update::UpdateServer;
-use web_frontend::ws_update::MassSend;
+
+/// Commands running at the same time, other messages wait for one of them to finish
+const MAX_RUNNING_COMMANDS: usize = 16;
+/// Messages waiting for a running command to finish, more are dropped
+const MAX_QUEUED_MESSAGES: usize = 200;
+/// Time a command can take, including its database queries
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A command took longer than `COMMAND_TIMEOUT`
+#[derive(Debug, Fail)]
+#[fail(display = "command took more than {} seconds", _0)]
+pub struct CommandTimeout(u64);
 
 /// Actor that processes various test commands
+/// Commands run concurrently on the actor's arbiter, they never block it while waiting on queries.
 pub struct CommandProcessor {
     db: Addr<DbConnectionPool>,
     irc_writer: Addr<IrcClientWriter>,
     update_server: Addr<UpdateServer>,
     commands: CommandCache,
+    /// Users with the owner permission level in every channel
+    owners: Vec<String>,
+    /// Parsed responses, keyed by their source so edited commands are parsed again
+    templates: HashMap<String, Template>,
+    /// Next response of round-robin commands, by the channel using them and their match expression
+    rotations: HashMap<(String, String), usize>,
+    cooldowns: Cooldowns,
+    spam_filter: SpamFilter,
+    permits: Permits,
+    registry: Arc<CommandRegistry>,
+    scripts: Addr<ScriptRunner>,
+    timers: Addr<TimerScheduler>,
+    points: Addr<PointsManager>,
+    polls: Addr<PollManager>,
+    raffles: Addr<RaffleManager>,
+    helix: Option<HelixCredentials>,
+    /// Number of commands currently running
+    running: usize,
+    /// Messages received while `MAX_RUNNING_COMMANDS` were running
+    queued: VecDeque<ChatMessage>,
+}
+
+/// Actors started before the processor, which commands also use
+pub struct Services {
+    pub timers: Addr<TimerScheduler>,
+    pub points: Addr<PointsManager>,
+    /// Shared with the dashboard, which checks scripts before saving them
+    pub scripts: Addr<ScriptRunner>,
+    /// Credentials for twitch's API, if configured
+    pub helix: Option<HelixCredentials>,
 }
 
 impl CommandProcessor {
@@ -31,135 +114,537 @@ impl CommandProcessor {
         irc_writer: Addr<IrcClientWriter>,
         update_server: Addr<UpdateServer>,
         commands: CommandCache,
+        owners: Vec<String>,
+        registry: CommandRegistry,
+        services: Services,
     ) -> Addr<Self> {
-        SyncArbiter::start(3, move || Self {
-            db: db.clone(),
-            irc_writer: irc_writer.clone(),
-            commands: commands.clone(),
-            update_server: update_server.clone(),
+        let Services {
+            timers,
+            points,
+            scripts,
+            helix,
+        } = services;
+        report_hidden_keywords(&commands, &registry);
+
+        let permits = Permits::new();
+        let polls = PollManager::create(db.clone(), irc_writer.clone(), update_server.clone());
+        let raffles = RaffleManager::create(db.clone(), irc_writer.clone(), update_server.clone());
+
+        Arbiter::start(move |_ctx| Self {
+            db,
+            irc_writer,
+            commands,
+            update_server,
+            owners,
+            templates: HashMap::new(),
+            rotations: HashMap::new(),
+            cooldowns: Cooldowns::new(),
+            spam_filter: SpamFilter::new(permits.clone()),
+            permits,
+            registry: Arc::new(registry),
+            scripts,
+            timers,
+            points,
+            polls,
+            raffles,
+            helix,
+            running: 0,
+            queued: VecDeque::new(),
         })
     }
 }
 
-impl Actor for CommandProcessor {
-    type Context = SyncContext<Self>;
+/// Log the stored commands and aliases that a built-in of the same name keeps from being used
+fn report_hidden_keywords(commands: &CommandCache, registry: &CommandRegistry) {
+    let commands = commands.read().expect("READ ERROR");
+    for (channel, keyword) in commands.keywords() {
+        if registry.get(keyword).is_some() {
+            println!(
+                "{} in {} is hidden by the built-in command of the same name, rename it",
+                keyword, channel
+            );
+        }
+    }
 }
 
-pub struct MetaCommand {
-    pub channel: String,
-    pub user: String,
-    pub message: String,
+impl Actor for CommandProcessor {
+    type Context = Context<Self>;
 }
 
-impl Message for MetaCommand {
-    type Result = String;
-}
+impl CommandProcessor {
+    /// Check a message against the spam filter of its channel, true if it was moderated
+    fn moderate(&mut self, msg: &ChatMessage) -> bool {
+        let level = PermissionLevel::of_user(&msg.user, &msg.channel, &msg.tags, &self.owners);
+        let violation = {
+            let commands = self.commands.read().expect("READ ERROR");
+            self.spam_filter
+                .check(&commands, &msg.channel, &msg.user, level, &msg.message)
+        };
 
-impl Handler<MetaCommand> for CommandProcessor {
-    type Result = String;
+        match violation {
+            Some(violation) => {
+                let message_id = msg.tags.get("id").map(|id| id.as_str());
+                moderation::enforce(&self.irc_writer, &msg.channel, &msg.user, message_id, &violation);
+                true
+            }
+            None => false,
+        }
+    }
 
-    fn handle(
-        &mut self,
-        msg: MetaCommand,
-        _ctx: &mut Self::Context,
-    ) -> <Self as Handler<MetaCommand>>::Result {
-        let MetaCommand {
+    /// Start processing a message, the next queued message is processed once it is done
+    fn process(&mut self, msg: ChatMessage, ctx: &mut Context<Self>) {
+        let text = msg.message.clone();
+        self.running += 1;
+
+        let command = self
+            .command(msg)
+            .into_actor(self)
+            .timeout(
+                COMMAND_TIMEOUT,
+                CommandTimeout(COMMAND_TIMEOUT.as_secs()).into(),
+            )
+            .then(move |result, act, ctx| {
+                if let Err(err) = result {
+                    println!("Error with command {} {:?}", text, err);
+                }
+
+                act.running -= 1;
+                if let Some(msg) = act.queued.pop_front() {
+                    act.process(msg, ctx);
+                }
+                fut::ok(())
+            });
+
+        ctx.spawn(command);
+    }
+
+    /// Run the built-in or stored command used in a message
+    fn command(&mut self, msg: ChatMessage) -> CommandFuture {
+        let ChatMessage {
             channel,
             user,
             message,
+            tags,
         } = msg;
 
-        if let Some(index) = message.find(' ') {
-            let (command, rest) = message.split_at(index);
-            let rest = rest.trim();
-
-            match command {
-                "remove" => {
-                    let match_expr = rest.split(' ').nth(0);
-                    if let Some(match_expr) = match_expr {
-                        let result = self.db.send(cold_data::models::RemoveCommand {
-                            channel: channel.clone(),
-                            match_expr: match_expr.to_owned(),
-                        })
-                                         .from_err()
-                                         .and_then(|result| {
-                                             match result {
-                                                 Ok(res) => {
-                                                     if res > 0 {
-                                                         let commands = self.commands.read().expect("READ ERROR");
-                                                         let json_commands = serde_json::to_string(&commands.commands)?;
-                                                         self.update_server.do_send(MassSend { message: json_commands });
-
-                                                         self.irc_writer.do_send(irc::client::SendChannelMessage {
-                                                             channel,
-                                                             message: format!("@{} Command has been removed!", user),
-                                                         });
-                                                     }
-                                                     Ok(res)
-                                                 }
-                                                 Err(err) => {
-                                                     println!("Error with command {:?}", err);
-                                                     self.irc_writer.do_send(irc::client::SendChannelMessage {
-                                                         channel,
-                                                         message: format!("@{} Command could not be removed, does it exist?", user),
-                                                     });
-                                                     Err(err)
-                                                 }
-                                             }
-                                         })
-                                         .wait();
+        let prefix = self
+            .commands
+            .read()
+            .expect("READ ERROR")
+            .command_prefix(&channel)
+            .to_owned();
+
+        let level = PermissionLevel::of_user(&user, &channel, &tags, &self.owners);
+        let subscriber = PermissionLevel::is_subscriber(&tags);
+        let room_id = tags.get("room-id").cloned();
+        let ctx = self.context(channel, room_id, user, level, subscriber, prefix);
+
+        if !message.starts_with(&ctx.prefix) {
+            return self.reply_pattern(&ctx, &message);
+        }
+
+        let text = message.as_str();
+        let message = message[ctx.prefix.len()..].trim();
+
+        let (name, args) = match message.find(' ') {
+            Some(index) => message.split_at(index),
+            None => (message, ""),
+        };
+        let args = args.trim();
+
+        match self.registry.get(name) {
+            Some(command) => {
+                if ctx.level < command.permission() {
+                    ctx.deny(name, command.permission());
+                    return done();
+                }
+
+                match args::parse(&command.args(), args) {
+                    Ok(args) => command.execute(ctx, args),
+                    Err(err) => {
+                        ctx.invalid_args(&*command, err);
+                        done()
                     }
                 }
-                "set" => {
-                    let key_index = rest.find(' ');
-
-                    if let Some(key_index) = key_index {
-                        let (keyword, rest) = rest.split_at(key_index);
-
-                        let _ = self
-                            .db
-                            .send(cold_data::models::CreateCommand {
-                                channel: channel.clone(),
-                                match_expr: keyword.to_owned(),
-                                command: rest.to_owned(),
-                            })
-                            .from_err()
-                            .and_then(|result| match result {
-                                Ok(res) => {
-                                    let commands = self.commands.read().expect("READ ERROR");
-                                    let json_commands = serde_json::to_string(&commands.commands)?;
-                                    self.update_server.do_send(MassSend{message: json_commands});
-
-                                    self.irc_writer.do_send(irc::client::SendChannelMessage {
-                                        channel,
-                                        message: format!("@{} Command has been set!", user),
-                                    });
-                                    Ok(res)
-                                }
-                                Err(err) => {
-                                    println!("Error with command {:?}", err);
-                                    self.irc_writer.do_send(irc::client::SendChannelMessage {
-												  channel,
-												  message: format!("@{} Command could not be set, ask the bot owner to check logs!", user),
-											  });
-                                    Err(err)
-                                }
-                            })
-                            .wait();
-                    } else {
-                        self.irc_writer.do_send(irc::client::SendChannelMessage {
-                            channel,
-                            message: format!(
-                                "@{} set command should be in the form: \"#set match_expression command\"!",
-                                user
-                            ),
-                        });
+            }
+            None => match counter::run(ctx.clone(), name, args) {
+                Some(counter) => counter,
+                None => self.reply_stored(&ctx, message, text),
+            },
+        }
+    }
+
+    /// Context given to commands run by `user` in `channel`
+    fn context(
+        &self,
+        channel: String,
+        room_id: Option<String>,
+        user: String,
+        level: PermissionLevel,
+        subscriber: bool,
+        prefix: String,
+    ) -> CommandContext {
+        CommandContext {
+            db: self.db.clone(),
+            irc_writer: self.irc_writer.clone(),
+            commands: self.commands.clone(),
+            update_server: self.update_server.clone(),
+            registry: self.registry.clone(),
+            scripts: self.scripts.clone(),
+            timers: self.timers.clone(),
+            permits: self.permits.clone(),
+            polls: self.polls.clone(),
+            raffles: self.raffles.clone(),
+            helix: self.helix.clone(),
+            channel,
+            room_id,
+            user,
+            level,
+            subscriber,
+            prefix,
+        }
+    }
+
+    /// Reply with the exact command starting `message`, or with a pattern command matching the whole text
+    /// `message` is the text after the prefix, its keyword may be several words.
+    fn reply_stored(&mut self, ctx: &CommandContext, message: &str, text: &str) -> CommandFuture {
+        let keyword = self
+            .commands
+            .read()
+            .expect("READ ERROR")
+            .find_exact_prefix(&ctx.channel, message)
+            .map(|(_, keyword)| keyword);
+
+        match keyword {
+            Some(keyword) => {
+                let args = message[keyword.len()..].trim();
+                self.respond(ctx, keyword, args, HashMap::new())
+            }
+            None => self.reply_pattern(ctx, text),
+        }
+    }
+
+    /// Reply with the first pattern command matching a message
+    fn reply_pattern(&mut self, ctx: &CommandContext, text: &str) -> CommandFuture {
+        let matched = self
+            .commands
+            .read()
+            .expect("READ ERROR")
+            .find_pattern(&ctx.channel, text)
+            .map(|(cmd, captures)| (cmd.match_expr.clone(), captures));
+
+        match matched {
+            Some((match_expr, captures)) => self.respond(ctx, &match_expr, text, captures),
+            None => done(),
+        }
+    }
+
+    /// Reply with a stored command if the user may use it and it is not on cooldown
+    fn respond(
+        &mut self,
+        ctx: &CommandContext,
+        keyword: &str,
+        args: &str,
+        captures: HashMap<String, String>,
+    ) -> CommandFuture {
+        let stored = {
+            let commands = self.commands.read().expect("READ ERROR");
+            commands.resolve(&ctx.channel, keyword).map(|cmd| Stored {
+                channel: cmd.channel.clone(),
+                match_expr: cmd.match_expr.clone(),
+                // The response of the command comes first
+                responses: Some((cmd.command.clone(), cmd.response_weight))
+                    .into_iter()
+                    .chain(
+                        commands
+                            .responses(cmd)
+                            .iter()
+                            .map(|variant| (variant.response.clone(), variant.weight)),
+                    )
+                    .collect(),
+                response_mode: cmd.response_mode(),
+                required: PermissionLevel::from_i32(cmd.permission),
+                global_cooldown: Duration::from_secs(cmd.global_cooldown.max(0) as u64),
+                user_cooldown: Duration::from_secs(cmd.user_cooldown.max(0) as u64),
+                mod_exempt: cmd.cooldown_mod_exempt,
+                script: cmd.script.clone(),
+            })
+        };
+
+        let Stored {
+            channel,
+            match_expr,
+            responses,
+            response_mode,
+            required,
+            global_cooldown,
+            user_cooldown,
+            mod_exempt,
+            script,
+        } = match stored {
+            Some(stored) => stored,
+            None => return done(),
+        };
+
+        if ctx.level < required {
+            ctx.deny(keyword, required);
+            return done();
+        }
+
+        // Aliases share the cooldowns of their command
+        let exempt = mod_exempt && ctx.level >= PermissionLevel::Moderator;
+        if !exempt
+            && !self
+                .cooldowns
+                .try_use(&ctx.channel, &match_expr, &ctx.user, global_cooldown, user_cooldown)
+        {
+            return done();
+        }
+
+        if let Some(script) = script {
+            return self.run_script(ctx, script, args);
+        }
+
+        let response = self.pick_response(&ctx.channel, &match_expr, responses, response_mode);
+        let template = match self.template(&response) {
+            Some(template) => template,
+            None => {
+                // Stored before templates existed, sent as it was written
+                ctx.say(response);
+                return done();
+            }
+        };
+
+        let count: Box<dyn Future<Item = i32, Error = Error>> = if template.uses_count() {
+            // Global commands count their uses in every channel together
+            let increment = ctx.query(IncrementCommandUses {
+                channel,
+                match_expr,
+            });
+            Box::new(increment.or_else(|err| {
+                println!("Error with command {:?}", err);
+                Ok(0)
+            }))
+        } else {
+            Box::new(future::ok(0))
+        };
+
+        let points: Box<dyn Future<Item = i32, Error = Error>> = if template.uses_points() {
+            let balance = ctx.query(GetPoints {
+                channel: ctx.channel.clone(),
+                user: ctx.user.to_lowercase(),
+            });
+            Box::new(balance.or_else(|err| {
+                println!("Error with command {:?}", err);
+                Ok(0)
+            }))
+        } else {
+            Box::new(future::ok(0))
+        };
+
+        let ctx = ctx.clone();
+        let args = args.to_owned();
+        Box::new(count.join(points).map(move |(count, points)| {
+            let counters = ctx
+                .commands
+                .read()
+                .expect("READ ERROR")
+                .channel_counters(&ctx.channel);
+            let message = template.render(&TemplateContext {
+                user: &ctx.user,
+                channel: &ctx.channel,
+                args: &args,
+                count,
+                points,
+                counters: &counters,
+                captures: &captures,
+            });
+            ctx.say(message);
+        }))
+    }
+
+    /// Run the script of a stored command with the values stored in the channel
+    fn run_script(&self, ctx: &CommandContext, script: String, args: &str) -> CommandFuture {
+        let scripts = self.scripts.clone();
+        let run = RunScript {
+            script,
+            channel: ctx.channel.clone(),
+            user: ctx.user.clone(),
+            args: args.to_owned(),
+            store: HashMap::new(),
+        };
+
+        let output = ctx
+            .query(LoadScriptStore {
+                channel: ctx.channel.clone(),
+            })
+            .and_then(move |store| {
+                scripts
+                    .send(RunScript { store, ..run })
+                    .from_err()
+                    .and_then(|result| result.map_err(Error::from))
+            });
+
+        let ctx = ctx.clone();
+        Box::new(output.then(move |result| -> CommandFuture {
+            let ScriptOutput { messages, changes } = match result {
+                Ok(output) => output,
+                Err(err) => {
+                    match err.downcast_ref::<ScriptError>() {
+                        Some(err) => ctx.reply(format!("Script failed, {}!", err)),
+                        None => ctx.reply_failure("Script could not be run", err),
                     }
+                    return done();
                 }
-                _ => {}
+            };
+
+            // Script messages can hold any text of the user
+            for message in messages {
+                ctx.say(plain_message(&message));
+            }
+
+            if changes.is_empty() {
+                return done();
             }
+
+            ctx.query(SaveScriptStore {
+                channel: ctx.channel.clone(),
+                changes,
+            })
+        }))
+    }
+
+    /// Response sent by a command, one of its variants picked following its mode
+    fn pick_response(
+        &mut self,
+        channel: &str,
+        match_expr: &str,
+        mut responses: Vec<(String, i32)>,
+        mode: ResponseMode,
+    ) -> String {
+        if responses.len() < 2 {
+            return responses.pop().map(|(response, _)| response).unwrap_or_default();
         }
 
-        String::new()
+        let mut rng = rand::thread_rng();
+        let index = match mode {
+            ResponseMode::Random => rng.gen_range(0, responses.len()),
+            ResponseMode::RoundRobin => {
+                let key = (channel.to_owned(), match_expr.to_owned());
+                if !self.rotations.contains_key(&key) {
+                    // Commands renamed or removed since are dropped when another one starts
+                    let commands = self.commands.read().expect("READ ERROR");
+                    self.rotations
+                        .retain(|(used_in, expr), _| commands.resolve(used_in, expr).is_some());
+                }
+
+                let next = self.rotations.entry(key).or_insert(0);
+                // Variants may have been removed since the last use
+                let index = *next % responses.len();
+                *next = index + 1;
+                index
+            }
+            ResponseMode::Weighted => {
+                let total: i64 = responses
+                    .iter()
+                    .map(|(_, weight)| i64::from((*weight).max(0)))
+                    .sum();
+                if total == 0 {
+                    0
+                } else {
+                    let mut roll = rng.gen_range(0, total);
+                    responses
+                        .iter()
+                        .position(|(_, weight)| {
+                            roll -= i64::from((*weight).max(0));
+                            roll < 0
+                        })
+                        .unwrap_or(0)
+                }
+            }
+        };
+
+        responses.swap_remove(index).0
+    }
+
+    /// Parsed template of a response, parsed only the first time it is used
+    fn template(&mut self, response: &str) -> Option<Template> {
+        if !self.templates.contains_key(response) {
+            let template = Template::parse(response).ok()?;
+            self.templates.insert(response.to_owned(), template);
+        }
+
+        self.templates.get(response).cloned()
+    }
+}
+
+/// What is needed from a cached command to respond with it
+struct Stored {
+    /// Channel the command is stored in, `GLOBAL_CHANNEL` for global commands
+    channel: String,
+    match_expr: String,
+    /// Response of the command then its variants, with their weights
+    responses: Vec<(String, i32)>,
+    response_mode: ResponseMode,
+    required: PermissionLevel,
+    global_cooldown: Duration,
+    user_cooldown: Duration,
+    mod_exempt: bool,
+    script: Option<String>,
+}
+
+/// A message sent in a channel the bot joined
+pub struct ChatMessage {
+    pub channel: String,
+    pub user: String,
+    pub message: String,
+    /// IRCv3 tags of the message, used for badges
+    pub tags: HashMap<String, String>,
+}
+
+impl Message for ChatMessage {
+    type Result = ();
+}
+
+impl Handler<ChatMessage> for CommandProcessor {
+    type Result = ();
+
+    fn handle(&mut self, msg: ChatMessage, ctx: &mut Self::Context) {
+        // Moderated messages don't run commands or count as chat activity
+        if self.moderate(&msg) {
+            return;
+        }
+
+        self.timers.do_send(ChatActivity {
+            channel: msg.channel.clone(),
+        });
+        self.polls.do_send(Vote {
+            channel: msg.channel.clone(),
+            user: msg.user.clone(),
+            message: msg.message.clone(),
+        });
+        let subscriber = PermissionLevel::is_subscriber(&msg.tags);
+        self.raffles.do_send(RaffleMessage {
+            channel: msg.channel.clone(),
+            user: msg.user.clone(),
+            subscriber,
+            message: msg.message.clone(),
+        });
+        self.points.do_send(ViewerChatted {
+            channel: msg.channel.clone(),
+            user: msg.user.clone(),
+            subscriber,
+        });
+
+        if self.running < MAX_RUNNING_COMMANDS {
+            self.process(msg, ctx);
+        } else if self.queued.len() < MAX_QUEUED_MESSAGES {
+            self.queued.push_back(msg);
+        } else {
+            println!(
+                "Too many commands running, message from {} in {} was dropped",
+                msg.user, msg.channel
+            );
+        }
     }
 }
@@ -0,0 +1,422 @@
+use actix::Addr;
+use cold_data::cache::CommandCacheInner;
+use cold_data::models::ModerationRule;
+use irc::client::{IrcClientWriter, SendChannelMessage};
+use permissions::PermissionLevel;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Names of the rules, in the order they are checked
+pub const RULES: &[&str] = &["links", "phrases", "length", "caps", "symbols", "repetition", "repeats"];
+/// Names of the actions, from the mildest to the strongest
+pub const ACTIONS: &[&str] = &["warn", "delete", "timeout", "ban"];
+
+/// Filter list of the domains links can point to
+pub const ALLOWED_DOMAINS: &str = "domains";
+/// Filter list of the phrases that are not allowed in chat
+pub const BANNED_PHRASES: &str = "phrases";
+
+/// Longest timeout Twitch allows
+pub const MAX_TIMEOUT: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+/// Shorter messages are not checked for caps and symbols, a short "OK!" is fine
+const MIN_CHECKED_LENGTH: usize = 15;
+/// Identical messages sent within this window count as repeats
+const REPEAT_WINDOW: Duration = Duration::from_secs(5 * 60);
+/// Offenses are forgotten once a user behaved for this long
+const STRIKE_RESET: Duration = Duration::from_secs(30 * 60);
+/// Entries are pruned once a map grows past this size
+const PRUNE_THRESHOLD: usize = 1000;
+
+/// A rule of the spam filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// Links to domains that are not allowed, threshold unused
+    Links,
+    /// Phrases of the banned list, threshold unused
+    Phrases,
+    /// Messages longer than the threshold in characters
+    Length,
+    /// Messages with at least the threshold percent of capital letters
+    Caps,
+    /// Messages with at least the threshold percent of symbols
+    Symbols,
+    /// The same character more than the threshold times in a row
+    Repetition,
+    /// The same message sent the threshold times in a few minutes
+    Repeats,
+}
+
+impl Rule {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "links" => Some(Rule::Links),
+            "phrases" => Some(Rule::Phrases),
+            "length" => Some(Rule::Length),
+            "caps" => Some(Rule::Caps),
+            "symbols" => Some(Rule::Symbols),
+            "repetition" => Some(Rule::Repetition),
+            "repeats" => Some(Rule::Repeats),
+            _ => None,
+        }
+    }
+
+    /// Threshold used until a channel sets its own
+    pub fn default_threshold(self) -> i32 {
+        match self {
+            Rule::Links | Rule::Phrases => 0,
+            Rule::Length => 350,
+            Rule::Caps => 70,
+            Rule::Symbols => 50,
+            Rule::Repetition => 10,
+            Rule::Repeats => 3,
+        }
+    }
+
+    /// Whether the rule can use a threshold, percentages are at most 100
+    pub fn valid_threshold(self, threshold: i32) -> bool {
+        match self {
+            Rule::Links | Rule::Phrases => true,
+            Rule::Caps | Rule::Symbols => threshold >= 1 && threshold <= 100,
+            Rule::Length => threshold >= 1,
+            Rule::Repetition | Rule::Repeats => threshold >= 2,
+        }
+    }
+
+    /// Why a message was moderated, shown to the user
+    fn reason(self) -> &'static str {
+        match self {
+            Rule::Links => "links are not allowed",
+            Rule::Phrases => "that phrase is not allowed",
+            Rule::Length => "your message is too long",
+            Rule::Caps => "please don't use so many caps",
+            Rule::Symbols => "please don't use so many symbols",
+            Rule::Repetition => "please don't repeat characters so much",
+            Rule::Repeats => "please don't send the same message again",
+        }
+    }
+
+    /// Whether a message breaks the rule, `Repeats` is checked by the filter
+    fn breaks(self, threshold: i32, message: &str, terms: &[String]) -> bool {
+        let threshold = threshold.max(0) as usize;
+
+        match self {
+            Rule::Links => links(message).any(|host| !allowed_domain(&host, terms)),
+            Rule::Phrases => {
+                let message = message.to_lowercase();
+                terms.iter().any(|phrase| message.contains(phrase.as_str()))
+            }
+            Rule::Length => message.chars().count() > threshold,
+            Rule::Caps => {
+                let letters: Vec<char> = message.chars().filter(|c| c.is_alphabetic()).collect();
+                let caps = letters.iter().filter(|c| c.is_uppercase()).count();
+                letters.len() >= MIN_CHECKED_LENGTH && caps * 100 >= threshold * letters.len()
+            }
+            Rule::Symbols => {
+                let visible: Vec<char> = message.chars().filter(|c| !c.is_whitespace()).collect();
+                let symbols = visible.iter().filter(|c| !c.is_alphanumeric()).count();
+                visible.len() >= MIN_CHECKED_LENGTH && symbols * 100 >= threshold * visible.len()
+            }
+            Rule::Repetition => longest_run(message) > threshold,
+            Rule::Repeats => false,
+        }
+    }
+}
+
+/// What is done to a user who broke a rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    /// Only tell the user
+    Warn,
+    /// Delete the message
+    Delete,
+    /// Time the user out for the rule's timeout
+    Timeout,
+    Ban,
+}
+
+impl Action {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "warn" => Some(Action::Warn),
+            "delete" => Some(Action::Delete),
+            "timeout" => Some(Action::Timeout),
+            "ban" => Some(Action::Ban),
+            _ => None,
+        }
+    }
+
+    /// The next stronger action, `Ban` stays a ban
+    fn escalate(self) -> Self {
+        match self {
+            Action::Warn => Action::Delete,
+            Action::Delete => Action::Timeout,
+            Action::Timeout | Action::Ban => Action::Ban,
+        }
+    }
+}
+
+/// Users allowed to post links for a while, kept in memory and shared with the permit command
+#[derive(Clone, Default)]
+pub struct Permits {
+    /// (channel, user) -> messages with links left and when the permit expires
+    inner: Arc<Mutex<HashMap<(String, String), (u32, Instant)>>>,
+}
+
+impl Permits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow a user to post `links` messages with links until `duration` passed
+    pub fn grant(&self, channel: &str, user: &str, links: u32, duration: Duration) {
+        let mut permits = self.inner.lock().expect("PERMIT LOCK");
+        let now = Instant::now();
+
+        if permits.len() > PRUNE_THRESHOLD {
+            permits.retain(|_, (_, expires_at)| *expires_at > now);
+        }
+        permits.insert(
+            (channel.to_lowercase(), user.to_lowercase()),
+            (links, now + duration),
+        );
+    }
+
+    /// Use one link of a user's permit, false if they have none left
+    fn consume(&self, channel: &str, user: &str) -> bool {
+        let mut permits = self.inner.lock().expect("PERMIT LOCK");
+        let key = (channel.to_lowercase(), user.to_lowercase());
+
+        let usable = match permits.get_mut(&key) {
+            Some((left, expires_at)) if *expires_at > Instant::now() && *left > 0 => {
+                *left -= 1;
+                true
+            }
+            Some(_) => false,
+            None => return false,
+        };
+
+        if !usable || permits[&key].0 == 0 {
+            permits.remove(&key);
+        }
+        usable
+    }
+}
+
+/// A rule broken by a message and what is done about it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub rule: Rule,
+    pub action: Action,
+    pub timeout: Duration,
+}
+
+/// Checks chat messages against the rules of their channel
+/// Offenses of a user escalate from the rule's action to its strongest action.
+pub struct SpamFilter {
+    permits: Permits,
+    /// (channel, user) -> offenses and when the last one happened
+    strikes: HashMap<(String, String), (u32, Instant)>,
+    /// (channel, user) -> recent messages, for the repeats rule
+    recent: HashMap<(String, String), VecDeque<(String, Instant)>>,
+}
+
+impl SpamFilter {
+    pub fn new(permits: Permits) -> Self {
+        Self {
+            permits,
+            strikes: HashMap::new(),
+            recent: HashMap::new(),
+        }
+    }
+
+    /// First rule of the channel a message breaks, `None` if it can be shown
+    pub fn check(
+        &mut self,
+        commands: &CommandCacheInner,
+        channel: &str,
+        user: &str,
+        level: PermissionLevel,
+        message: &str,
+    ) -> Option<Violation> {
+        let now = Instant::now();
+        let key = (channel.to_owned(), user.to_owned());
+
+        let broken = RULES
+            .iter()
+            .filter_map(|name| commands.moderation_rule(channel, name))
+            .filter(|rule| rule.enabled && level < PermissionLevel::from_i32(rule.exempt_level))
+            .filter_map(|settings| Rule::parse(&settings.rule).map(|rule| (rule, settings)))
+            .find(|(rule, settings)| {
+                if *rule == Rule::Repeats {
+                    return self.repeats(&key, message, now) >= settings.threshold.max(0) as usize;
+                }
+
+                let terms = match rule {
+                    Rule::Links => commands.moderation_terms(channel, ALLOWED_DOMAINS),
+                    Rule::Phrases => commands.moderation_terms(channel, BANNED_PHRASES),
+                    _ => &[][..],
+                };
+                // A permitted user's link is allowed, using up one link of the permit
+                rule.breaks(settings.threshold, message, terms)
+                    && !(*rule == Rule::Links && self.permits.consume(channel, user))
+            });
+
+        self.prune(now);
+
+        let (rule, settings) = broken?;
+        Some(Violation {
+            rule,
+            action: self.strike(key, settings, now),
+            timeout: Duration::from_secs(settings.timeout_seconds.max(1) as u64).min(MAX_TIMEOUT),
+        })
+    }
+
+    /// Remember a message and count the identical ones the user sent recently, itself included
+    fn repeats(&mut self, key: &(String, String), message: &str, now: Instant) -> usize {
+        let message = message.trim().to_lowercase();
+        let recent = self.recent.entry(key.clone()).or_insert_with(VecDeque::new);
+
+        while recent
+            .front()
+            .map_or(false, |(_, sent_at)| now.duration_since(*sent_at) > REPEAT_WINDOW)
+        {
+            recent.pop_front();
+        }
+
+        recent.push_back((message.clone(), now));
+        recent.iter().filter(|(sent, _)| *sent == message).count()
+    }
+
+    /// Record an offense and pick its action, each new offense is one step stronger
+    fn strike(&mut self, key: (String, String), settings: &ModerationRule, now: Instant) -> Action {
+        let first = Action::parse(&settings.action).unwrap_or(Action::Warn);
+        let strongest = Action::parse(&settings.max_action).unwrap_or(first).max(first);
+
+        let strikes = match self.strikes.get(&key) {
+            Some((strikes, last)) if now.duration_since(*last) < STRIKE_RESET => strikes + 1,
+            _ => 1,
+        };
+        self.strikes.insert(key, (strikes, now));
+
+        (1..strikes).fold(first, |action, _| action.escalate().min(strongest))
+    }
+
+    /// Forget old offenses and messages so the maps don't grow forever
+    fn prune(&mut self, now: Instant) {
+        if self.strikes.len() > PRUNE_THRESHOLD {
+            self.strikes
+                .retain(|_, (_, last)| now.duration_since(*last) < STRIKE_RESET);
+        }
+        if self.recent.len() > PRUNE_THRESHOLD {
+            self.recent.retain(|_, recent| {
+                recent
+                    .back()
+                    .map_or(false, |(_, sent_at)| now.duration_since(*sent_at) <= REPEAT_WINDOW)
+            });
+        }
+    }
+}
+
+/// Apply a violation through the IRC writer and tell the user why
+/// Messages without an id can't be deleted, the user is purged with a 1 second timeout instead.
+pub fn enforce(
+    irc_writer: &Addr<IrcClientWriter>,
+    channel: &str,
+    user: &str,
+    message_id: Option<&str>,
+    violation: &Violation,
+) {
+    let reason = violation.rule.reason();
+    let send = |message: String| {
+        irc_writer.do_send(SendChannelMessage {
+            channel: channel.to_owned(),
+            message,
+        })
+    };
+
+    match violation.action {
+        Action::Warn => send(format!("@{} {} (warning)", user, reason)),
+        Action::Delete => {
+            match message_id {
+                Some(id) => send(format!("/delete {}", id)),
+                None => send(format!("/timeout {} 1 {}", user, reason)),
+            }
+            send(format!("@{} {}", user, reason));
+        }
+        Action::Timeout => {
+            let seconds = violation.timeout.as_secs();
+            send(format!("/timeout {} {} {}", user, seconds, reason));
+            send(format!("@{} {} ({}s timeout)", user, reason, seconds));
+        }
+        Action::Ban => send(format!("/ban {} {}", user, reason)),
+    }
+}
+
+/// Host of every link in a message, lowercased
+fn links<'a>(message: &'a str) -> impl Iterator<Item = String> + 'a {
+    message.split_whitespace().filter_map(|word| {
+        let word = word.to_lowercase();
+        let word = word
+            .trim_left_matches("https://")
+            .trim_left_matches("http://")
+            .trim_left_matches(|c: char| !c.is_alphanumeric());
+        let host = word
+            .split(|c| c == '/' || c == '?' || c == '#' || c == ':')
+            .next()
+            .unwrap_or("")
+            .trim_right_matches(|c: char| !c.is_alphanumeric());
+
+        if is_host(host) {
+            Some(host.to_owned())
+        } else {
+            None
+        }
+    })
+}
+
+/// Whether a word looks like a domain, e.g. `example.com`, with a top level domain of letters
+fn is_host(host: &str) -> bool {
+    let labels: Vec<&str> = host.split('.').collect();
+    let tld = labels[labels.len() - 1];
+
+    labels.len() >= 2
+        && labels
+            .iter()
+            .all(|label| !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-'))
+        && tld.len() >= 2
+        && tld.chars().all(|c| c.is_alphabetic())
+}
+
+/// Whether a host is an allowed domain or one of its subdomains
+fn allowed_domain(host: &str, domains: &[String]) -> bool {
+    domains.iter().any(|domain| {
+        host == domain || (host.ends_with(domain.as_str()) && host[..host.len() - domain.len()].ends_with('.'))
+    })
+}
+
+/// Length of the longest run of the same character
+fn longest_run(message: &str) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous = None;
+
+    for c in message.chars() {
+        run = if previous == Some(c) { run + 1 } else { 1 };
+        previous = Some(c);
+        longest = longest.max(run);
+    }
+
+    longest
+}
+
+/// Domain as stored in the allowed list, without scheme, `www.` or path
+pub fn normalize_domain(domain: &str) -> String {
+    let domain = domain.to_lowercase();
+    let domain = domain
+        .trim_left_matches("https://")
+        .trim_left_matches("http://")
+        .trim_left_matches("www.");
+    domain.split('/').next().unwrap_or("").to_owned()
+}
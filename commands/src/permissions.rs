@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// Who is allowed to use a command, each level includes the ones below it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PermissionLevel {
+    Everyone = 0,
+    Subscriber = 1,
+    Vip = 2,
+    Moderator = 3,
+    Broadcaster = 4,
+    /// Configured bot owners, in every channel
+    Owner = 5,
+}
+
+impl PermissionLevel {
+    /// Level stored in the database, unknown values are treated as owner only
+    pub fn from_i32(level: i32) -> Self {
+        match level {
+            level if level <= 0 => PermissionLevel::Everyone,
+            1 => PermissionLevel::Subscriber,
+            2 => PermissionLevel::Vip,
+            3 => PermissionLevel::Moderator,
+            4 => PermissionLevel::Broadcaster,
+            _ => PermissionLevel::Owner,
+        }
+    }
+
+    /// Parse a level written in chat
+    pub fn parse(level: &str) -> Option<Self> {
+        match level.to_lowercase().as_str() {
+            "everyone" | "all" => Some(PermissionLevel::Everyone),
+            "subscriber" | "sub" => Some(PermissionLevel::Subscriber),
+            "vip" => Some(PermissionLevel::Vip),
+            "moderator" | "mod" => Some(PermissionLevel::Moderator),
+            "broadcaster" | "streamer" => Some(PermissionLevel::Broadcaster),
+            "owner" => Some(PermissionLevel::Owner),
+            _ => None,
+        }
+    }
+
+    /// Level of a user in a channel, using the badges sent in the message tags
+    pub fn of_user(
+        user: &str,
+        channel: &str,
+        tags: &HashMap<String, String>,
+        owners: &[String],
+    ) -> Self {
+        if owners.iter().any(|owner| owner.eq_ignore_ascii_case(user)) {
+            return PermissionLevel::Owner;
+        }
+
+        // The broadcaster is known even if tags were not sent
+        if user.eq_ignore_ascii_case(channel) {
+            return PermissionLevel::Broadcaster;
+        }
+
+        tags.get("badges")
+            .map_or("", |badges| badges.as_str())
+            .split(',')
+            .filter_map(|badge| match badge.split('/').next() {
+                Some("broadcaster") => Some(PermissionLevel::Broadcaster),
+                Some("moderator") => Some(PermissionLevel::Moderator),
+                Some("vip") => Some(PermissionLevel::Vip),
+                Some("subscriber") | Some("founder") => Some(PermissionLevel::Subscriber),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(PermissionLevel::Everyone)
+    }
+
+    /// Whether the message tags show a subscriber badge, whatever the level of the user
+    pub fn is_subscriber(tags: &HashMap<String, String>) -> bool {
+        tags.get("badges")
+            .map_or("", |badges| badges.as_str())
+            .split(',')
+            .any(|badge| match badge.split('/').next() {
+                Some("subscriber") | Some("founder") => true,
+                _ => false,
+            })
+    }
+}
+
+impl fmt::Display for PermissionLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            PermissionLevel::Everyone => "everyone",
+            PermissionLevel::Subscriber => "subscriber",
+            PermissionLevel::Vip => "vip",
+            PermissionLevel::Moderator => "moderator",
+            PermissionLevel::Broadcaster => "broadcaster",
+            PermissionLevel::Owner => "owner",
+        };
+
+        write!(f, "{}", name)
+    }
+}
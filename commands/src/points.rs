@@ -0,0 +1,272 @@
+use actix::fut;
+use actix::prelude::*;
+use actix_web::HttpMessage;
+use cold_data::cache::CommandCache;
+use cold_data::models::EarnPoints;
+use cold_data::DbConnectionPool;
+use failure::Error;
+use futures::{future, Future};
+use helix::HelixCredentials;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// Time between two payouts, the points earned in between are saved together
+const PAYOUT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// How often twitch is asked which channels are live
+const LIVE_CHECK_INTERVAL: Duration = Duration::from_secs(2 * 60);
+/// Streams of the channels given as `user_login`
+const HELIX_STREAMS_PATH: &str = "/streams";
+
+/// Live streams returned by twitch
+#[derive(Deserialize)]
+struct Streams {
+    data: Vec<Stream>,
+}
+
+#[derive(Deserialize)]
+struct Stream {
+    /// Login of the channel, `user_name` is its display name
+    user_login: String,
+}
+
+/// Actor paying points to the viewers of live channels
+/// Viewers are known from twitch membership messages and from chat, since large channels get no membership.
+pub struct PointsManager {
+    db: Addr<DbConnectionPool>,
+    commands: CommandCache,
+    /// Channels the bot joined, checked for being live
+    channels: Vec<String>,
+    /// Without credentials no channel is known to be live, so no points are paid
+    helix: Option<HelixCredentials>,
+    /// Channels live at the last check
+    live: HashSet<String>,
+    /// Whether the last live check failed, so failures are only logged once
+    check_failed: bool,
+    /// Users in each channel and whether they are known to be subscribers
+    viewers: HashMap<String, HashMap<String, bool>>,
+    /// Users who chatted in each channel since the last payout
+    chatters: HashMap<String, HashSet<String>>,
+}
+
+impl PointsManager {
+    pub fn create(
+        db: Addr<DbConnectionPool>,
+        commands: CommandCache,
+        channels: Vec<String>,
+        helix: Option<HelixCredentials>,
+    ) -> Addr<Self> {
+        if helix.is_none() {
+            println!("No client id and app token configured, points are not paid");
+        }
+
+        Arbiter::start(move |_ctx| Self {
+            db,
+            commands,
+            channels,
+            helix,
+            live: HashSet::new(),
+            check_failed: false,
+            viewers: HashMap::new(),
+            chatters: HashMap::new(),
+        })
+    }
+
+    fn is_live(&self, channel: &str) -> bool {
+        self.live.contains(channel)
+    }
+
+    /// Log a failed live check unless the previous one failed too
+    fn check_failure<E: ::std::fmt::Debug>(&mut self, err: E) {
+        if !self.check_failed {
+            println!("Could not check live channels {:?}", err);
+            self.check_failed = true;
+        }
+    }
+
+    /// Save the points earned by the viewers of live channels since the last payout
+    fn payout(&mut self) -> Box<dyn Future<Item = (), Error = ()>> {
+        let chatters = ::std::mem::replace(&mut self.chatters, HashMap::new());
+        let mut earnings = Vec::new();
+
+        {
+            let commands = self.commands.read().expect("READ ERROR");
+            for (channel, viewers) in &self.viewers {
+                if !self.is_live(channel) {
+                    continue;
+                }
+
+                let (presence, chat, sub_multiplier) = commands.point_rates(channel);
+                let chatted = chatters.get(channel);
+
+                for (user, subscriber) in viewers {
+                    let mut amount = presence;
+                    if chatted.map_or(false, |chatted| chatted.contains(user)) {
+                        amount += chat;
+                    }
+                    if *subscriber {
+                        amount *= sub_multiplier;
+                    }
+
+                    if amount > 0 {
+                        earnings.push((channel.clone(), user.clone(), amount));
+                    }
+                }
+            }
+        }
+
+        if earnings.is_empty() {
+            return Box::new(future::ok(()));
+        }
+
+        Box::new(self.db.send(EarnPoints { earnings }).then(|result| {
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => println!("Could not save earned points {:?}", err),
+                Err(err) => println!("Could not save earned points {:?}", err),
+            }
+            Ok(())
+        }))
+    }
+
+    /// Ask twitch which of the channels are live
+    fn check_live(&mut self, ctx: &mut Context<Self>) {
+        if self.channels.is_empty() {
+            return;
+        }
+
+        let query: Vec<String> = self
+            .channels
+            .iter()
+            .map(|channel| format!("user_login={}", channel))
+            .collect();
+        let request = match self.helix {
+            Some(ref helix) => helix.get(&format!("{}?{}", HELIX_STREAMS_PATH, query.join("&"))),
+            None => return,
+        };
+
+        let request = match request {
+            Ok(request) => request,
+            Err(err) => {
+                self.check_failure(err);
+                return;
+            }
+        };
+
+        let streams = request
+            .send()
+            .from_err::<Error>()
+            .and_then(|response| response.json::<Streams>().from_err());
+
+        ctx.spawn(streams.into_actor(self).then(|result, act, _ctx| {
+            match result {
+                Ok(streams) => {
+                    act.check_failed = false;
+                    act.live = streams
+                        .data
+                        .into_iter()
+                        .map(|stream| stream.user_login.to_lowercase())
+                        .collect()
+                }
+                Err(err) => act.check_failure(err),
+            }
+            fut::ok(())
+        }));
+    }
+}
+
+impl Actor for PointsManager {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.check_live(ctx);
+        ctx.run_interval(LIVE_CHECK_INTERVAL, |act, ctx| act.check_live(ctx));
+        ctx.run_interval(PAYOUT_INTERVAL, |act, ctx| {
+            ctx.spawn(act.payout().into_actor(act));
+        });
+    }
+}
+
+/// Save the points earned since the last payout now, so they are not lost when the bot stops
+pub struct FlushPoints;
+
+impl Message for FlushPoints {
+    type Result = Result<(), ()>;
+}
+
+impl Handler<FlushPoints> for PointsManager {
+    type Result = ResponseFuture<(), ()>;
+
+    fn handle(&mut self, _msg: FlushPoints, _ctx: &mut Self::Context) -> Self::Result {
+        self.payout()
+    }
+}
+
+/// A user joined a channel
+pub struct ViewerJoined {
+    pub channel: String,
+    pub user: String,
+}
+
+impl Message for ViewerJoined {
+    type Result = ();
+}
+
+impl Handler<ViewerJoined> for PointsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: ViewerJoined, _ctx: &mut Self::Context) {
+        self.viewers
+            .entry(msg.channel)
+            .or_insert_with(HashMap::new)
+            .entry(msg.user.to_lowercase())
+            .or_insert(false);
+    }
+}
+
+/// A user left a channel
+pub struct ViewerLeft {
+    pub channel: String,
+    pub user: String,
+}
+
+impl Message for ViewerLeft {
+    type Result = ();
+}
+
+impl Handler<ViewerLeft> for PointsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: ViewerLeft, _ctx: &mut Self::Context) {
+        if let Some(viewers) = self.viewers.get_mut(&msg.channel) {
+            viewers.remove(&msg.user.to_lowercase());
+        }
+    }
+}
+
+/// A user chatted in a channel, which also tells whether they are a subscriber
+pub struct ViewerChatted {
+    pub channel: String,
+    pub user: String,
+    pub subscriber: bool,
+}
+
+impl Message for ViewerChatted {
+    type Result = ();
+}
+
+impl Handler<ViewerChatted> for PointsManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: ViewerChatted, _ctx: &mut Self::Context) {
+        let user = msg.user.to_lowercase();
+
+        self.viewers
+            .entry(msg.channel.clone())
+            .or_insert_with(HashMap::new)
+            .insert(user.clone(), msg.subscriber);
+        self.chatters
+            .entry(msg.channel)
+            .or_insert_with(HashSet::new)
+            .insert(user);
+    }
+}
@@ -0,0 +1,304 @@
+use actix::fut;
+use actix::prelude::*;
+use cold_data::models::SavePoll;
+use cold_data::DbConnectionPool;
+use irc::client::{IrcClientWriter, SendChannelMessage};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use web_frontend::ws_update::{Update, UpdateServer};
+
+pub const MIN_POLL_OPTIONS: usize = 2;
+pub const MAX_POLL_OPTIONS: usize = 10;
+/// Used when the command does not end with a duration
+pub const DEFAULT_POLL_DURATION: Duration = Duration::from_secs(60);
+pub const MIN_POLL_DURATION: Duration = Duration::from_secs(10);
+pub const MAX_POLL_DURATION: Duration = Duration::from_secs(60 * 60);
+
+/// Why a poll could not be started or ended
+#[derive(Debug, Fail)]
+pub enum PollError {
+    #[fail(display = "a poll is already running")]
+    AlreadyRunning,
+    #[fail(display = "no poll is running")]
+    NotRunning,
+}
+
+/// A poll running in a channel
+struct Poll {
+    question: String,
+    options: Vec<String>,
+    /// Votes of each option, in the order of `options`
+    votes: Vec<i32>,
+    /// Users who already voted
+    voters: HashSet<String>,
+    started_by: String,
+    duration: Duration,
+    ends_at: Instant,
+    /// Ends the poll when its time runs out
+    timer: SpawnHandle,
+}
+
+impl Poll {
+    /// Option a chat message votes for, by number or text
+    fn option(&self, message: &str) -> Option<usize> {
+        let message = message.trim();
+
+        match message.parse::<usize>() {
+            Ok(number) if number >= 1 && number <= self.options.len() => Some(number - 1),
+            _ => self
+                .options
+                .iter()
+                .position(|option| option.eq_ignore_ascii_case(message)),
+        }
+    }
+}
+
+/// Actor running the polls of every channel, one at a time per channel
+/// Votes are counted from every chat message, tallies are streamed to the frontend.
+pub struct PollManager {
+    db: Addr<DbConnectionPool>,
+    irc_writer: Addr<IrcClientWriter>,
+    update_server: Addr<UpdateServer>,
+    polls: HashMap<String, Poll>,
+}
+
+impl PollManager {
+    pub fn create(
+        db: Addr<DbConnectionPool>,
+        irc_writer: Addr<IrcClientWriter>,
+        update_server: Addr<UpdateServer>,
+    ) -> Addr<Self> {
+        Arbiter::start(move |_ctx| Self {
+            db,
+            irc_writer,
+            update_server,
+            polls: HashMap::new(),
+        })
+    }
+
+    fn say(&self, channel: &str, message: String) {
+        self.irc_writer.do_send(SendChannelMessage {
+            channel: channel.to_owned(),
+            message,
+        });
+    }
+
+    /// Send the tally of a poll to the frontend
+    fn send_update(&self, channel: &str, poll: &Poll, ended: bool) {
+        let now = Instant::now();
+        let remaining = if ended || poll.ends_at <= now {
+            0
+        } else {
+            (poll.ends_at - now).as_secs()
+        };
+
+        let update = Update::Poll {
+            channel,
+            question: &poll.question,
+            options: &poll.options,
+            votes: &poll.votes,
+            remaining,
+            ended,
+        };
+
+        match update.mass_send() {
+            Ok(update) => self.update_server.do_send(update),
+            Err(err) => println!("Could not send poll update {:?}", err),
+        }
+    }
+
+    /// End the poll of a channel, announce its result and save it
+    fn finish(&mut self, channel: &str, ctx: &mut Context<Self>) {
+        let poll = match self.polls.remove(channel) {
+            Some(poll) => poll,
+            None => return,
+        };
+
+        self.send_update(channel, &poll, true);
+        self.say(channel, result(&poll));
+
+        let save = SavePoll {
+            channel: channel.to_owned(),
+            question: poll.question,
+            started_by: poll.started_by,
+            duration_seconds: poll.duration.as_secs() as i32,
+            options: poll.options.into_iter().zip(poll.votes).collect(),
+        };
+
+        ctx.spawn(self.db.send(save).into_actor(self).then(|result, _act, _ctx| {
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => println!("Could not save poll {:?}", err),
+                Err(err) => println!("Could not save poll {:?}", err),
+            }
+            fut::ok(())
+        }));
+    }
+}
+
+/// Result of a poll as announced in chat
+fn result(poll: &Poll) -> String {
+    let total: i32 = poll.votes.iter().sum();
+    if total == 0 {
+        return format!("Poll ended with no votes: {}", poll.question);
+    }
+
+    let tally: Vec<String> = poll
+        .options
+        .iter()
+        .zip(&poll.votes)
+        .map(|(option, votes)| format!("{} {} ({}%)", option, votes, votes * 100 / total))
+        .collect();
+
+    let most = poll.votes.iter().cloned().max().unwrap_or(0);
+    let winners: Vec<&str> = poll
+        .options
+        .iter()
+        .zip(&poll.votes)
+        .filter(|(_, votes)| **votes == most)
+        .map(|(option, _)| option.as_str())
+        .collect();
+
+    let outcome = if winners.len() == 1 {
+        format!("{} wins", winners[0])
+    } else {
+        format!("tie between {}", winners.join(" and "))
+    };
+
+    format!(
+        "Poll ended: {} {} ({})",
+        poll.question,
+        tally.join(", "),
+        outcome
+    )
+}
+
+impl Actor for PollManager {
+    type Context = Context<Self>;
+}
+
+/// Start a poll in a channel, fails if one is already running
+pub struct StartPoll {
+    pub channel: String,
+    pub question: String,
+    pub options: Vec<String>,
+    pub duration: Duration,
+    pub started_by: String,
+}
+
+impl Message for StartPoll {
+    type Result = Result<(), PollError>;
+}
+
+impl Handler<StartPoll> for PollManager {
+    type Result = Result<(), PollError>;
+
+    fn handle(&mut self, msg: StartPoll, ctx: &mut Self::Context) -> Result<(), PollError> {
+        if self.polls.contains_key(&msg.channel) {
+            return Err(PollError::AlreadyRunning);
+        }
+
+        let channel = msg.channel.clone();
+        let timer = ctx.run_later(msg.duration, move |act, ctx| act.finish(&channel, ctx));
+
+        let poll = Poll {
+            votes: vec![0; msg.options.len()],
+            question: msg.question,
+            options: msg.options,
+            voters: HashSet::new(),
+            started_by: msg.started_by,
+            duration: msg.duration,
+            ends_at: Instant::now() + msg.duration,
+            timer,
+        };
+
+        let choices: Vec<String> = poll
+            .options
+            .iter()
+            .enumerate()
+            .map(|(index, option)| format!("{}) {}", index + 1, option))
+            .collect();
+        self.say(
+            &msg.channel,
+            format!(
+                "Poll: {} Vote with the number or text of an option: {} ({}s)",
+                poll.question,
+                choices.join(", "),
+                poll.duration.as_secs()
+            ),
+        );
+
+        self.send_update(&msg.channel, &poll, false);
+        self.polls.insert(msg.channel, poll);
+
+        Ok(())
+    }
+}
+
+/// End the poll of a channel early, a canceled poll has no result and is not saved
+pub struct EndPoll {
+    pub channel: String,
+    pub cancel: bool,
+}
+
+impl Message for EndPoll {
+    type Result = Result<(), PollError>;
+}
+
+impl Handler<EndPoll> for PollManager {
+    type Result = Result<(), PollError>;
+
+    fn handle(&mut self, msg: EndPoll, ctx: &mut Self::Context) -> Result<(), PollError> {
+        let timer = match self.polls.get(&msg.channel) {
+            Some(poll) => poll.timer,
+            None => return Err(PollError::NotRunning),
+        };
+        ctx.cancel_future(timer);
+
+        if !msg.cancel {
+            self.finish(&msg.channel, ctx);
+        } else if let Some(poll) = self.polls.remove(&msg.channel) {
+            self.send_update(&msg.channel, &poll, true);
+            self.say(&msg.channel, format!("Poll canceled: {}", poll.question));
+        }
+
+        Ok(())
+    }
+}
+
+/// A chat message, counted as a vote if it matches an option of the channel's poll
+pub struct Vote {
+    pub channel: String,
+    pub user: String,
+    pub message: String,
+}
+
+impl Message for Vote {
+    type Result = ();
+}
+
+impl Handler<Vote> for PollManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: Vote, _ctx: &mut Self::Context) {
+        let counted = match self.polls.get_mut(&msg.channel) {
+            Some(poll) => match poll.option(&msg.message) {
+                Some(option) => {
+                    let first_vote = poll.voters.insert(msg.user.to_lowercase());
+                    if first_vote {
+                        poll.votes[option] += 1;
+                    }
+                    first_vote
+                }
+                None => false,
+            },
+            None => false,
+        };
+
+        if counted {
+            if let Some(poll) = self.polls.get(&msg.channel) {
+                self.send_update(&msg.channel, poll, false);
+            }
+        }
+    }
+}
@@ -0,0 +1,635 @@
+use actix::dev::ToEnvelope;
+use actix::fut;
+use actix::prelude::*;
+use cold_data::models::{
+    AddPoints, AddRaffleEntry, AddRaffleWinner, ClaimRaffleWin, CloseRaffle, OpenRaffle, SpendPoints,
+};
+use cold_data::DbConnectionPool;
+use failure::Error;
+use irc::client::{IrcClientWriter, SendChannelMessage};
+use rand::prng::ChaChaRng;
+use rand::{self, Rng, SeedableRng};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use web_frontend::ws_update::{Update, UpdateServer};
+
+/// Most tickets a subscriber can get
+pub const MAX_SUB_LUCK: u32 = 10;
+/// Most winners drawn at once
+pub const MAX_RAFFLE_WINNERS: usize = 10;
+/// Time a winner has to respond when the command does not choose one
+pub const DEFAULT_CLAIM_TIMEOUT: Duration = Duration::from_secs(60);
+pub const MAX_CLAIM_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// Most points a raffle can cost to enter
+pub const MAX_RAFFLE_COST: u32 = 1_000_000;
+
+/// Why a raffle could not be opened, closed or drawn
+#[derive(Debug, Fail)]
+pub enum RaffleError {
+    #[fail(display = "a raffle is already running")]
+    AlreadyRunning,
+    #[fail(display = "no raffle is running")]
+    NotRunning,
+    #[fail(display = "entries are already closed")]
+    AlreadyClosed,
+    #[fail(display = "no one is left to draw")]
+    NoEntries,
+    #[fail(display = "it could not be saved")]
+    NotSaved,
+}
+
+/// A drawn winner who has to respond before their timer redraws them
+struct Pending {
+    draw: i32,
+    timer: SpawnHandle,
+}
+
+/// A raffle of a channel, from its opening until it is ended or replaced
+struct Raffle {
+    id: i32,
+    keyword: String,
+    sub_luck: u32,
+    /// Points paid to enter
+    cost: u32,
+    /// Zero when winners don't have to respond
+    claim_timeout: Duration,
+    /// Users can still enter
+    open: bool,
+    /// Users who were not drawn yet with their tickets, in the order they entered
+    entries: Vec<(String, u32)>,
+    /// Every user who entered, or is paying to enter
+    entered: HashSet<String>,
+    /// Seeded when the raffle is opened, so draws can be repeated from the saved seed and entries
+    rng: ChaChaRng,
+    /// Winners drawn so far, including redrawn ones
+    draws: i32,
+    /// Winners who did not respond yet, by user
+    pending: HashMap<String, Pending>,
+    /// Winners who responded or still can
+    winners: Vec<String>,
+}
+
+impl Raffle {
+    /// The raffle still needs a moderator, another one can't be opened yet
+    fn running(&self) -> bool {
+        self.open || !self.pending.is_empty() || (self.draws == 0 && !self.entries.is_empty())
+    }
+}
+
+/// Remove a random user from the entries, each ticket has the same chance
+fn pick<R: Rng>(entries: &mut Vec<(String, u32)>, rng: &mut R) -> Option<String> {
+    let total: u32 = entries.iter().map(|(_, tickets)| tickets).sum();
+    if total == 0 {
+        return None;
+    }
+
+    let mut ticket = rng.gen_range(0, total);
+    let index = entries.iter().position(|(_, tickets)| {
+        if ticket < *tickets {
+            true
+        } else {
+            ticket -= tickets;
+            false
+        }
+    })?;
+
+    Some(entries.remove(index).0)
+}
+
+/// Actor running the raffles of every channel, one at a time per channel
+/// Entries, draws and claims are saved as they happen so raffles can be audited.
+pub struct RaffleManager {
+    db: Addr<DbConnectionPool>,
+    irc_writer: Addr<IrcClientWriter>,
+    update_server: Addr<UpdateServer>,
+    raffles: HashMap<String, Raffle>,
+    /// Channels whose raffle is being saved before it opens
+    opening: HashSet<String>,
+}
+
+impl RaffleManager {
+    pub fn create(
+        db: Addr<DbConnectionPool>,
+        irc_writer: Addr<IrcClientWriter>,
+        update_server: Addr<UpdateServer>,
+    ) -> Addr<Self> {
+        Arbiter::start(move |_ctx| Self {
+            db,
+            irc_writer,
+            update_server,
+            raffles: HashMap::new(),
+            opening: HashSet::new(),
+        })
+    }
+
+    fn say(&self, channel: &str, message: String) {
+        self.irc_writer.do_send(SendChannelMessage {
+            channel: channel.to_owned(),
+            message,
+        });
+    }
+
+    /// Save a change to a raffle, failures are logged
+    fn save<M, T>(&self, msg: M, ctx: &mut Context<Self>)
+    where
+        M: Message<Result = Result<T, Error>> + Send + 'static,
+        T: Send + 'static,
+        DbConnectionPool: Handler<M>,
+        <DbConnectionPool as Actor>::Context: ToEnvelope<DbConnectionPool, M>,
+    {
+        ctx.spawn(self.db.send(msg).into_actor(self).then(|result, _act, _ctx| {
+            match result {
+                Ok(Ok(_)) => {}
+                Ok(Err(err)) => println!("Could not save raffle {:?}", err),
+                Err(err) => println!("Could not save raffle {:?}", err),
+            }
+            fut::ok(())
+        }));
+    }
+
+    /// Send the state of a raffle to the frontend
+    fn send_update(&self, channel: &str, raffle: &Raffle, ended: bool) {
+        let update = Update::Raffle {
+            channel,
+            keyword: &raffle.keyword,
+            open: raffle.open,
+            entries: raffle.entered.len(),
+            winners: &raffle.winners,
+            ended,
+        };
+
+        match update.mass_send() {
+            Ok(update) => self.update_server.do_send(update),
+            Err(err) => println!("Could not send raffle update {:?}", err),
+        }
+    }
+
+    /// Add a user to the entries, one who paid is refunded if the raffle closed in the meantime
+    fn enter(
+        &mut self,
+        channel: &str,
+        raffle_id: i32,
+        user: String,
+        tickets: u32,
+        paid: u32,
+        ctx: &mut Context<Self>,
+    ) {
+        let entry = match self.raffles.get_mut(channel) {
+            Some(raffle) => {
+                if raffle.id == raffle_id && raffle.open {
+                    let position = raffle.entries.len() as i32;
+                    raffle.entries.push((user.clone(), tickets));
+
+                    Some(AddRaffleEntry {
+                        raffle_id,
+                        user: user.clone(),
+                        position,
+                        tickets: tickets as i32,
+                    })
+                } else {
+                    None
+                }
+            }
+            None => None,
+        };
+
+        match entry {
+            Some(entry) => {
+                self.save(entry, ctx);
+                if let Some(raffle) = self.raffles.get(channel) {
+                    self.send_update(channel, raffle, false);
+                }
+            }
+            None => self.refund(channel, user, paid, ctx),
+        }
+    }
+
+    /// Give back the points a user paid to enter a raffle they could not enter
+    fn refund(&self, channel: &str, user: String, paid: u32, ctx: &mut Context<Self>) {
+        if paid == 0 {
+            return;
+        }
+
+        self.save(
+            AddPoints {
+                channel: channel.to_owned(),
+                user,
+                amount: paid as i32,
+            },
+            ctx,
+        );
+    }
+
+    /// Let a user who could not pay try to enter again
+    fn forget(&mut self, channel: &str, raffle_id: i32, user: &str) {
+        if let Some(raffle) = self.raffles.get_mut(channel) {
+            if raffle.id == raffle_id {
+                raffle.entered.remove(user);
+            }
+        }
+    }
+
+    /// Draw up to `count` winners and wait for them to respond
+    fn draw_winners(&mut self, channel: &str, count: usize, ctx: &mut Context<Self>) -> Vec<String> {
+        let (raffle_id, claim_timeout, drawn) = match self.raffles.get_mut(channel) {
+            Some(raffle) => {
+                let mut drawn = Vec::new();
+                while drawn.len() < count {
+                    match pick(&mut raffle.entries, &mut raffle.rng) {
+                        Some(user) => {
+                            raffle.draws += 1;
+                            raffle.winners.push(user.clone());
+                            drawn.push((raffle.draws, user));
+                        }
+                        None => break,
+                    }
+                }
+                (raffle.id, raffle.claim_timeout, drawn)
+            }
+            None => return Vec::new(),
+        };
+
+        let claimed = claim_timeout == Duration::from_secs(0);
+        let mut winners = Vec::new();
+        for (draw, user) in drawn {
+            self.save(
+                AddRaffleWinner {
+                    raffle_id,
+                    draw,
+                    user: user.clone(),
+                    claimed,
+                },
+                ctx,
+            );
+
+            if !claimed {
+                let (expired_channel, expired_user) = (channel.to_owned(), user.clone());
+                let timer = ctx.run_later(claim_timeout, move |act, ctx| {
+                    act.expire(&expired_channel, &expired_user, ctx)
+                });
+                if let Some(raffle) = self.raffles.get_mut(channel) {
+                    raffle.pending.insert(user.clone(), Pending { draw, timer });
+                }
+            }
+
+            winners.push(user);
+        }
+
+        winners
+    }
+
+    /// Redraw a winner who did not respond in time
+    fn expire(&mut self, channel: &str, user: &str, ctx: &mut Context<Self>) {
+        let hint = match self.raffles.get_mut(channel) {
+            Some(raffle) => {
+                if raffle.pending.remove(user).is_none() {
+                    return;
+                }
+                raffle.winners.retain(|winner| winner != user);
+                claim_hint(raffle)
+            }
+            None => return,
+        };
+
+        let message = match self.draw_winners(channel, 1, ctx).first() {
+            Some(winner) => format!(
+                "@{} did not respond in time, the new winner is @{}! {}",
+                user,
+                winner,
+                hint
+            ),
+            None => format!("@{} did not respond in time and no one is left to draw!", user),
+        };
+        self.say(channel, message);
+
+        if let Some(raffle) = self.raffles.get(channel) {
+            self.send_update(channel, raffle, false);
+        }
+    }
+}
+
+/// Tells winners how to claim their win, if they have to
+fn claim_hint(raffle: &Raffle) -> String {
+    if raffle.claim_timeout == Duration::from_secs(0) {
+        String::new()
+    } else {
+        format!(
+            "Type {} within {}s to claim it!",
+            raffle.keyword,
+            raffle.claim_timeout.as_secs()
+        )
+    }
+}
+
+/// Seed as saved with the raffle
+fn hex(seed: &[u8]) -> String {
+    seed.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+impl Actor for RaffleManager {
+    type Context = Context<Self>;
+}
+
+/// Open a raffle in a channel, replacing one that is done
+pub struct StartRaffle {
+    pub channel: String,
+    pub keyword: String,
+    /// Tickets of each subscriber
+    pub sub_luck: u32,
+    /// Points paid to enter
+    pub cost: u32,
+    pub claim_timeout: Duration,
+    pub opened_by: String,
+}
+
+impl Message for StartRaffle {
+    type Result = Result<(), RaffleError>;
+}
+
+impl Handler<StartRaffle> for RaffleManager {
+    type Result = ResponseActFuture<Self, (), RaffleError>;
+
+    fn handle(&mut self, msg: StartRaffle, _ctx: &mut Self::Context) -> Self::Result {
+        let running = self
+            .raffles
+            .get(&msg.channel)
+            .map_or(false, |raffle| raffle.running());
+        if running || self.opening.contains(&msg.channel) {
+            return Box::new(fut::err(RaffleError::AlreadyRunning));
+        }
+
+        let mut seed = [0u8; 32];
+        rand::thread_rng().fill(&mut seed);
+
+        let open_raffle = OpenRaffle {
+            channel: msg.channel.clone(),
+            keyword: msg.keyword.clone(),
+            sub_luck: msg.sub_luck as i32,
+            seed: hex(&seed),
+            opened_by: msg.opened_by.clone(),
+            cost: msg.cost as i32,
+        };
+
+        self.opening.insert(msg.channel.clone());
+        Box::new(self.db.send(open_raffle).into_actor(self).then(move |result, act, ctx| {
+            act.opening.remove(&msg.channel);
+            let id = match result {
+                Ok(Ok(id)) => id,
+                Ok(Err(err)) => {
+                    println!("Could not save raffle {:?}", err);
+                    return fut::err(RaffleError::NotSaved);
+                }
+                Err(err) => {
+                    println!("Could not save raffle {:?}", err);
+                    return fut::err(RaffleError::NotSaved);
+                }
+            };
+
+            if let Some(done) = act.raffles.remove(&msg.channel) {
+                act.save(CloseRaffle { raffle_id: done.id }, ctx);
+            }
+
+            let raffle = Raffle {
+                id,
+                keyword: msg.keyword,
+                sub_luck: msg.sub_luck,
+                cost: msg.cost,
+                claim_timeout: msg.claim_timeout,
+                open: true,
+                entries: Vec::new(),
+                entered: HashSet::new(),
+                rng: ChaChaRng::from_seed(seed),
+                draws: 0,
+                pending: HashMap::new(),
+                winners: Vec::new(),
+            };
+
+            let cost = if raffle.cost > 0 {
+                format!(" for {} points", raffle.cost)
+            } else {
+                String::new()
+            };
+            let luck = if raffle.sub_luck > 1 {
+                format!(", subscribers get {} tickets", raffle.sub_luck)
+            } else {
+                String::new()
+            };
+            act.say(
+                &msg.channel,
+                format!("Raffle is open! Type {} to enter{}{}.", raffle.keyword, cost, luck),
+            );
+
+            act.send_update(&msg.channel, &raffle, false);
+            act.raffles.insert(msg.channel, raffle);
+            fut::ok(())
+        }))
+    }
+}
+
+/// Stop taking entries in the raffle of a channel, results in the number of users who entered
+pub struct CloseEntries {
+    pub channel: String,
+}
+
+impl Message for CloseEntries {
+    type Result = Result<usize, RaffleError>;
+}
+
+impl Handler<CloseEntries> for RaffleManager {
+    type Result = Result<usize, RaffleError>;
+
+    fn handle(&mut self, msg: CloseEntries, ctx: &mut Self::Context) -> Result<usize, RaffleError> {
+        let (raffle_id, entered) = match self.raffles.get_mut(&msg.channel) {
+            Some(raffle) => {
+                if !raffle.open {
+                    return Err(RaffleError::AlreadyClosed);
+                }
+                raffle.open = false;
+                (raffle.id, raffle.entered.len())
+            }
+            None => return Err(RaffleError::NotRunning),
+        };
+
+        self.save(CloseRaffle { raffle_id }, ctx);
+        if let Some(raffle) = self.raffles.get(&msg.channel) {
+            self.send_update(&msg.channel, raffle, false);
+        }
+
+        Ok(entered)
+    }
+}
+
+/// Draw winners of the raffle of a channel, entries are closed first
+pub struct DrawWinners {
+    pub channel: String,
+    pub count: usize,
+}
+
+impl Message for DrawWinners {
+    type Result = Result<(), RaffleError>;
+}
+
+impl Handler<DrawWinners> for RaffleManager {
+    type Result = Result<(), RaffleError>;
+
+    fn handle(&mut self, msg: DrawWinners, ctx: &mut Self::Context) -> Result<(), RaffleError> {
+        let (raffle_id, was_open) = match self.raffles.get_mut(&msg.channel) {
+            Some(raffle) => {
+                let was_open = raffle.open;
+                raffle.open = false;
+                (raffle.id, was_open)
+            }
+            None => return Err(RaffleError::NotRunning),
+        };
+
+        if was_open {
+            self.save(CloseRaffle { raffle_id }, ctx);
+        }
+
+        let winners = self.draw_winners(&msg.channel, msg.count, ctx);
+        let hint = match self.raffles.get(&msg.channel) {
+            Some(raffle) => {
+                self.send_update(&msg.channel, raffle, false);
+                claim_hint(raffle)
+            }
+            None => String::new(),
+        };
+
+        if winners.is_empty() {
+            return Err(RaffleError::NoEntries);
+        }
+
+        let mentions: Vec<String> = winners.iter().map(|winner| format!("@{}", winner)).collect();
+        let title = if winners.len() == 1 { "winner is" } else { "winners are" };
+        self.say(
+            &msg.channel,
+            format!("The raffle {} {}! {}", title, mentions.join(", "), hint),
+        );
+
+        Ok(())
+    }
+}
+
+/// End the raffle of a channel, winners who did not respond are no longer waited for
+pub struct EndRaffle {
+    pub channel: String,
+}
+
+impl Message for EndRaffle {
+    type Result = Result<(), RaffleError>;
+}
+
+impl Handler<EndRaffle> for RaffleManager {
+    type Result = Result<(), RaffleError>;
+
+    fn handle(&mut self, msg: EndRaffle, ctx: &mut Self::Context) -> Result<(), RaffleError> {
+        let mut raffle = match self.raffles.remove(&msg.channel) {
+            Some(raffle) => raffle,
+            None => return Err(RaffleError::NotRunning),
+        };
+
+        for (_, pending) in raffle.pending.drain() {
+            ctx.cancel_future(pending.timer);
+        }
+        raffle.open = false;
+
+        self.save(CloseRaffle { raffle_id: raffle.id }, ctx);
+        self.send_update(&msg.channel, &raffle, true);
+        self.say(&msg.channel, format!("Raffle for {} has ended.", raffle.keyword));
+
+        Ok(())
+    }
+}
+
+/// A chat message, entering the raffle of the channel or claiming a win with its keyword
+pub struct RaffleMessage {
+    pub channel: String,
+    pub user: String,
+    /// Subscribers get the luck of the raffle as tickets
+    pub subscriber: bool,
+    pub message: String,
+}
+
+impl Message for RaffleMessage {
+    type Result = ();
+}
+
+impl Handler<RaffleMessage> for RaffleManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: RaffleMessage, ctx: &mut Self::Context) {
+        let user = msg.user.to_lowercase();
+
+        // Drawn winners claim with the keyword, so a message sent before the draw can't claim
+        let claim = self.raffles.get_mut(&msg.channel).and_then(|raffle| {
+            if !msg.message.trim().eq_ignore_ascii_case(&raffle.keyword) {
+                return None;
+            }
+            let raffle_id = raffle.id;
+            raffle.pending.remove(&user).map(|pending| (raffle_id, pending))
+        });
+        if let Some((raffle_id, pending)) = claim {
+            ctx.cancel_future(pending.timer);
+            self.save(
+                ClaimRaffleWin {
+                    raffle_id,
+                    draw: pending.draw,
+                },
+                ctx,
+            );
+            self.say(&msg.channel, format!("@{} claimed the win!", user));
+            return;
+        }
+
+        let (raffle_id, tickets, cost) = match self.raffles.get_mut(&msg.channel) {
+            Some(raffle) => {
+                let enters = raffle.open
+                    && msg.message.trim().eq_ignore_ascii_case(&raffle.keyword)
+                    && !raffle.entered.contains(&user);
+                if !enters {
+                    return;
+                }
+
+                // Marked right away so sending the keyword twice is not paid twice
+                raffle.entered.insert(user.clone());
+                let tickets = if msg.subscriber { raffle.sub_luck } else { 1 };
+                (raffle.id, tickets, raffle.cost)
+            }
+            None => return,
+        };
+
+        if cost == 0 {
+            self.enter(&msg.channel, raffle_id, user, tickets, 0, ctx);
+            return;
+        }
+
+        let spend_points = SpendPoints {
+            channel: msg.channel.clone(),
+            user: user.clone(),
+            amount: cost as i32,
+        };
+        let channel = msg.channel;
+
+        ctx.spawn(self.db.send(spend_points).into_actor(self).then(move |result, act, ctx| {
+            match result {
+                Ok(Ok(true)) => act.enter(&channel, raffle_id, user, tickets, cost, ctx),
+                Ok(Ok(false)) => {
+                    act.forget(&channel, raffle_id, &user);
+                    act.say(
+                        &channel,
+                        format!("@{} you need {} points to enter the raffle!", user, cost),
+                    );
+                }
+                Ok(Err(err)) => {
+                    println!("Could not spend raffle points {:?}", err);
+                    act.forget(&channel, raffle_id, &user);
+                }
+                Err(err) => {
+                    println!("Could not spend raffle points {:?}", err);
+                    act.forget(&channel, raffle_id, &user);
+                }
+            }
+            fut::ok(())
+        }));
+    }
+}
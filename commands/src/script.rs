@@ -0,0 +1,409 @@
+use actix::{Actor, Addr, Handler, Message, SyncArbiter, SyncContext};
+use cold_data::models::{CheckScript, MAX_SCRIPT_LENGTH};
+use command::MAX_CHAT_MESSAGE_LENGTH;
+use failure::Error;
+use rlua::{self, HookTriggers, Lua, StdLib, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Memory a single script can allocate
+const MEMORY_LIMIT: usize = 4 * 1024 * 1024;
+/// Time a single script can run for
+const TIME_LIMIT: Duration = Duration::from_millis(250);
+/// Instructions run between two time checks
+const HOOK_INSTRUCTIONS: u32 = 1000;
+/// Messages a single script can send
+const MAX_MESSAGES: usize = 3;
+/// Keys a channel can store, including keys removed by the running script
+const MAX_STORE_KEYS: usize = 100;
+const MAX_STORE_KEY_LENGTH: usize = 100;
+const MAX_STORE_VALUE_LENGTH: usize = 500;
+/// Longest text the pattern functions accept
+/// A pattern is matched inside a single call the hook can't stop, its time grows with the text.
+const MAX_PATTERN_TEXT_LENGTH: usize = 500;
+/// Most repetitions (`*`, `+`, `-` and `?`) in a pattern, each one multiplies the backtracking
+const MAX_PATTERN_REPETITIONS: usize = 2;
+
+/// Wraps the pattern functions of the string library with a check of their arguments
+const WRAP_PATTERN_FUNCTIONS: &str = r#"
+local check = ...
+for _, name in ipairs({"find", "match", "gmatch", "gsub"}) do
+    local original = string[name]
+    string[name] = function(text, pattern, ...)
+        check(text, pattern)
+        return original(text, pattern, ...)
+    end
+end
+"#;
+
+/// Globals of the base library that could reach outside the sandbox,
+/// or catch the time and memory limit errors and keep running
+const REMOVED_GLOBALS: &[&str] = &[
+    "dofile",
+    "loadfile",
+    "load",
+    "loadstring",
+    "require",
+    "collectgarbage",
+    "print",
+    "pcall",
+    "xpcall",
+];
+
+/// Error while checking or running a script
+#[derive(Debug, Fail)]
+pub enum ScriptError {
+    #[fail(display = "script is longer than {} characters", _0)]
+    TooLong(usize),
+    #[fail(display = "{}", _0)]
+    Lua(String),
+}
+
+impl From<rlua::Error> for ScriptError {
+    fn from(err: rlua::Error) -> Self {
+        let message = match err {
+            rlua::Error::SyntaxError { message, .. } => message,
+            rlua::Error::MemoryError(_) => "script used too much memory".to_owned(),
+            rlua::Error::CallbackError { cause, .. } => return ScriptError::from((*cause).clone()),
+            err => err.to_string(),
+        };
+        ScriptError::Lua(message)
+    }
+}
+
+/// Actor running command scripts in a sandbox, with limited memory and time
+pub struct ScriptRunner;
+
+impl ScriptRunner {
+    pub fn create() -> Addr<Self> {
+        SyncArbiter::start(2, || ScriptRunner)
+    }
+}
+
+impl Actor for ScriptRunner {
+    type Context = SyncContext<Self>;
+}
+
+impl Handler<CheckScript> for ScriptRunner {
+    type Result = Result<(), Error>;
+
+    fn handle(&mut self, msg: CheckScript, _ctx: &mut Self::Context) -> Self::Result {
+        Ok(check_script(&msg.script)?)
+    }
+}
+
+/// Compile a script without running it
+fn check_script(script: &str) -> Result<(), ScriptError> {
+    if script.len() > MAX_SCRIPT_LENGTH {
+        return Err(ScriptError::TooLong(MAX_SCRIPT_LENGTH));
+    }
+
+    sandbox().context(|lua| -> Result<(), ScriptError> {
+        lua.load(script).into_function()?;
+        Ok(())
+    })
+}
+
+/// Run the script of a stored command
+pub struct RunScript {
+    pub script: String,
+    pub channel: String,
+    pub user: String,
+    /// Everything after the trigger
+    pub args: String,
+    /// Values stored by scripts of the channel
+    pub store: HashMap<String, String>,
+}
+
+/// Messages a script sent and the values it changed
+pub struct ScriptOutput {
+    pub messages: Vec<String>,
+    /// Changed keys, `None` when a key was removed
+    pub changes: HashMap<String, Option<String>>,
+}
+
+impl Message for RunScript {
+    type Result = Result<ScriptOutput, ScriptError>;
+}
+
+impl Handler<RunScript> for ScriptRunner {
+    type Result = Result<ScriptOutput, ScriptError>;
+
+    fn handle(&mut self, msg: RunScript, _ctx: &mut Self::Context) -> Self::Result {
+        run_script(msg)
+    }
+}
+
+/// Run a script in a new sandbox, failing once it ran for longer than `TIME_LIMIT`
+fn run_script(msg: RunScript) -> Result<ScriptOutput, ScriptError> {
+    let RunScript {
+        script,
+        channel,
+        user,
+        args,
+        store,
+    } = msg;
+
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    let store = Arc::new(Mutex::new(Store {
+        values: store,
+        changes: HashMap::new(),
+    }));
+
+    let lua = sandbox();
+    let started = Instant::now();
+    lua.set_hook(
+        HookTriggers {
+            every_nth_instruction: Some(HOOK_INSTRUCTIONS),
+            ..Default::default()
+        },
+        move |_lua, _debug| {
+            if started.elapsed() > TIME_LIMIT {
+                Err(rlua::Error::RuntimeError("script took too long".to_owned()))
+            } else {
+                Ok(())
+            }
+        },
+    );
+
+    lua.context(|lua| -> Result<(), ScriptError> {
+        let globals = lua.globals();
+        globals.set("user", user.as_str())?;
+        globals.set("channel", channel.as_str())?;
+        globals.set("text", args.as_str())?;
+        globals.set(
+            "args",
+            lua.create_sequence_from(args.split_whitespace().map(str::to_owned))?,
+        )?;
+
+        let say = {
+            let messages = messages.clone();
+            lua.create_function(move |_, message: String| send(&messages, message))?
+        };
+        let reply = {
+            let messages = messages.clone();
+            let user = user.clone();
+            lua.create_function(move |_, message: String| {
+                send(&messages, format!("@{} {}", user, message))
+            })?
+        };
+        globals.set("say", say)?;
+        globals.set("reply", reply)?;
+
+        let get = {
+            let store = store.clone();
+            lua.create_function(move |_, key: String| {
+                Ok(store.lock().expect("LOCK ERROR").get(&key))
+            })?
+        };
+        let set = {
+            let store = store.clone();
+            lua.create_function(move |_, (key, value): (String, Option<String>)| {
+                store.lock().expect("LOCK ERROR").set(key, value)
+            })?
+        };
+        let store_table = lua.create_table()?;
+        store_table.set("get", get)?;
+        store_table.set("set", set)?;
+        globals.set("store", store_table)?;
+
+        lua.load(&script).exec()?;
+        Ok(())
+    })?;
+
+    let messages = messages.lock().expect("LOCK ERROR").split_off(0);
+    let changes = store.lock().expect("LOCK ERROR").changes.drain().collect();
+
+    Ok(ScriptOutput { messages, changes })
+}
+
+/// Lua state with only the safe parts of the standard library
+fn sandbox() -> Lua {
+    let lua = Lua::new_with(StdLib::BASE | StdLib::TABLE | StdLib::STRING | StdLib::MATH);
+    lua.set_memory_limit(Some(MEMORY_LIMIT));
+
+    lua.context(|lua| {
+        let check = lua
+            .create_function(|_, (text, pattern): (rlua::String, rlua::String)| {
+                check_pattern(text.as_bytes(), pattern.as_bytes())
+            })
+            .expect("Could not create the pattern check");
+        lua.load(WRAP_PATTERN_FUNCTIONS)
+            .call::<_, ()>(check)
+            .expect("Could not wrap the pattern functions");
+
+        let globals = lua.globals();
+        for name in REMOVED_GLOBALS {
+            globals
+                .set(*name, Value::Nil)
+                .expect("Could not remove unsafe global");
+        }
+    });
+
+    lua
+}
+
+/// Refuse texts and patterns that could keep a pattern function running past the time limit
+fn check_pattern(text: &[u8], pattern: &[u8]) -> rlua::Result<()> {
+    if text.len() > MAX_PATTERN_TEXT_LENGTH {
+        return Err(rlua::Error::RuntimeError(format!(
+            "patterns can only be used on {} characters",
+            MAX_PATTERN_TEXT_LENGTH
+        )));
+    }
+    if pattern_repetitions(pattern) > MAX_PATTERN_REPETITIONS {
+        return Err(rlua::Error::RuntimeError(format!(
+            "patterns can have at most {} repetitions",
+            MAX_PATTERN_REPETITIONS
+        )));
+    }
+    Ok(())
+}
+
+/// Number of `*`, `+`, `-` and `?` of a Lua pattern, outside of sets and escapes
+fn pattern_repetitions(pattern: &[u8]) -> usize {
+    let mut count = 0;
+    let mut bytes = pattern.iter();
+
+    while let Some(byte) = bytes.next() {
+        match byte {
+            // %b takes the two characters after it
+            b'%' => {
+                if bytes.next() == Some(&b'b') {
+                    bytes.nth(1);
+                }
+            }
+            // A ] right after the opening [ or [^ is part of the set
+            b'[' => {
+                let mut first = true;
+                while let Some(byte) = bytes.next() {
+                    match byte {
+                        b'^' if first => continue,
+                        b'%' => {
+                            bytes.next();
+                        }
+                        b']' if !first => break,
+                        _ => {}
+                    }
+                    first = false;
+                }
+            }
+            b'*' | b'+' | b'-' | b'?' => count += 1,
+            _ => {}
+        }
+    }
+
+    count
+}
+
+/// Queue a message sent by a script, cut to fit in a chat message
+fn send(messages: &Mutex<Vec<String>>, mut message: String) -> rlua::Result<()> {
+    let mut messages = messages.lock().expect("LOCK ERROR");
+    if messages.len() >= MAX_MESSAGES {
+        return Err(rlua::Error::RuntimeError(format!(
+            "scripts can send at most {} messages",
+            MAX_MESSAGES
+        )));
+    }
+
+    if message.len() > MAX_CHAT_MESSAGE_LENGTH {
+        let mut end = MAX_CHAT_MESSAGE_LENGTH;
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        message.truncate(end);
+    }
+
+    messages.push(message);
+    Ok(())
+}
+
+/// Values of a channel as a script sees them
+struct Store {
+    values: HashMap<String, String>,
+    changes: HashMap<String, Option<String>>,
+}
+
+impl Store {
+    fn get(&self, key: &str) -> Option<String> {
+        match self.changes.get(key) {
+            Some(changed) => changed.clone(),
+            None => self.values.get(key).cloned(),
+        }
+    }
+
+    fn set(&mut self, key: String, value: Option<String>) -> rlua::Result<()> {
+        if key.is_empty() || key.len() > MAX_STORE_KEY_LENGTH {
+            return Err(rlua::Error::RuntimeError(format!(
+                "store keys must have 1 to {} characters",
+                MAX_STORE_KEY_LENGTH
+            )));
+        }
+        if value.as_ref().map_or(false, |value| value.len() > MAX_STORE_VALUE_LENGTH) {
+            return Err(rlua::Error::RuntimeError(format!(
+                "store values can have at most {} characters",
+                MAX_STORE_VALUE_LENGTH
+            )));
+        }
+
+        let values = &self.values;
+        let added = self.changes.keys().filter(|key| !values.contains_key(*key)).count();
+        let is_new = !values.contains_key(&key) && !self.changes.contains_key(&key);
+        if is_new && values.len() + added >= MAX_STORE_KEYS {
+            return Err(rlua::Error::RuntimeError(format!(
+                "a channel can store at most {} keys",
+                MAX_STORE_KEYS
+            )));
+        }
+
+        self.changes.insert(key, value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(script: &str) -> Result<ScriptOutput, ScriptError> {
+        run_script(RunScript {
+            script: script.to_owned(),
+            channel: "channel".to_owned(),
+            user: "user".to_owned(),
+            args: String::new(),
+            store: HashMap::new(),
+        })
+    }
+
+    #[test]
+    fn endless_loop_is_stopped() {
+        assert!(run("while true do end").is_err());
+    }
+
+    #[test]
+    fn pcall_loop_is_stopped() {
+        let started = Instant::now();
+        assert!(run("while true do pcall(function() while true do end end) end").is_err());
+        assert!(run("while true do xpcall(function() while true do end end, print) end").is_err());
+        assert!(started.elapsed() < TIME_LIMIT * 8);
+    }
+
+    #[test]
+    fn backtracking_patterns_are_refused() {
+        let started = Instant::now();
+        assert!(run(r#"string.find(string.rep("a", 5000), ".-.-.-.-b")"#).is_err());
+        assert!(run(r#"local text = string.rep("a", 400) text:gsub(".-.-.-b", "")"#).is_err());
+        assert!(run(r#"for _ in string.gmatch(string.rep("a", 400), "a?a?a?a?b") do end"#).is_err());
+        assert!(started.elapsed() < TIME_LIMIT * 4);
+
+        let output = run(r#"say(("hello there"):match("(%w+) ([%w-]+)"))"#).unwrap();
+        assert_eq!(output.messages, vec!["hello"]);
+    }
+
+    #[test]
+    fn messages_are_sent() {
+        let output = run("say('hi') reply('there')").unwrap();
+        assert_eq!(output.messages, vec!["hi", "@user there"]);
+    }
+}
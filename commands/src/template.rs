@@ -0,0 +1,283 @@
+use chrono::Local;
+use rand::{thread_rng, Rng};
+use std::collections::HashMap;
+
+/// Error found while parsing a response template
+#[derive(Debug, Fail, PartialEq)]
+pub enum TemplateError {
+    #[fail(display = "{{ at position {} is never closed", _0)]
+    Unclosed(usize),
+    #[fail(display = "}} at position {} was never opened, use }}}} for a literal }}", _0)]
+    UnexpectedClose(usize),
+    #[fail(display = "unknown variable {{{}}}", _0)]
+    UnknownVariable(String),
+    #[fail(display = "invalid range in {{{}}}, expected {{random min-max}}", _0)]
+    InvalidRange(String),
+}
+
+/// A value inserted in a response when it is sent
+#[derive(Debug, Clone, PartialEq)]
+pub enum Variable {
+    /// `{user}` user that used the command
+    User,
+    /// `{channel}` channel the command was used in
+    Channel,
+    /// `{args}` everything after the trigger
+    Args,
+    /// `{1}`, `{2}`... a single argument, starting at 1
+    Arg(usize),
+    /// `{touser}` first argument without a leading @, or the user if there is none
+    ToUser,
+    /// `{random min-max}` random number, both ends included
+    Random(i64, i64),
+    /// `{time}` current time
+    Time,
+    /// `{count}` number of times the command was used
+    Count,
+    /// `{count:name}` value of a counter of the channel
+    Counter(String),
+    /// `{points}` points of the user in the channel
+    Points,
+    /// `{match:1}` or `{match:name}` group captured by a regex command
+    Match(String),
+}
+
+impl Variable {
+    fn parse(name: &str) -> Result<Self, TemplateError> {
+        let name = name.trim();
+
+        let variable = match name {
+            "user" => Variable::User,
+            "channel" => Variable::Channel,
+            "args" => Variable::Args,
+            "touser" => Variable::ToUser,
+            "time" => Variable::Time,
+            "count" => Variable::Count,
+            "points" => Variable::Points,
+            _ if name.starts_with("count:") => Variable::Counter(name["count:".len()..].trim().to_lowercase()),
+            _ if name.starts_with("match:") => Variable::Match(name["match:".len()..].trim().to_owned()),
+            _ if name.starts_with("random ") => {
+                match parse_range(&name["random ".len()..]) {
+                    Some((min, max)) if min <= max => Variable::Random(min, max),
+                    _ => return Err(TemplateError::InvalidRange(name.to_owned())),
+                }
+            }
+            _ => match name.parse::<usize>() {
+                Ok(index) if index > 0 => Variable::Arg(index),
+                _ => return Err(TemplateError::UnknownVariable(name.to_owned())),
+            },
+        };
+
+        Ok(variable)
+    }
+}
+
+/// `min-max` where both bounds can be negative, like `-5-5`
+/// The separator is the last `-` that follows a digit.
+fn parse_range(range: &str) -> Option<(i64, i64)> {
+    let separator = range
+        .char_indices()
+        .filter(|&(index, c)| {
+            c == '-' && range[..index].trim_right().ends_with(|c: char| c.is_ascii_digit())
+        })
+        .map(|(index, _)| index)
+        .last()?;
+
+    let min = range[..separator].trim().parse().ok()?;
+    let max = range[separator + 1..].trim().parse().ok()?;
+    Some((min, max))
+}
+
+/// Random number between `min` and `max` included, for any bounds with `min <= max`
+fn random_between(min: i64, max: i64) -> i64 {
+    // Number of values after `min`, which fits in a u64 even for the whole i64 range
+    let span = max.wrapping_sub(min) as u64;
+    let offset = if span == u64::max_value() {
+        thread_rng().gen::<u64>()
+    } else {
+        thread_rng().gen_range(0, span + 1)
+    };
+    min.wrapping_add(offset as i64)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Variable(Variable),
+}
+
+/// Values available when rendering a template
+pub struct TemplateContext<'a> {
+    pub user: &'a str,
+    pub channel: &'a str,
+    pub args: &'a str,
+    pub count: i32,
+    /// Points of the user, only loaded when the template uses them
+    pub points: i32,
+    /// Counters of the channel, by name
+    pub counters: &'a HashMap<String, i32>,
+    /// Groups captured by the match expression, empty for exact commands
+    pub captures: &'a HashMap<String, String>,
+}
+
+/// A parsed command response
+/// Variables are written in braces, `{{` and `}}` are literal braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = source.char_indices().peekable();
+
+        while let Some((position, c)) = chars.next() {
+            match c {
+                '{' if chars.peek().map(|&(_, next)| next) == Some('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek().map(|&(_, next)| next) == Some('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '}')) => break,
+                            Some((_, c)) => name.push(c),
+                            None => return Err(TemplateError::Unclosed(position)),
+                        }
+                    }
+
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(text.split_off(0)));
+                    }
+                    segments.push(Segment::Variable(Variable::parse(&name)?));
+                }
+                '}' => return Err(TemplateError::UnexpectedClose(position)),
+                c => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Whether rendering needs the use count of the command
+    pub fn uses_count(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| *segment == Segment::Variable(Variable::Count))
+    }
+
+    /// Whether rendering needs the points of the user
+    pub fn uses_points(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| *segment == Segment::Variable(Variable::Points))
+    }
+
+    /// Render the response, text of a variable starting it can't run a chat command like `/ban`
+    pub fn render(&self, context: &TemplateContext) -> String {
+        let result = self.render_segments(context);
+
+        let literal_start = match self.segments.first() {
+            Some(Segment::Text(text)) => !text.trim().is_empty(),
+            _ => false,
+        };
+        if literal_start {
+            result
+        } else {
+            plain_message(&result).to_owned()
+        }
+    }
+
+    fn render_segments(&self, context: &TemplateContext) -> String {
+        let mut result = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => result.push_str(text),
+                Segment::Variable(variable) => match variable {
+                    Variable::User => result.push_str(context.user),
+                    Variable::Channel => result.push_str(context.channel),
+                    Variable::Args => result.push_str(context.args),
+                    Variable::Arg(index) => {
+                        if let Some(arg) = context.args.split_whitespace().nth(index - 1) {
+                            result.push_str(arg);
+                        }
+                    }
+                    Variable::ToUser => {
+                        let target = context
+                            .args
+                            .split_whitespace()
+                            .next()
+                            .map_or(context.user, |arg| arg.trim_left_matches('@'));
+                        result.push_str(target);
+                    }
+                    Variable::Random(min, max) => {
+                        let value = random_between(*min, *max);
+                        result.push_str(&value.to_string());
+                    }
+                    Variable::Time => result.push_str(&Local::now().format("%H:%M").to_string()),
+                    Variable::Count => result.push_str(&context.count.to_string()),
+                    Variable::Points => result.push_str(&context.points.to_string()),
+                    Variable::Counter(name) => {
+                        let value = context.counters.get(name).cloned().unwrap_or(0);
+                        result.push_str(&value.to_string());
+                    }
+                    Variable::Match(group) => {
+                        if let Some(captured) = context.captures.get(group) {
+                            result.push_str(captured);
+                        }
+                    }
+                },
+            }
+        }
+
+        result
+    }
+}
+
+/// A message without the leading `/` or `.` that would make twitch run it as a chat command
+pub fn plain_message(message: &str) -> &str {
+    message.trim_left_matches(|c: char| c == '/' || c == '.' || c.is_whitespace())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str, args: &str) -> String {
+        let (counters, captures) = (HashMap::new(), HashMap::new());
+        Template::parse(source).unwrap().render(&TemplateContext {
+            user: "viewer",
+            channel: "channel",
+            args,
+            count: 0,
+            points: 0,
+            counters: &counters,
+            captures: &captures,
+        })
+    }
+
+    #[test]
+    fn args_cannot_start_a_chat_command() {
+        assert_eq!(render("{args}", "/ban someone"), "ban someone");
+        assert_eq!(render("{1} {2}", ".timeout someone 600"), "timeout someone");
+        assert_eq!(render(" {touser}", "/ban"), "ban");
+    }
+
+    #[test]
+    fn written_chat_commands_are_kept() {
+        assert_eq!(render("/me waves at {touser}", "someone"), "/me waves at someone");
+        assert_eq!(render("hello {args}", "/ban someone"), "hello /ban someone");
+    }
+}
@@ -0,0 +1,175 @@
+use actix::prelude::*;
+use cold_data::models::{ListTimers, ReloadTimers, Timer, MIN_TIMER_INTERVAL_SECONDS};
+use cold_data::DbConnectionPool;
+use irc::client::{IrcClientWriter, SendChannelMessage};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How often due timers are checked
+const TICK_INTERVAL: Duration = Duration::from_secs(5);
+/// How often timers are loaded again, for changes made from the dashboard
+const RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+/// Shortest time between two timers of the same channel
+const CHANNEL_GAP: Duration = Duration::from_secs(60);
+/// Shortest interval a timer can have
+pub const MIN_TIMER_INTERVAL: Duration = Duration::from_secs(MIN_TIMER_INTERVAL_SECONDS as u64);
+
+/// A timer and when it is sent next
+struct Scheduled {
+    timer: Timer,
+    next_at: Instant,
+    /// Chat messages since the timer was last sent
+    messages: usize,
+}
+
+/// Actor sending the timers of every channel through the IRC writer
+/// Timers only fire once enough chat messages arrived since they were last sent.
+pub struct TimerScheduler {
+    db: Addr<DbConnectionPool>,
+    irc_writer: Addr<IrcClientWriter>,
+    timers: HashMap<(String, String), Scheduled>,
+    /// When a timer was last sent in each channel
+    last_sent: HashMap<String, Instant>,
+}
+
+impl TimerScheduler {
+    pub fn create(db: Addr<DbConnectionPool>, irc_writer: Addr<IrcClientWriter>) -> Addr<Self> {
+        Arbiter::start(move |_ctx| Self {
+            db,
+            irc_writer,
+            timers: HashMap::new(),
+            last_sent: HashMap::new(),
+        })
+    }
+
+    /// Load the timers, keeping the schedule of those that did not change
+    fn reload(&mut self, ctx: &mut Context<Self>) {
+        let load = self
+            .db
+            .send(ListTimers {})
+            .into_actor(self)
+            .map(|result, act, _ctx| match result {
+                Ok(timers) => act.set_timers(timers),
+                Err(err) => println!("Could not load timers {:?}", err),
+            })
+            .map_err(|err, _act, _ctx| println!("Could not load timers {:?}", err));
+
+        ctx.spawn(load);
+    }
+
+    fn set_timers(&mut self, timers: Vec<Timer>) {
+        let now = Instant::now();
+        let mut previous = ::std::mem::replace(&mut self.timers, HashMap::new());
+
+        // New timers of a channel are spread over their interval so they don't fire together
+        let mut per_channel: HashMap<String, u32> = HashMap::new();
+        for timer in &timers {
+            *per_channel.entry(timer.channel.clone()).or_insert(0) += 1;
+        }
+        let mut placed: HashMap<String, u32> = HashMap::new();
+
+        for timer in timers {
+            let key = (timer.channel.clone(), timer.name.clone());
+            let every = interval(&timer);
+
+            let index = placed.entry(timer.channel.clone()).or_insert(0);
+            *index += 1;
+            let count = per_channel[&timer.channel];
+
+            let scheduled = match previous.remove(&key) {
+                Some(old) if interval(&old.timer) == every => Scheduled {
+                    timer,
+                    next_at: old.next_at,
+                    messages: old.messages,
+                },
+                _ => Scheduled {
+                    timer,
+                    next_at: now + every * *index / (count + 1),
+                    messages: 0,
+                },
+            };
+
+            self.timers.insert(key, scheduled);
+        }
+    }
+
+    /// Send the timers that are due, at most one per channel
+    fn tick(&mut self) {
+        let now = Instant::now();
+        let mut sent: Vec<String> = Vec::new();
+
+        for scheduled in self.timers.values_mut() {
+            let timer = &scheduled.timer;
+            let recently_sent = self
+                .last_sent
+                .get(&timer.channel)
+                .map_or(false, |last| now.duration_since(*last) < CHANNEL_GAP);
+
+            if !timer.enabled
+                || now < scheduled.next_at
+                || scheduled.messages < timer.min_messages.max(0) as usize
+                || recently_sent
+                || sent.contains(&timer.channel)
+            {
+                continue;
+            }
+
+            self.irc_writer.do_send(SendChannelMessage {
+                channel: timer.channel.clone(),
+                message: timer.message.clone(),
+            });
+
+            scheduled.next_at = now + interval(timer);
+            scheduled.messages = 0;
+            sent.push(timer.channel.clone());
+        }
+
+        for channel in sent {
+            self.last_sent.insert(channel, now);
+        }
+    }
+}
+
+/// Interval of a timer, never shorter than `MIN_TIMER_INTERVAL`
+fn interval(timer: &Timer) -> Duration {
+    Duration::from_secs(timer.interval_seconds.max(0) as u64).max(MIN_TIMER_INTERVAL)
+}
+
+impl Actor for TimerScheduler {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.reload(ctx);
+        ctx.run_interval(TICK_INTERVAL, |act, _ctx| act.tick());
+        ctx.run_interval(RELOAD_INTERVAL, |act, ctx| act.reload(ctx));
+    }
+}
+
+/// A chat message was sent in a channel
+pub struct ChatActivity {
+    pub channel: String,
+}
+
+impl Message for ChatActivity {
+    type Result = ();
+}
+
+impl Handler<ChatActivity> for TimerScheduler {
+    type Result = ();
+
+    fn handle(&mut self, msg: ChatActivity, _ctx: &mut Self::Context) {
+        for scheduled in self.timers.values_mut() {
+            if scheduled.timer.channel == msg.channel {
+                scheduled.messages += 1;
+            }
+        }
+    }
+}
+
+impl Handler<ReloadTimers> for TimerScheduler {
+    type Result = ();
+
+    fn handle(&mut self, _msg: ReloadTimers, ctx: &mut Self::Context) {
+        self.reload(ctx);
+    }
+}
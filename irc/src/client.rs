@@ -4,19 +4,44 @@ use actix::Addr;
 use actix::Arbiter;
 use actix::Context;
 use actix::Handler;
-use regex::Regex;
+use futures::future;
+use futures::sync::oneshot;
+use futures::Future;
+use notice::{Notice, NoticeKind};
+use regex::{Captures, Regex};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::net::TcpStream;
+use std::time::{Duration, Instant};
 
 lazy_static!{
     static ref MESSAGE_CHANNEL_REGEX: Regex = { // REGEX for normal channel messages
-        Regex::new(r"^:(?P<user>.*)!.*@.*.tmi.twitch.tv PRIVMSG #(?P<channel>.*) :(?P<message>.*)\r\n$").unwrap()
+        Regex::new(r"^(?:@(?P<tags>\S*) )?:(?P<user>[^!]*)!.*@.*.tmi.twitch.tv PRIVMSG #(?P<channel>\S*) :(?P<message>.*)$").unwrap()
     };
     static ref PING_REGEX: Regex = {
         Regex::new(r"^PING :(?P<message>.*)$").unwrap()
     };
+    static ref NOTICE_REGEX: Regex = { // REGEX for server notices about a channel
+        Regex::new(r"^(?:@(?P<tags>\S*) )?:tmi.twitch.tv NOTICE #(?P<channel>\S*) :(?P<message>.*)$").unwrap()
+    };
+    static ref USER_STATE_REGEX: Regex = { // Sent after joining a channel and after every message the bot sends to it
+        Regex::new(r"^(?:@(?P<tags>\S*) )?:tmi.twitch.tv USERSTATE #(?P<channel>\S*)$").unwrap()
+    };
+    static ref MEMBERSHIP_REGEX: Regex = { // Users joining or leaving a channel, sent in batches by twitch
+        Regex::new(r"^:(?P<user>[^!]*)!\S* (?P<command>JOIN|PART) #(?P<channel>\S*)$").unwrap()
+    };
 }
 
+/// How long to wait for twitch to accept or reject a channel message
+const ACKNOWLEDGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Twitch drops a message identical to the previous one sent in the same channel within this window
+const DUPLICATE_WINDOW: Duration = Duration::from_secs(30);
+
+/// Appended to a message that would otherwise be dropped as a duplicate.
+/// The tag character is not rendered by twitch chat, so the message looks unchanged.
+const DUPLICATE_SUFFIX: &str = " \u{E0000}";
+
 #[derive(Debug, Fail)]
 pub enum IrcError {
     #[fail(display = "Connection failed: {}", 0)]
@@ -25,6 +50,12 @@ pub enum IrcError {
     ReadFailed(String),
     #[fail(display = "Could not write {}", 0)]
     WriteFailed(String),
+    #[fail(display = "Message was rejected: {:?}", 0)]
+    Rejected(NoticeKind),
+    #[fail(display = "Message was not acknowledged by the server")]
+    Unacknowledged,
+    #[fail(display = "Connection was closed by the server")]
+    ConnectionClosed,
 }
 
 /// Builds an irc client
@@ -33,6 +64,7 @@ pub struct IrcClientBuilder<'a> {
     url: &'a str,
     nickname: Option<&'a str>,
     password: Option<&'a str>,
+    avoid_duplicates: bool,
 }
 
 impl<'a> IrcClientBuilder<'a> {
@@ -42,6 +74,7 @@ impl<'a> IrcClientBuilder<'a> {
             url,
             nickname: None,
             password: None,
+            avoid_duplicates: false,
         }
     }
 
@@ -57,9 +90,16 @@ impl<'a> IrcClientBuilder<'a> {
         self
     }
 
+    /// Alter channel messages that would be identical to the previous one sent in the same channel,
+    /// so they are not silently dropped by twitch.
+    pub fn avoid_duplicates(mut self, avoid_duplicates: bool) -> Self {
+        self.avoid_duplicates = avoid_duplicates;
+        self
+    }
+
     /// Builds and connects and returns an irc client
     pub fn connect(self) -> Result<(IrcClientReader, Addr<IrcClientWriter>), IrcError> {
-        let (reader, writer) = connect(self.url)?;
+        let (reader, writer) = connect_with(self.url, self.avoid_duplicates)?;
 
         if let Some(password) = self.password {
             writer.do_send(SendLine(format!("PASS {}", password)));
@@ -69,6 +109,12 @@ impl<'a> IrcClientBuilder<'a> {
             writer.do_send(SendLine(format!("NICK {}", nickname)));
         }
 
+        // Tags and notices are needed to know whether twitch accepted the messages we send,
+        // membership to know who is in a channel
+        writer.do_send(SendLine(
+            "CAP REQ :twitch.tv/tags twitch.tv/commands twitch.tv/membership".to_owned(),
+        ));
+
         Ok((reader, writer))
     }
 }
@@ -76,23 +122,36 @@ impl<'a> IrcClientBuilder<'a> {
 /// Connect to the irc client using a url
 /// `url` must be in the form: ip:port
 pub fn connect(url: &str) -> Result<(IrcClientReader, Addr<IrcClientWriter>), IrcError> {
+    connect_with(url, false)
+}
+
+fn connect_with(
+    url: &str,
+    avoid_duplicates: bool,
+) -> Result<(IrcClientReader, Addr<IrcClientWriter>), IrcError> {
     let stream = match TcpStream::connect(url) {
         Ok(stream) => stream,
         Err(err) => return Err(IrcError::ConnectionFailed(format!("{:?}", err).to_string())),
     };
 
-    let reader = IrcClientReader {
-        reader: BufReader::new(
-            stream
-                .try_clone()
-                .map_err(|_e| IrcError::ConnectionFailed("Cloning stream".to_owned()))?,
-        ),
-    };
+    let read_stream = stream
+        .try_clone()
+        .map_err(|_e| IrcError::ConnectionFailed("Cloning stream".to_owned()))?;
 
-    let writer = Arbiter::start(|_| IrcClientWriter {
+    let writer = Arbiter::start(move |_| IrcClientWriter {
         writer: BufWriter::new(stream),
+        avoid_duplicates,
+        last_messages: HashMap::new(),
+        pending: HashMap::new(),
+        joining: HashSet::new(),
+        quit: None,
     });
 
+    let reader = IrcClientReader {
+        reader: BufReader::new(read_stream),
+        writer: writer.clone(),
+    };
+
     Ok((reader, writer))
 }
 
@@ -101,6 +160,11 @@ pub fn connect(url: &str) -> Result<(IrcClientReader, Addr<IrcClientWriter>), Ir
 pub enum IrcMessage {
     ChannelMessage(ChannelMessage),
     Ping(String),
+    Notice(Notice),
+    /// Channel name the state was sent for
+    UserState(String),
+    Join(Membership),
+    Part(Membership),
     Unknown(String),
 }
 
@@ -110,10 +174,65 @@ pub struct ChannelMessage {
     pub user: String,
     pub channel: String,
     pub message: String,
+    /// IRCv3 tags sent with the message, empty if the capability was not granted
+    pub tags: HashMap<String, String>,
+}
+
+/// A user joining or leaving a channel
+#[derive(Debug)]
+pub struct Membership {
+    pub user: String,
+    pub channel: String,
+}
+
+/// Parse an IRCv3 tag string in the form `key=value;key2=value2`
+/// Escaped values are unescaped
+pub fn parse_tags(tags: &str) -> HashMap<String, String> {
+    tags.split(';')
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| {
+            let mut parts = tag.splitn(2, '=');
+            let key = parts.next().unwrap_or_default().to_owned();
+            let value = unescape_tag_value(parts.next().unwrap_or_default());
+            (key, value)
+        })
+        .collect()
+}
+
+fn unescape_tag_value(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some(':') => result.push(';'),
+            Some('s') => result.push(' '),
+            Some('r') => result.push('\r'),
+            Some('n') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => {}
+        }
+    }
+
+    result
+}
+
+fn captured_tags(captures: &Captures) -> HashMap<String, String> {
+    captures
+        .name("tags")
+        .map(|tags| parse_tags(tags.as_str()))
+        .unwrap_or_default()
 }
 
 pub struct IrcClientReader {
     reader: BufReader<TcpStream>,
+    /// Notified of notices so it can tell senders whether their message went through
+    writer: Addr<IrcClientWriter>,
 }
 
 impl IrcClientReader {
@@ -121,37 +240,96 @@ impl IrcClientReader {
     pub fn wait_for_line(&mut self) -> Result<String, IrcError> {
         let mut line = String::new();
 
-        self.reader
+        let read = self
+            .reader
             .read_line(&mut line)
             .map_err(|e| IrcError::ReadFailed(format!("{:?}", e)))?;
 
+        if read == 0 {
+            return Err(IrcError::ConnectionClosed);
+        }
+
         Ok(line)
     }
 
     /// Get the next formatted message the irc
     pub fn next_message(&mut self) -> Result<IrcMessage, IrcError> {
         let line = self.wait_for_line()?;
+        let line = line.trim_right_matches(|c| c == '\r' || c == '\n');
 
-        if let Some(captures) = MESSAGE_CHANNEL_REGEX.captures(&line) {
+        if let Some(captures) = MESSAGE_CHANNEL_REGEX.captures(line) {
             return Ok(IrcMessage::ChannelMessage(ChannelMessage {
                 user: captures["user"].to_owned(),
                 channel: captures["channel"].to_owned(),
                 message: captures["message"].to_owned(),
+                tags: captured_tags(&captures),
             }));
         }
-        if let Some(captures) = PING_REGEX.captures(&line) {
+        if let Some(captures) = PING_REGEX.captures(line) {
             return Ok(IrcMessage::Ping(
                 captures["message"].to_owned()
             ));
         }
+        if let Some(captures) = NOTICE_REGEX.captures(line) {
+            let tags = captured_tags(&captures);
+            let notice = Notice {
+                channel: captures["channel"].to_owned(),
+                kind: NoticeKind::from_msg_id(tags.get("msg-id").map_or("", |id| id.as_str())),
+                message: captures["message"].to_owned(),
+            };
+
+            if notice.kind.is_rejection() {
+                self.writer.do_send(Acknowledge {
+                    channel: notice.channel.clone(),
+                    result: Err(IrcError::Rejected(notice.kind.clone())),
+                });
+            }
 
-        Ok(IrcMessage::Unknown(line))
+            return Ok(IrcMessage::Notice(notice));
+        }
+        if let Some(captures) = USER_STATE_REGEX.captures(line) {
+            let channel = captures["channel"].to_owned();
+
+            self.writer.do_send(Acknowledge {
+                channel: channel.clone(),
+                result: Ok(()),
+            });
+
+            return Ok(IrcMessage::UserState(channel));
+        }
+        if let Some(captures) = MEMBERSHIP_REGEX.captures(line) {
+            let membership = Membership {
+                user: captures["user"].to_owned(),
+                channel: captures["channel"].to_owned(),
+            };
+
+            return Ok(match &captures["command"] {
+                "JOIN" => IrcMessage::Join(membership),
+                _ => IrcMessage::Part(membership),
+            });
+        }
+
+        Ok(IrcMessage::Unknown(line.to_owned()))
     }
 }
 
 /// Actor that allows writing to an irc server
 pub struct IrcClientWriter {
     writer: BufWriter<TcpStream>,
+    avoid_duplicates: bool,
+    /// Last message sent to each channel and when it was sent
+    last_messages: HashMap<String, (String, Instant)>,
+    /// Channel messages waiting for twitch to accept or reject them, oldest first
+    pending: HashMap<String, VecDeque<PendingMessage>>,
+    /// Channels joined whose first USERSTATE, which answers the JOIN, was not received yet
+    joining: HashSet<String>,
+    /// Quit reason, set once a quit was requested while messages were still pending
+    quit: Option<(String, oneshot::Sender<Result<(), IrcError>>)>,
+}
+
+struct PendingMessage {
+    sent_at: Instant,
+    result: oneshot::Sender<Result<(), IrcError>>,
 }
 
 impl IrcClientWriter {
@@ -181,12 +359,91 @@ impl IrcClientWriter {
     /// Join an irc channel
     /// This will send JOIN #`channel_name`
     pub fn join(&mut self, channel_name: &str) -> Result<(), IrcError> {
-        self.send_line(&format!("JOIN #{}", channel_name))
+        self.send_line(&format!("JOIN #{}", channel_name))?;
+        self.joining.insert(channel_name.to_owned());
+        Ok(())
+    }
+
+    /// Send a message to a channel
+    /// If duplicates are avoided, a message identical to the last one sent in the channel is altered invisibly
+    pub fn send_channel_message(&mut self, channel: &str, message: &str) -> Result<(), IrcError> {
+        let message = self.deduplicate(channel, message);
+        self.send_line(&format!("PRIVMSG #{} :{}", channel, message))
+    }
+
+    /// Returns the message that should be sent to `channel` and remembers it.
+    /// Since the altered message differs from the original, repeats alternate between both forms.
+    fn deduplicate(&mut self, channel: &str, message: &str) -> String {
+        if !self.avoid_duplicates {
+            return message.to_owned();
+        }
+
+        let message = match self.last_messages.get(channel) {
+            Some((last, sent_at)) if last == message && sent_at.elapsed() < DUPLICATE_WINDOW => {
+                format!("{}{}", message, DUPLICATE_SUFFIX)
+            }
+            _ => message.to_owned(),
+        };
+
+        self.last_messages
+            .insert(channel.to_owned(), (message.clone(), Instant::now()));
+
+        message
+    }
+
+    /// Twitch answers messages in the order they were sent, so the answer is for the oldest pending message
+    /// The USERSTATE answering a JOIN is not an answer to a message.
+    fn acknowledge(&mut self, channel: &str, result: Result<(), IrcError>) {
+        if result.is_ok() && self.joining.remove(channel) {
+            return;
+        }
+
+        let pending = self
+            .pending
+            .get_mut(channel)
+            .and_then(|queue| queue.pop_front());
+
+        if let Some(pending) = pending {
+            let _ = pending.result.send(result);
+        }
+    }
+
+    /// Sends QUIT and stops the writer if a quit was requested and no message is waiting for an answer
+    fn quit_when_idle(&mut self, ctx: &mut Context<Self>) {
+        if self.pending.values().any(|queue| !queue.is_empty()) {
+            return;
+        }
+
+        if let Some((reason, result)) = self.quit.take() {
+            let _ = result.send(self.send_line(&format!("QUIT :{}", reason)));
+            ctx.stop();
+        }
+    }
+
+    /// Fail messages that twitch never answered
+    fn expire_pending(&mut self) {
+        for queue in self.pending.values_mut() {
+            while queue
+                .front()
+                .map_or(false, |pending| pending.sent_at.elapsed() >= ACKNOWLEDGE_TIMEOUT)
+            {
+                if let Some(pending) = queue.pop_front() {
+                    let _ = pending.result.send(Err(IrcError::Unacknowledged));
+                }
+            }
+        }
     }
 }
 
 impl Actor for IrcClientWriter {
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(Duration::from_secs(1), |act, ctx| {
+            act.expire_pending();
+            act.quit_when_idle(ctx);
+        });
+    }
 }
 
 /// Send a raw line to irc server
@@ -229,6 +486,7 @@ impl Handler<JoinChannel> for IrcClientWriter {
 }
 
 /// Send a message to an IRC channel
+/// Resolves once twitch accepted the message, or with the reason it was dropped
 pub struct SendChannelMessage {
     pub channel: String,
     pub message: String,
@@ -239,13 +497,120 @@ impl Message for SendChannelMessage {
 }
 
 impl Handler<SendChannelMessage> for IrcClientWriter {
-    type Result = Result<(), IrcError>;
+    type Result = ResponseFuture<(), IrcError>;
 
     fn handle(
         &mut self,
         msg: SendChannelMessage,
         _ctx: &mut Self::Context,
     ) -> <Self as Handler<SendChannelMessage>>::Result {
-        self.send_line(&format!("PRIVMSG #{} :{}", msg.channel, msg.message))
+        if let Err(err) = self.send_channel_message(&msg.channel, &msg.message) {
+            return Box::new(future::err(err));
+        }
+
+        // Chat commands like /timeout are answered by their own notices instead of USERSTATE
+        if is_chat_command(&msg.message) {
+            return Box::new(future::ok(()));
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        self.pending
+            .entry(msg.channel)
+            .or_insert_with(VecDeque::new)
+            .push_back(PendingMessage {
+                sent_at: Instant::now(),
+                result: sender,
+            });
+
+        Box::new(receiver.then(|result| match result {
+            Ok(result) => result,
+            Err(_canceled) => Err(IrcError::Unacknowledged),
+        }))
+    }
+}
+
+/// Chat commands answered by their own notices instead of USERSTATE
+/// `/me` is answered like any chat message, so it is not one of them.
+const NOTICE_COMMANDS: &[&str] = &[
+    "ban",
+    "unban",
+    "timeout",
+    "untimeout",
+    "delete",
+    "clear",
+    "slow",
+    "slowoff",
+    "followers",
+    "followersoff",
+    "subscribers",
+    "subscribersoff",
+    "emoteonly",
+    "emoteonlyoff",
+    "r9kbeta",
+    "r9kbetaoff",
+    "mod",
+    "unmod",
+    "vip",
+    "unvip",
+    "host",
+    "unhost",
+    "raid",
+    "unraid",
+    "commercial",
+    "marker",
+    "color",
+    "w",
+];
+
+/// Messages running a chat command that twitch answers with a notice, like `/timeout`
+fn is_chat_command(message: &str) -> bool {
+    if !message.starts_with('/') && !message.starts_with('.') {
+        return false;
+    }
+
+    let name = message[1..].split_whitespace().next().unwrap_or("");
+    NOTICE_COMMANDS.iter().any(|command| name.eq_ignore_ascii_case(command))
+}
+
+/// Twitch's answer to the oldest message waiting in a channel
+struct Acknowledge {
+    channel: String,
+    result: Result<(), IrcError>,
+}
+
+impl Message for Acknowledge {
+    type Result = ();
+}
+
+impl Handler<Acknowledge> for IrcClientWriter {
+    type Result = ();
+
+    fn handle(&mut self, msg: Acknowledge, ctx: &mut Self::Context) {
+        self.acknowledge(&msg.channel, msg.result);
+        self.quit_when_idle(ctx);
+    }
+}
+
+/// Leave the server with a reason and stop the writer
+/// Messages sent to the writer before this one are written first,
+/// and QUIT is only sent once twitch answered them or they timed out.
+pub struct Quit(pub String);
+
+impl Message for Quit {
+    type Result = Result<(), IrcError>;
+}
+
+impl Handler<Quit> for IrcClientWriter {
+    type Result = ResponseFuture<(), IrcError>;
+
+    fn handle(&mut self, msg: Quit, ctx: &mut Self::Context) -> <Self as Handler<Quit>>::Result {
+        let (sender, receiver) = oneshot::channel();
+        self.quit = Some((msg.0, sender));
+        self.quit_when_idle(ctx);
+
+        Box::new(receiver.then(|result| match result {
+            Ok(result) => result,
+            Err(_canceled) => Err(IrcError::ConnectionClosed),
+        }))
     }
 }
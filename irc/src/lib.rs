@@ -9,6 +9,8 @@ extern crate lazy_static;
 extern crate regex;
 
 extern crate actix;
+extern crate futures;
 
 
 pub mod client;
+pub mod notice;
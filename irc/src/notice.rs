@@ -0,0 +1,47 @@
+/// Reason given by twitch in the `msg-id` tag of a `NOTICE`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NoticeKind {
+    /// Messages are being sent too quickly
+    RateLimit,
+    /// Message was identical to the previous one sent in the last 30 seconds
+    Duplicate,
+    /// Channel is in slow mode and the bot talked too recently
+    SlowMode,
+    /// Bot is banned from the channel
+    Banned,
+    /// Channel was suspended
+    ChannelSuspended,
+    /// Any other `msg-id`, empty if the notice had none
+    Other(String),
+}
+
+impl NoticeKind {
+    pub fn from_msg_id(msg_id: &str) -> Self {
+        match msg_id {
+            "msg_ratelimit" => NoticeKind::RateLimit,
+            "msg_duplicate" => NoticeKind::Duplicate,
+            "msg_slowmode" => NoticeKind::SlowMode,
+            "msg_banned" => NoticeKind::Banned,
+            "msg_channel_suspended" => NoticeKind::ChannelSuspended,
+            other => NoticeKind::Other(other.to_owned()),
+        }
+    }
+
+    /// Whether the notice means the last message sent to the channel was dropped
+    /// Twitch prefixes every rejection reason with `msg_`, other notices answer chat commands
+    /// like `/timeout` or `/delete` and are never matched with a pending message.
+    pub fn is_rejection(&self) -> bool {
+        match self {
+            NoticeKind::Other(msg_id) => msg_id.starts_with("msg_"),
+            _ => true,
+        }
+    }
+}
+
+/// A notice sent by the server about a channel
+#[derive(Debug, Clone)]
+pub struct Notice {
+    pub channel: String,
+    pub kind: NoticeKind,
+    pub message: String,
+}
@@ -16,6 +16,23 @@ pub struct TwitchConfig {
     pub token: String,
     pub irc_server: String,
     pub channels: Vec<String>,
+    /// Invisibly alter messages that twitch would otherwise drop as duplicates
+    #[serde(default = "default_avoid_duplicates")]
+    pub avoid_duplicates: bool,
+    /// Users allowed to run owner only commands in every channel
+    #[serde(default)]
+    pub owners: Vec<String>,
+    /// Client id of a twitch application, used with `app_token` to know which channels are live
+    /// Without both, no channel is treated as live and points are not paid.
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// App access token of the same twitch application
+    #[serde(default)]
+    pub app_token: Option<String>,
+}
+
+fn default_avoid_duplicates() -> bool {
+    true
 }
 
 /// Loads a config from a file path
@@ -4,6 +4,7 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate actix;
+extern crate actix_web;
 extern crate cold_data;
 extern crate commands;
 extern crate dotenv;
@@ -12,15 +13,23 @@ extern crate toml;
 extern crate web_frontend;
 
 mod config;
-
-use actix::Addr;
-use cold_data::{cache::CommandCache, DbConnectionPool};
-use commands::CommandProcessor;
+mod shutdown;
+
+use actix::{Actor, Addr};
+use cold_data::{cache::CommandCache, DbConnectionPool, PendingWrites};
+use commands::command::CommandRegistry;
+use commands::helix::HelixCredentials;
+use commands::points::{PointsManager, ViewerJoined, ViewerLeft};
+use commands::script::ScriptRunner;
+use commands::timers::TimerScheduler;
+use commands::{CommandProcessor, Services};
 use dotenv::dotenv;
 use failure::Error;
 use irc::client::IrcClientReader;
 use irc::client::IrcClientWriter;
 use irc::client::{IrcClientBuilder, IrcMessage, SendLine};
+use shutdown::Shutdown;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
 use web_frontend::start_server;
@@ -34,23 +43,72 @@ fn main() -> Result<(), Error> {
 
     let command_cache = CommandCache::new();
 
-    let db = DbConnectionPool::connect(command_cache.clone());
+    let pending_writes = PendingWrites::new();
+
+    let db = DbConnectionPool::connect(command_cache.clone(), pending_writes.clone());
 
-    let update_server = start_server(db.clone());
+    let scripts = ScriptRunner::create();
 
     let (reader, writer) = IrcClientBuilder::create(&config.twitch.irc_server)
         .nick(&config.twitch.username)
         .pass(&config.twitch.token)
+        .avoid_duplicates(config.twitch.avoid_duplicates)
         .connect()?;
 
-    let command_processor = CommandProcessor::create(db.clone(), writer.clone(), update_server.clone(), command_cache);
+    let timers = TimerScheduler::create(db.clone(), writer.clone());
+
+    let (update_server, http_server) = start_server(
+        db.clone(),
+        scripts.clone().recipient(),
+        timers.clone().recipient(),
+    );
+
+    let helix = HelixCredentials::new(
+        config.twitch.client_id.clone(),
+        config.twitch.app_token.clone(),
+    );
+
+    let points = PointsManager::create(
+        db.clone(),
+        command_cache.clone(),
+        config.twitch.channels.clone(),
+        helix.clone(),
+    );
+
+    let command_processor = CommandProcessor::create(
+        db.clone(),
+        writer.clone(),
+        update_server.clone(),
+        command_cache,
+        config.twitch.owners.clone(),
+        CommandRegistry::with_builtins(),
+        Services {
+            timers,
+            points: points.clone(),
+            scripts,
+            helix,
+        },
+    );
+
+    let accepting = Arc::new(AtomicBool::new(true));
+
+    Shutdown {
+        accepting: accepting.clone(),
+        irc_writer: writer.clone(),
+        update_server: update_server.clone(),
+        http_server,
+        points: points.clone(),
+        pending_writes,
+    }.start();
 
     run_irc(
         reader,
         writer,
         command_processor,
+        points,
         config.clone(),
         update_server.clone(),
+        accepting,
     ).unwrap();
 
     system.run();
@@ -63,8 +121,10 @@ fn run_irc(
     mut reader: IrcClientReader,
     writer: Addr<IrcClientWriter>,
     command_processor: Addr<CommandProcessor>,
+    points: Addr<PointsManager>,
     config: Arc<config::Config>,
     update_server: Addr<UpdateServer>,
+    accepting: Arc<AtomicBool>,
 ) -> Result<(), Error> {
     // We spawn the thread after making hte irc client since IrcClientBuilder will create an actor that needs to be on the main thread
     thread::spawn(move || {
@@ -73,27 +133,48 @@ fn run_irc(
         }
 
         loop {
-            match reader
-                .next_message()
-                .expect("Message could not be received")
-            {
+            let message = match reader.next_message() {
+                Ok(message) => message,
+                Err(err) => {
+                    println!("Irc connection ended: {}", err);
+                    break;
+                }
+            };
+
+            match message {
                 IrcMessage::ChannelMessage(message) => {
                     println!("{:?}", message);
-                    if message.message.starts_with("#") {
-                        let channel = message.channel;
-                        let user = message.user;
-                        let text = message.message[1..].trim();
-
-                        command_processor.do_send(commands::MetaCommand {
-                            channel,
-                            user,
-                            message: text.to_owned(),
+                    if accepting.load(Ordering::SeqCst) {
+                        command_processor.do_send(commands::ChatMessage {
+                            channel: message.channel,
+                            user: message.user,
+                            message: message.message,
+                            tags: message.tags,
                         });
                     }
                 },
                 IrcMessage::Ping(message) => {
                     writer.do_send(SendLine(format!("PONG :{}",message)));
                 }
+                IrcMessage::Notice(notice) => {
+                    println!("{:?}", notice);
+                }
+                IrcMessage::UserState(_) => {}
+                // The bot's own membership is sent too, it does not earn points
+                IrcMessage::Join(membership) => {
+                    if !membership.user.eq_ignore_ascii_case(&config.twitch.username) {
+                        points.do_send(ViewerJoined {
+                            channel: membership.channel,
+                            user: membership.user,
+                        });
+                    }
+                }
+                IrcMessage::Part(membership) => {
+                    points.do_send(ViewerLeft {
+                        channel: membership.channel,
+                        user: membership.user,
+                    });
+                }
                 IrcMessage::Unknown(message) => {
                     println!("{}", message);
                 }
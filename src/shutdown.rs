@@ -0,0 +1,98 @@
+use actix::actors::signal;
+use actix::prelude::*;
+use actix_web::server::{Server, StopServer};
+use cold_data::PendingWrites;
+use commands::points::{FlushPoints, PointsManager};
+use futures::Future;
+use irc::client::{IrcClientWriter, Quit};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use web_frontend::ws_update::{CloseSessions, UpdateServer};
+
+/// How long to wait for in-flight database writes before exiting anyway
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Actor that shuts the bot down cleanly on SIGINT, SIGTERM or SIGQUIT
+pub struct Shutdown {
+    /// Cleared once shutdown starts, the irc thread stops forwarding commands
+    pub accepting: Arc<AtomicBool>,
+    pub irc_writer: Addr<IrcClientWriter>,
+    pub update_server: Addr<UpdateServer>,
+    pub http_server: Addr<Server>,
+    /// Holds the points earned since the last payout, saved before waiting for writes
+    pub points: Addr<PointsManager>,
+    pub pending_writes: PendingWrites,
+}
+
+impl Shutdown {
+    fn shutdown(&mut self, ctx: &mut Context<Self>) {
+        if !self.accepting.swap(false, Ordering::SeqCst) {
+            // Already shutting down
+            return;
+        }
+
+        println!("Shutting down...");
+
+        let quit = self
+            .irc_writer
+            .send(Quit("Shutting down".to_owned()))
+            .then(|_| Ok::<(), ()>(()));
+        let close_sessions = self
+            .update_server
+            .send(CloseSessions)
+            .then(|_| Ok::<(), ()>(()));
+        let stop_http = self
+            .http_server
+            .send(StopServer { graceful: true })
+            .then(|_| Ok::<(), ()>(()));
+        let flush_points = self.points.send(FlushPoints).then(|_| Ok::<(), ()>(()));
+
+        ctx.spawn(
+            quit.join4(close_sessions, stop_http, flush_points)
+                .into_actor(self)
+                .map(|_, act, ctx| act.wait_for_writes(ctx, Instant::now())),
+        );
+    }
+
+    /// Stops the system once every database write finished
+    fn wait_for_writes(&mut self, ctx: &mut Context<Self>, since: Instant) {
+        let pending = self.pending_writes.count();
+
+        if pending == 0 || since.elapsed() >= DRAIN_TIMEOUT {
+            if pending > 0 {
+                println!("Exiting with {} database writes still running", pending);
+            }
+            System::current().stop();
+            return;
+        }
+
+        ctx.run_later(Duration::from_millis(100), move |act, ctx| {
+            act.wait_for_writes(ctx, since)
+        });
+    }
+}
+
+impl Actor for Shutdown {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let signals = System::current()
+            .registry()
+            .get::<signal::ProcessSignals>();
+        signals.do_send(signal::Subscribe(ctx.address().recipient()));
+    }
+}
+
+impl Handler<signal::Signal> for Shutdown {
+    type Result = ();
+
+    fn handle(&mut self, msg: signal::Signal, ctx: &mut Self::Context) {
+        match msg.0 {
+            signal::SignalType::Int | signal::SignalType::Term | signal::SignalType::Quit => {
+                self.shutdown(ctx)
+            }
+            _ => {}
+        }
+    }
+}
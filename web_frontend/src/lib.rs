@@ -4,20 +4,31 @@ extern crate cold_data;
 extern crate env_logger;
 extern crate futures;
 extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 
 pub mod ws_update;
 
 use actix::Addr;
 use actix::Arbiter;
+use actix::Recipient;
 use actix_web::fs;
 use actix_web::http;
 use actix_web::ws;
 use actix_web::HttpResponse;
+use actix_web::server::Server;
 use actix_web::{server, App, HttpRequest};
-use actix_web::{AsyncResponder, Responder};
-use cold_data::{models::ListCommands, DbConnectionPool};
-use futures::Future;
-use ws_update::UpdateServer;
+use actix_web::{AsyncResponder, FutureResponse, Json, Path, Responder, State};
+use cold_data::models::{
+    CheckScript, EditQuote, GetQueue, ListCommands, ListQueues, ListQuotes, ListRaffleEntries, ListRaffles,
+    ListTimers, ReloadTimers, RemoveQuote, RemoveTimer, SetCommandScript, SetTimer, Timer, MAX_QUOTE_GAME_LENGTH, MAX_QUOTE_LENGTH, MAX_SCRIPT_LENGTH,
+    MAX_TIMER_MESSAGE_LENGTH, MIN_TIMER_INTERVAL_SECONDS,
+};
+use cold_data::DbConnectionPool;
+use futures::{future, Future};
+use ws_update::{Update, UpdateServer};
 
 /// Update websocket root for the front-end
 pub fn update_route(
@@ -38,19 +49,318 @@ fn commands_route(req: &HttpRequest<ApiState>) -> impl Responder {
         .responder()
 }
 
+/// Set the script of a command, an empty body removes it
+/// Scripts that don't compile are rejected with the Lua error, like in chat.
+fn script_route(
+    (path, body, state): (Path<(String, String)>, String, State<ApiState>),
+) -> FutureResponse<HttpResponse> {
+    let (channel, match_expr) = path.into_inner();
+
+    if body.len() > MAX_SCRIPT_LENGTH {
+        return Box::new(future::ok(HttpResponse::PayloadTooLarge().finish()));
+    }
+
+    let script = if body.trim().is_empty() {
+        None
+    } else {
+        Some(body)
+    };
+
+    // Removing a script needs no check
+    let check: Box<dyn Future<Item = Result<(), String>, Error = actix_web::Error>> = match script {
+        Some(ref script) => Box::new(
+            state
+                .scripts
+                .send(CheckScript {
+                    script: script.clone(),
+                })
+                .from_err()
+                .map(|result| result.map_err(|err| err.to_string())),
+        ),
+        None => Box::new(future::ok(Ok(()))),
+    };
+
+    let db = state.db.clone();
+    let update_server = state.update_server.clone();
+    check
+        .and_then(move |checked| -> FutureResponse<HttpResponse> {
+            if let Err(err) = checked {
+                return Box::new(future::ok(HttpResponse::BadRequest().body(err)));
+            }
+
+            let set_script = SetCommandScript {
+                channel,
+                match_expr,
+                script,
+            };
+            Box::new(db.send(set_script).from_err().and_then(move |result| match result {
+                Ok(0) => Ok(HttpResponse::NotFound().finish()),
+                Ok(_) => {
+                    send_commands_update(&db, update_server);
+                    Ok(HttpResponse::NoContent().finish())
+                }
+                Err(err) => Err(err.into()),
+            }))
+        })
+        .responder()
+}
+
+/// Send every command to the frontend after one changed, errors are only logged
+fn send_commands_update(db: &Addr<DbConnectionPool>, update_server: Addr<UpdateServer>) {
+    Arbiter::spawn(db.send(ListCommands {}).then(move |result| {
+        match result {
+            Ok(Ok(commands)) => {
+                let update = Update::Commands {
+                    commands: &commands,
+                };
+                match update.mass_send() {
+                    Ok(update) => update_server.do_send(update),
+                    Err(err) => println!("Could not send commands update {:?}", err),
+                }
+            }
+            Ok(Err(err)) => println!("Could not send commands update {:?}", err),
+            Err(err) => println!("Could not send commands update {:?}", err),
+        }
+        Ok(())
+    }));
+}
+
+fn timers_route(req: &HttpRequest<ApiState>) -> impl Responder {
+    req.state()
+        .db
+        .send(ListTimers {})
+        .from_err()
+        .and_then(|result| match result {
+            Ok(result) => Ok(HttpResponse::Ok().json(result)),
+            Err(err) => Err(err),
+        })
+        .responder()
+}
+
+/// Timer sent by the dashboard, the channel and name come from the path
+#[derive(Deserialize)]
+struct TimerForm {
+    message: String,
+    interval_seconds: i32,
+    #[serde(default)]
+    min_messages: i32,
+    #[serde(default = "enabled_default")]
+    enabled: bool,
+}
+
+fn enabled_default() -> bool {
+    true
+}
+
+/// Create or replace a timer, the bot's timers are reloaded right away
+fn set_timer_route(
+    (path, form, state): (Path<(String, String)>, Json<TimerForm>, State<ApiState>),
+) -> FutureResponse<HttpResponse> {
+    let (channel, name) = path.into_inner();
+    let form = form.into_inner();
+
+    if form.interval_seconds < MIN_TIMER_INTERVAL_SECONDS
+        || form.message.trim().is_empty()
+        || form.message.len() > MAX_TIMER_MESSAGE_LENGTH
+    {
+        return Box::new(futures::future::ok(HttpResponse::BadRequest().finish()));
+    }
+
+    let timers = state.timers.clone();
+    state
+        .db
+        .send(SetTimer(Timer {
+            channel,
+            name: name.to_lowercase(),
+            message: form.message,
+            interval_seconds: form.interval_seconds,
+            min_messages: form.min_messages.max(0),
+            enabled: form.enabled,
+        }))
+        .from_err()
+        .and_then(move |result| match result {
+            Ok(_) => {
+                let _ = timers.do_send(ReloadTimers);
+                Ok(HttpResponse::NoContent().finish())
+            }
+            Err(err) => Err(err),
+        })
+        .responder()
+}
+
+fn remove_timer_route(
+    (path, state): (Path<(String, String)>, State<ApiState>),
+) -> FutureResponse<HttpResponse> {
+    let (channel, name) = path.into_inner();
+    let timers = state.timers.clone();
+
+    state
+        .db
+        .send(RemoveTimer { channel, name })
+        .from_err()
+        .and_then(move |result| match result {
+            Ok(0) => Ok(HttpResponse::NotFound().finish()),
+            Ok(_) => {
+                let _ = timers.do_send(ReloadTimers);
+                Ok(HttpResponse::NoContent().finish())
+            }
+            Err(err) => Err(err),
+        })
+        .responder()
+}
+
+fn quotes_route(req: &HttpRequest<ApiState>) -> impl Responder {
+    req.state()
+        .db
+        .send(ListQuotes {})
+        .from_err()
+        .and_then(|result| match result {
+            Ok(result) => Ok(HttpResponse::Ok().json(result)),
+            Err(err) => Err(err),
+        })
+        .responder()
+}
+
+/// Quote sent by the dashboard, quotes are only added from chat
+#[derive(Deserialize)]
+struct QuoteForm {
+    quote: String,
+    #[serde(default)]
+    game: Option<String>,
+}
+
+fn edit_quote_route(
+    (path, form, state): (Path<(String, i32)>, Json<QuoteForm>, State<ApiState>),
+) -> FutureResponse<HttpResponse> {
+    let (channel, number) = path.into_inner();
+    let form = form.into_inner();
+    let game = form.game.filter(|game| !game.trim().is_empty());
+
+    if form.quote.trim().is_empty()
+        || form.quote.len() > MAX_QUOTE_LENGTH
+        || game.as_ref().map_or(false, |game| game.len() > MAX_QUOTE_GAME_LENGTH)
+    {
+        return Box::new(futures::future::ok(HttpResponse::BadRequest().finish()));
+    }
+
+    state
+        .db
+        .send(EditQuote {
+            channel,
+            number,
+            quote: form.quote,
+            game,
+        })
+        .from_err()
+        .and_then(|result| match result {
+            Ok(0) => Ok(HttpResponse::NotFound().finish()),
+            Ok(_) => Ok(HttpResponse::NoContent().finish()),
+            Err(err) => Err(err),
+        })
+        .responder()
+}
+
+fn remove_quote_route(
+    (path, state): (Path<(String, i32)>, State<ApiState>),
+) -> FutureResponse<HttpResponse> {
+    let (channel, number) = path.into_inner();
+
+    state
+        .db
+        .send(RemoveQuote { channel, number })
+        .from_err()
+        .and_then(|result| match result {
+            Ok(0) => Ok(HttpResponse::NotFound().finish()),
+            Ok(_) => Ok(HttpResponse::NoContent().finish()),
+            Err(err) => Err(err),
+        })
+        .responder()
+}
+
+/// Raffles shown in the dashboard
+const RAFFLE_HISTORY_LENGTH: i64 = 50;
+
+fn raffles_route(req: &HttpRequest<ApiState>) -> impl Responder {
+    req.state()
+        .db
+        .send(ListRaffles {
+            limit: RAFFLE_HISTORY_LENGTH,
+        })
+        .from_err()
+        .and_then(|result| match result {
+            Ok(result) => Ok(HttpResponse::Ok().json(result)),
+            Err(err) => Err(err),
+        })
+        .responder()
+}
+
+/// Entries of a raffle in order, with the saved seed they allow checking its draws
+fn raffle_entries_route(
+    (path, state): (Path<i32>, State<ApiState>),
+) -> FutureResponse<HttpResponse> {
+    state
+        .db
+        .send(ListRaffleEntries {
+            raffle_id: path.into_inner(),
+        })
+        .from_err()
+        .and_then(|result| match result {
+            Ok(result) => Ok(HttpResponse::Ok().json(result)),
+            Err(err) => Err(err),
+        })
+        .responder()
+}
+
+fn queues_route(req: &HttpRequest<ApiState>) -> impl Responder {
+    req.state()
+        .db
+        .send(ListQueues {})
+        .from_err()
+        .and_then(|result| match result {
+            Ok(result) => Ok(HttpResponse::Ok().json(result)),
+            Err(err) => Err(err),
+        })
+        .responder()
+}
+
+/// Viewer queue of a channel, shown by the read-only queue page
+fn queue_route((path, state): (Path<String>, State<ApiState>)) -> FutureResponse<HttpResponse> {
+    state
+        .db
+        .send(GetQueue {
+            channel: path.into_inner(),
+        })
+        .from_err()
+        .and_then(|result| match result {
+            Ok(result) => Ok(HttpResponse::Ok().json(result)),
+            Err(err) => Err(err),
+        })
+        .responder()
+}
+
 struct ApiState {
     db: Addr<DbConnectionPool>,
+    update_server: Addr<UpdateServer>,
+    /// Checks that scripts set from the dashboard compile
+    scripts: Recipient<CheckScript>,
+    /// Told when timers are changed from the dashboard
+    timers: Recipient<ReloadTimers>,
 }
 
 /// Start the front-end server
-pub fn start_server(db: Addr<DbConnectionPool>) -> Addr<UpdateServer> {
+/// Returns the update server and the http server, which should be stopped on shutdown
+pub fn start_server(
+    db: Addr<DbConnectionPool>,
+    scripts: Recipient<CheckScript>,
+    timers: Recipient<ReloadTimers>,
+) -> (Addr<UpdateServer>, Addr<Server>) {
     println!("Starting frontend...");
 
     let _ = env_logger::init();
 
     let update_server = Arbiter::start(|_ctx| UpdateServer::default());
 
-    {
+    let http_server = {
         let update_server = update_server.clone();
         server::new(move || {
             let ws_state = ws_update::WsUpdateSessionState::new(update_server.clone());
@@ -59,11 +369,37 @@ pub fn start_server(db: Addr<DbConnectionPool>) -> Addr<UpdateServer> {
                     .prefix("/ws")
                     .resource("/update/", |r| r.route().f(update_route))
                     .boxed(),
-                App::with_state(ApiState { db: db.clone() })
+                App::with_state(ApiState {
+                    db: db.clone(),
+                    update_server: update_server.clone(),
+                    scripts: scripts.clone(),
+                    timers: timers.clone(),
+                })
                     .prefix("/api")
                     .resource("/commands/", |r| {
                         r.method(http::Method::GET).f(commands_route)
                     })
+                    .resource("/commands/{channel}/{match_expr}/script", |r| {
+                        r.method(http::Method::PUT).with(script_route)
+                    })
+                    .resource("/timers/", |r| r.method(http::Method::GET).f(timers_route))
+                    .resource("/timers/{channel}/{name}", |r| {
+                        r.method(http::Method::PUT).with(set_timer_route);
+                        r.method(http::Method::DELETE).with(remove_timer_route)
+                    })
+                    .resource("/quotes/", |r| r.method(http::Method::GET).f(quotes_route))
+                    .resource("/quotes/{channel}/{number}", |r| {
+                        r.method(http::Method::PUT).with(edit_quote_route);
+                        r.method(http::Method::DELETE).with(remove_quote_route)
+                    })
+                    .resource("/raffles/", |r| r.method(http::Method::GET).f(raffles_route))
+                    .resource("/raffles/{id}/entries", |r| {
+                        r.method(http::Method::GET).with(raffle_entries_route)
+                    })
+                    .resource("/queues/", |r| r.method(http::Method::GET).f(queues_route))
+                    .resource("/queues/{channel}", |r| {
+                        r.method(http::Method::GET).with(queue_route)
+                    })
                     .boxed(),
                 App::new()
                     .handler(
@@ -76,7 +412,9 @@ pub fn start_server(db: Addr<DbConnectionPool>) -> Addr<UpdateServer> {
             ]
         }).bind("127.0.0.1:80")
             .unwrap()
-            .start();
-    }
-    update_server
+            // Shutdown is coordinated by the application
+            .disable_signals()
+            .start()
+    };
+    (update_server, http_server)
 }
@@ -1,8 +1,10 @@
 use actix::fut;
 use actix::prelude::*;
 use actix_web::ws;
+use cold_data::models::{Command, ViewerQueue};
 use rand;
 use rand::{Rng, ThreadRng};
+use serde_json;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::time::Instant;
@@ -11,9 +13,19 @@ use std::time::Instant;
 #[derive(Message)]
 pub struct Message(pub String);
 
+/// Sent to a session to close it cleanly
+#[derive(Message)]
+pub struct Close;
+
+/// A connected frontend client
+struct Client {
+    addr: Recipient<Message>,
+    close: Recipient<Close>,
+}
+
 /// Server responsible for updating the frontend
 pub struct UpdateServer {
-    clients: HashMap<usize, Recipient<Message>>,
+    clients: HashMap<usize, Client>,
     rng: RefCell<ThreadRng>,
 }
 
@@ -30,7 +42,7 @@ impl UpdateServer {
     /// Send message to all connected frontend clients
     pub fn send_update(&self, message: &str) {
         self.clients.iter().for_each(|(_id, client)| {
-            let _ = client.do_send(Message(message.to_owned()));
+            let _ = client.addr.do_send(Message(message.to_owned()));
         });
     }
 }
@@ -44,6 +56,7 @@ impl Actor for UpdateServer {
 #[rtype(usize)]
 pub struct Connect {
     pub addr: Recipient<Message>,
+    pub close: Recipient<Close>,
 }
 
 impl Handler<Connect> for UpdateServer {
@@ -51,7 +64,13 @@ impl Handler<Connect> for UpdateServer {
 
     fn handle(&mut self, msg: Connect, _: &mut Context<Self>) -> usize {
         let id = self.rng.borrow_mut().gen::<usize>();
-        self.clients.insert(id, msg.addr);
+        self.clients.insert(
+            id,
+            Client {
+                addr: msg.addr,
+                close: msg.close,
+            },
+        );
         println!("Connected: {}", id);
 
         id
@@ -80,6 +99,55 @@ pub struct MassSend {
     pub message: String,
 }
 
+/// Updates sent to every frontend client, as JSON tagged with their `type`
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Update<'a> {
+    /// Every stored command
+    Commands { commands: &'a [Command] },
+    /// New value of a counter, for overlays
+    Counter {
+        channel: &'a str,
+        name: &'a str,
+        value: i32,
+        /// The counter was deleted and should no longer be shown
+        removed: bool,
+    },
+    /// Tally of a running poll, sent on every vote and once more when it ends
+    Poll {
+        channel: &'a str,
+        question: &'a str,
+        options: &'a [String],
+        /// Votes of each option, in the order of `options`
+        votes: &'a [i32],
+        /// Seconds left before the poll ends
+        remaining: u64,
+        ended: bool,
+    },
+    /// State of a raffle, sent on every entry, draw and claim
+    Raffle {
+        channel: &'a str,
+        keyword: &'a str,
+        open: bool,
+        /// Number of users who entered
+        entries: usize,
+        /// Winners who responded or still can
+        winners: &'a [String],
+        ended: bool,
+    },
+    /// Viewer queue of a channel, sent whenever users join, leave or are picked
+    Queue { queue: &'a ViewerQueue },
+}
+
+impl<'a> Update<'a> {
+    /// Message sending the update to every client
+    pub fn mass_send(&self) -> Result<MassSend, serde_json::Error> {
+        Ok(MassSend {
+            message: serde_json::to_string(self)?,
+        })
+    }
+}
+
 impl Handler<MassSend> for UpdateServer {
     type Result = ();
 
@@ -89,7 +157,21 @@ impl Handler<MassSend> for UpdateServer {
         _ctx: &mut Self::Context,
     ) -> <Self as Handler<MassSend>>::Result {
         for (_, client) in &self.clients {
-            let _ = client.do_send(Message(msg.message.clone()));
+            let _ = client.addr.do_send(Message(msg.message.clone()));
+        }
+    }
+}
+
+/// Close every connected session, used when shutting down
+#[derive(Message)]
+pub struct CloseSessions;
+
+impl Handler<CloseSessions> for UpdateServer {
+    type Result = ();
+
+    fn handle(&mut self, _msg: CloseSessions, _ctx: &mut Self::Context) {
+        for (_, client) in self.clients.drain() {
+            let _ = client.close.do_send(Close);
         }
     }
 }
@@ -127,7 +209,8 @@ impl Actor for WsUpdateSession {
         ctx.state()
             .addr
             .send(Connect {
-                addr: addr.recipient(),
+                addr: addr.clone().recipient(),
+                close: addr.recipient(),
             })
             .into_actor(self)
             .then(|res, act, ctx| {
@@ -154,6 +237,15 @@ impl Handler<Message> for WsUpdateSession {
     }
 }
 
+impl Handler<Close> for WsUpdateSession {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Close, ctx: &mut Self::Context) {
+        ctx.close(Some(ws::CloseCode::Away.into()));
+        ctx.stop();
+    }
+}
+
 impl StreamHandler<ws::Message, ws::ProtocolError> for WsUpdateSession {
     fn handle(&mut self, msg: ws::Message, ctx: &mut Self::Context) {
         match msg {
@@ -8,25 +8,42 @@ class CommandsComponent extends React.Component {
         var socket = new WebSocket('ws://' + window.location.host + '/ws/update/');
 
 
-        this.state = {message_count: 0, update_socket: socket, commands: null};
+        this.state = {message_count: 0, update_socket: socket, commands: null, counters: {}, timers: [], quotes: [], polls: {}, raffles: {}, raffle_history: []};
         var object = this;
         socket.onmessage = function (event) {
             console.log(event.data);
+            let update = JSON.parse(event.data);
             object.state.message_count += 1;
-            object.setState(
-                {
-                    message_count: object.state.message_count + 1,
-                    commands: JSON.parse(event.data).map((command) =>
-                        <li key={command.match_expr} className="card blue-grey darken-1">
-                            <div className="card-content white-text">
-                                <div className="card-title">{command.channel}</div>
-                                Expr: <input onChange={object.onChange} type="text" className="" value={command.match_expr}/>
-                                Command: <input onChange={object.onChange} type="text" className="" value={command.command}/>
-                            </div>
-                        </li>)
-                }
-                );
 
+            if (update.type === "commands") {
+                object.setState(
+                    {
+                        message_count: object.state.message_count + 1,
+                        commands: update.commands.map((command) => object.renderCommand(command))
+                    }
+                    );
+            } else if (update.type === "counter") {
+                let counters = Object.assign({}, object.state.counters);
+                if (update.removed) {
+                    delete counters[update.channel + " " + update.name];
+                } else {
+                    counters[update.channel + " " + update.name] = update;
+                }
+                object.setState({counters: counters});
+            } else if (update.type === "poll") {
+                let polls = Object.assign({}, object.state.polls);
+                polls[update.channel] = update;
+                object.setState({polls: polls});
+            } else if (update.type === "raffle") {
+                let raffles = Object.assign({}, object.state.raffles);
+                if (update.ended) {
+                    delete raffles[update.channel];
+                    object.loadRaffles();
+                } else {
+                    raffles[update.channel] = update;
+                }
+                object.setState({raffles: raffles});
+            }
         };
         this.onChange = this.handleChange.bind(this);
     }
@@ -35,6 +52,112 @@ class CommandsComponent extends React.Component {
         console.log(event);
     }
 
+    // An empty script removes it, the command then sends its response again
+    saveScript(command, script) {
+        fetch("/api/commands/" + encodeURIComponent(command.channel) + "/" + encodeURIComponent(command.match_expr) + "/script", {
+            method: "PUT",
+            body: script
+        })
+        .then(result => {
+            if (result.status === 400) {
+                result.text().then(error => alert("Script was not saved, " + error));
+            } else if (!result.ok) {
+                console.log("Script was not saved", result.status);
+            }
+        });
+    }
+
+    loadTimers() {
+        fetch("/api/timers/")
+        .then(result => result.json())
+        .then(timers => this.setState({timers: timers}));
+    }
+
+    timerUrl(timer) {
+        return "/api/timers/" + encodeURIComponent(timer.channel) + "/" + encodeURIComponent(timer.name);
+    }
+
+    // The bot sends the changed timers once it loads them again, within a minute
+    toggleTimer(timer) {
+        fetch(this.timerUrl(timer), {
+            method: "PUT",
+            headers: {"Content-Type": "application/json"},
+            body: JSON.stringify(Object.assign({}, timer, {enabled: !timer.enabled}))
+        })
+        .then(() => this.loadTimers());
+    }
+
+    removeTimer(timer) {
+        fetch(this.timerUrl(timer), {method: "DELETE"})
+        .then(() => this.loadTimers());
+    }
+
+    loadQuotes() {
+        fetch("/api/quotes/")
+        .then(result => result.json())
+        .then(quotes => this.setState({quotes: quotes}));
+    }
+
+    quoteUrl(quote) {
+        return "/api/quotes/" + encodeURIComponent(quote.channel) + "/" + quote.number;
+    }
+
+    saveQuote(quote, text) {
+        fetch(this.quoteUrl(quote), {
+            method: "PUT",
+            headers: {"Content-Type": "application/json"},
+            body: JSON.stringify({quote: text, game: quote.game})
+        })
+        .then(() => this.loadQuotes());
+    }
+
+    removeQuote(quote) {
+        fetch(this.quoteUrl(quote), {method: "DELETE"})
+        .then(() => this.loadQuotes());
+    }
+
+    loadRaffles() {
+        fetch("/api/raffles/")
+        .then(result => result.json())
+        .then(raffles => this.setState({raffle_history: raffles}));
+    }
+
+    renderRaffle(raffle) {
+        let winners = raffle.winners.map((winner) => winner.user + (winner.claimed ? "" : " (no response)"));
+        return (
+            <li key={raffle.id} className="collection-item">
+                {raffle.channel} {raffle.keyword} opened by {raffle.opened_by} at {raffle.opened_at}: {raffle.entries} entries,
+                winners: {winners.join(", ") || "none"}
+                <a className="btn-flat" href={"/api/raffles/" + raffle.id + "/entries"}>Entries</a>
+                <span className="grey-text">seed {raffle.seed}</span>
+            </li>);
+    }
+
+    renderQuote(quote) {
+        let text = quote.quote;
+        return (
+            <li key={quote.channel + " " + quote.number} className="collection-item">
+                {quote.channel} #{quote.number} by {quote.added_by} ({quote.game || "no game"}, {quote.added_at}):
+                <input type="text" defaultValue={text} onChange={(event) => text = event.target.value}/>
+                <button className="btn-flat" onClick={() => this.saveQuote(quote, text)}>Save</button>
+                <button className="btn-flat" onClick={() => this.removeQuote(quote)}>Remove</button>
+            </li>);
+    }
+
+    renderCommand(command) {
+        let script = command.script || "";
+        return (
+            <li key={command.channel + command.match_expr} className="card blue-grey darken-1">
+                <div className="card-content white-text">
+                    <div className="card-title">{command.channel}</div>
+                    Expr: <input onChange={this.onChange} type="text" className="" value={command.match_expr}/>
+                    Command: <input onChange={this.onChange} type="text" className="" value={command.command}/>
+                    Script: <textarea className="materialize-textarea" defaultValue={script} onChange={(event) => script = event.target.value}/>
+                    <button className="btn" onClick={() => this.saveScript(command, script)}>Save script</button>
+                </div>
+            </li>);
+    }
+
     componentDidMount() {
         console.log("TEST");
         fetch("http://127.0.0.1/api/commands/")
@@ -42,17 +165,13 @@ class CommandsComponent extends React.Component {
             return result.json();
         })
             .then(data => {
-                let commands = data.map((command) =>
-                    <li key={command.match_expr} className = "card blue-grey darken-1">
-                        <div className="card-content white-text">
-                            <div className="card-title">{command.channel}</div>
-                            Expr: <input onChange={this.onChange}type="text" className="" value={command.match_expr} />
-                            Command: <input onChange={this.onChange} type="text" className="" value={command.command} />
-                        </div>
-                   </li>);
+                let commands = data.map((command) => this.renderCommand(command));
 
                 this.setState({commands: commands});
         });
+        this.loadTimers();
+        this.loadQuotes();
+        this.loadRaffles();
     }
 
 
@@ -60,6 +179,49 @@ class CommandsComponent extends React.Component {
         return (
             <div>
                 <div>{this.state.message_count}</div>
+                <ul className="collection">
+                    {Object.keys(this.state.counters).map((key) => {
+                        let counter = this.state.counters[key];
+                        return <li key={key} className="collection-item">{counter.channel} {counter.name}: {counter.value}</li>;
+                    })}
+                </ul>
+                <ul className="collection">
+                    {this.state.timers.map((timer) =>
+                        <li key={timer.channel + " " + timer.name} className="collection-item">
+                            {timer.channel} {timer.name} every {timer.interval_seconds}s: {timer.message}
+                            <button className="btn-flat" onClick={() => this.toggleTimer(timer)}>{timer.enabled ? "Pause" : "Resume"}</button>
+                            <button className="btn-flat" onClick={() => this.removeTimer(timer)}>Remove</button>
+                        </li>
+                    )}
+                </ul>
+                <ul className="collection">
+                    {Object.keys(this.state.polls).map((channel) => {
+                        let poll = this.state.polls[channel];
+                        let total = poll.votes.reduce((sum, votes) => sum + votes, 0);
+                        return (
+                            <li key={channel} className="collection-item">
+                                {channel} {poll.question} {poll.ended ? "(ended)" : "(" + poll.remaining + "s left)"}
+                                {poll.options.map((option, index) =>
+                                    <div key={index}>
+                                        {option}: {poll.votes[index]}
+                                        <div className="progress"><div className="determinate" style={{width: (total ? poll.votes[index] * 100 / total : 0) + "%"}}></div></div>
+                                    </div>
+                                )}
+                            </li>);
+                    })}
+                </ul>
+                <ul className="collection">
+                    {Object.keys(this.state.raffles).map((channel) => {
+                        let raffle = this.state.raffles[channel];
+                        return (
+                            <li key={channel} className="collection-item">
+                                {channel} raffle {raffle.keyword} ({raffle.open ? "open" : "closed"}): {raffle.entries} entries,
+                                winners: {raffle.winners.join(", ") || "none"}
+                            </li>);
+                    })}
+                </ul>
+                <ul className="collection">{this.state.raffle_history.map((raffle) => this.renderRaffle(raffle))}</ul>
+                <ul className="collection">{this.state.quotes.map((quote) => this.renderQuote(quote))}</ul>
                 <ul className="container">{this.state.commands}</ul>
             </div>
         );
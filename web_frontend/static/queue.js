@@ -0,0 +1,67 @@
+'use strict'
+const e = React.createElement;
+
+// Read-only viewer queues, queue.html?channel=name shows a single channel
+class QueueComponent extends React.Component {
+    constructor(props) {
+        super(props);
+
+        let channel = new URLSearchParams(window.location.search).get("channel");
+        var socket = new WebSocket('ws://' + window.location.host + '/ws/update/');
+
+        this.state = {channel: channel ? channel.replace(/^#/, "").toLowerCase() : null, update_socket: socket, queues: {}};
+        var object = this;
+        socket.onmessage = function (event) {
+            let update = JSON.parse(event.data);
+
+            if (update.type === "queue" && object.shows(update.queue.channel)) {
+                let queues = Object.assign({}, object.state.queues);
+                queues[update.queue.channel] = update.queue;
+                object.setState({queues: queues});
+            }
+        };
+    }
+
+    shows(channel) {
+        return this.state.channel === null || this.state.channel === channel;
+    }
+
+    componentDidMount() {
+        let url = this.state.channel === null ? "/api/queues/" : "/api/queues/" + encodeURIComponent(this.state.channel);
+        fetch(url)
+        .then(result => result.json())
+        .then(data => {
+            let queues = {};
+            (Array.isArray(data) ? data : [data]).forEach((queue) => queues[queue.channel] = queue);
+            this.setState({queues: queues});
+        });
+    }
+
+    renderQueue(queue) {
+        return (
+            <div key={queue.channel} className="card blue-grey darken-1">
+                <div className="card-content white-text">
+                    <div className="card-title">
+                        {queue.channel} queue ({queue.open ? "open" : "closed"}{queue.sub_priority ? ", subscribers first" : ""})
+                    </div>
+                    <ol>
+                        {queue.entries.map((entry) =>
+                            <li key={entry.id}>{entry.user}{entry.subscriber ? " (sub)" : ""}</li>
+                        )}
+                    </ol>
+                    {queue.entries.length === 0 ? "Nobody is waiting" : ""}
+                </div>
+            </div>);
+    }
+
+    render() {
+        return (
+            <div className="container">
+                {Object.keys(this.state.queues).map((channel) => this.renderQueue(this.state.queues[channel]))}
+            </div>
+        );
+    }
+}
+
+const domContainer = document.querySelector('#queue_container');
+ReactDOM.render(e(QueueComponent), domContainer);